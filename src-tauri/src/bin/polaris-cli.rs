@@ -0,0 +1,381 @@
+//! Headless front end to the waveform and project code of the editor, for
+//! machines without a display such as CI servers.
+//!
+//! Exits with 0 on success, 1 when `compare` finds differences, `golden`
+//! unexpected outputs or `validate` problems, and 2 when the command itself
+//! fails.
+
+use polaris_lib::golden::{check_expected_files, check_outputs};
+use polaris_lib::instruction_trace::{instruction_trace, trace_to_csv, vars_of_kind};
+use polaris_lib::sapho_float::FloatFormat;
+use polaris_lib::sapho_names::SignalKind;
+use polaris_lib::signal_export::{
+    sample_signals, write_table, ExportFormat, ExportSignal, Sampling, SignalExportRequest,
+};
+use polaris_lib::spf::SpfProject;
+use polaris_lib::translation::{check_project, load_symbol_tables, var_paths};
+use polaris_lib::vcd_writer::export_vcd;
+use polaris_lib::waveform::WaveformSession;
+use polaris_lib::waveform_diff::{compare_waveforms, DiffOptions, SignalMapping};
+use serde::Serialize;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
+
+const USAGE: &str = "\
+Usage: polaris-cli <command> [options]
+
+Commands:
+  convert <input> <output.vcd>
+      Convert a VCD, FST or GHW file to VCD
+  hierarchy <waveform>
+      Print the scopes and vars of a waveform as JSON
+  compare <a> <b> [--map <path-a>=<path-b>]... [--tolerance <x>]
+          [--relative-tolerance <x>] [--float-format <mantissa>,<exponent>]
+          [--spf <project.spf>] [--limit <n>]
+      Print the differences between two runs as JSON
+  export <waveform> <output.csv|output.json|output.parquet> --signal <path>[=<radix>]...
+          [--clock-edge rising|falling] [--clock <path>] [--from <t>] [--to <t>]
+          [--float-format <mantissa>,<exponent>] [--cycles]
+      Write signals sampled on every change, or on the edges of a clock
+  trace <waveform> [--signal <valr2 path>] [--spf <project.spf>] [--json]
+      Print the instructions a processor executed, as CSV by default
+  golden <waveform> <project.spf>
+      Check the outputs of a simulation against the expected values the
+      project declares for its processor
+  validate <project.spf> [--waveform <file>]
+      Check the tables a project declares, and its value labels against the
+      signals of a waveform when given";
+
+/// Positional arguments and `--name value` options of a command line.
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+/// Options that take no value.
+const FLAGS: &[&str] = &["json", "cycles"];
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args {
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if FLAGS.contains(&name) => {
+                    parsed.options.push((name.to_string(), None));
+                }
+                Some(name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--{} needs a value", name))?;
+                    parsed.options.push((name.to_string(), Some(value)));
+                }
+                None => parsed.positional.push(arg),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The positional arguments, which must be exactly `names`.
+    fn positional<const N: usize>(&self, names: [&str; N]) -> Result<[&str; N], String> {
+        let values: Vec<&str> = self.positional.iter().map(String::as_str).collect();
+        values
+            .try_into()
+            .map_err(|_| format!("expected {}", names.join(" ")))
+    }
+
+    /// Reject options the command does not know.
+    fn allow(&self, names: &[&str]) -> Result<(), String> {
+        match self
+            .options
+            .iter()
+            .find(|(name, _)| !names.contains(&name.as_str()))
+        {
+            Some((name, _)) => Err(format!("unknown option --{}", name)),
+            None => Ok(()),
+        }
+    }
+
+    fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.options
+            .iter()
+            .filter(move |(option, _)| option == name)
+            .filter_map(|(_, value)| value.as_deref())
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.value(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("--{} expects a number, found {}", name, value))
+            })
+            .transpose()
+    }
+
+    fn float_format(&self) -> Result<Option<FloatFormat>, String> {
+        self.value("float-format")
+            .map(|format| {
+                let (mantissa, exponent) = format
+                    .split_once(',')
+                    .and_then(|(m, e)| Some((m.parse().ok()?, e.parse().ok()?)))
+                    .ok_or_else(|| {
+                        format!(
+                            "--float-format expects <mantissa>,<exponent>, found {}",
+                            format
+                        )
+                    })?;
+                FloatFormat::new(mantissa, exponent)
+            })
+            .transpose()
+    }
+}
+
+/// Signal of the var at `path`.
+fn signal_ref(session: &WaveformSession, path: &str) -> Result<usize, String> {
+    let hierarchy = session.waveform.hierarchy();
+    hierarchy
+        .all_vars()
+        .map(|var_ref| &hierarchy[var_ref])
+        .find(|var| var.full_name(hierarchy) == path)
+        .map(|var| var.signal_ref().index())
+        .ok_or_else(|| format!("{} is not a signal of the waveform", path))
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize output: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn convert(args: &Args) -> Result<ExitCode, String> {
+    args.allow(&[])?;
+    let [src, dst] = args.positional(["<input>", "<output.vcd>"])?;
+    export_vcd(src, dst, &AtomicBool::new(false), |_| {})?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn hierarchy(args: &Args) -> Result<ExitCode, String> {
+    args.allow(&[])?;
+    let [path] = args.positional(["<waveform>"])?;
+    print_json(&WaveformSession::open(path)?.hierarchy(0))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn compare(args: &Args) -> Result<ExitCode, String> {
+    args.allow(&[
+        "map",
+        "tolerance",
+        "relative-tolerance",
+        "float-format",
+        "spf",
+        "limit",
+    ])?;
+    let [path_a, path_b] = args.positional(["<a>", "<b>"])?;
+    let signal_map = args
+        .values("map")
+        .map(|mapping| {
+            let (a, b) = mapping
+                .split_once('=')
+                .ok_or_else(|| format!("--map expects <path-a>=<path-b>, found {}", mapping))?;
+            Ok(SignalMapping {
+                a: a.to_string(),
+                b: b.to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let options = DiffOptions {
+        tolerance: args.number("tolerance")?.unwrap_or(0.0),
+        relative_tolerance: args.number("relative-tolerance")?.unwrap_or(0.0),
+        float_format: args.float_format()?,
+        limit: args.number("limit")?,
+    };
+
+    let mut session_a = WaveformSession::open(path_a)?;
+    let mut session_b = WaveformSession::open(path_b)?;
+    if let Some(spf_path) = args.value("spf") {
+        let project = SpfProject::load(spf_path)?;
+        session_a.symbols = load_symbol_tables(&session_a, Some((spf_path, &project)));
+        session_b.symbols = load_symbol_tables(&session_b, Some((spf_path, &project)));
+    }
+    let diff = compare_waveforms(&mut session_a, &mut session_b, &signal_map, &options)?;
+    print_json(&diff)?;
+    let differs = diff.signals.iter().any(|signal| signal.mismatches > 0)
+        || !diff.only_in_a.is_empty()
+        || !diff.only_in_b.is_empty();
+    Ok(if differs {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn export(args: &Args) -> Result<ExitCode, String> {
+    args.allow(&[
+        "signal",
+        "clock-edge",
+        "clock",
+        "from",
+        "to",
+        "float-format",
+        "cycles",
+    ])?;
+    let [path, dst] = args.positional(["<waveform>", "<output>"])?;
+    let mut session = WaveformSession::open(path)?;
+    let signals = args
+        .values("signal")
+        .map(|signal| {
+            let (path, radix) = match signal.split_once('=') {
+                Some((path, radix)) => (path, Some(radix.to_string())),
+                None => (signal, None),
+            };
+            Ok(ExportSignal {
+                signal_ref: signal_ref(&session, path)?,
+                radix,
+                float_format: None,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if signals.is_empty() {
+        return Err("export needs at least one --signal".to_string());
+    }
+    let sampling = match args.value("clock-edge") {
+        None => Sampling::Changes,
+        Some("rising") => Sampling::RisingEdge,
+        Some("falling") => Sampling::FallingEdge,
+        Some(other) => {
+            return Err(format!(
+                "--clock-edge expects rising or falling, found {}",
+                other
+            ))
+        }
+    };
+    let request = SignalExportRequest {
+        signals,
+        sampling,
+        clock_ref: args
+            .value("clock")
+            .map(|clock| signal_ref(&session, clock))
+            .transpose()?,
+        from: args.number("from")?,
+        to: args.number("to")?,
+        float_format: args.float_format()?,
+        cycles: args.flag("cycles"),
+    };
+
+    let table = sample_signals(&mut session, &request)?;
+    let contents = write_table(&table, ExportFormat::from_path(dst))?;
+    std::fs::write(dst, contents).map_err(|e| format!("Failed to write {}: {}", dst, e))?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn trace(args: &Args) -> Result<ExitCode, String> {
+    args.allow(&["signal", "spf", "json"])?;
+    let [path] = args.positional(["<waveform>"])?;
+    let mut session = WaveformSession::open(path)?;
+    if let Some(spf_path) = args.value("spf") {
+        let project = SpfProject::load(spf_path)?;
+        session.symbols = load_symbol_tables(&session, Some((spf_path, &project)));
+    }
+    let instruction_ref = match args.value("signal") {
+        Some(signal) => Some(
+            vars_of_kind(&session, SignalKind::Instruction)
+                .into_iter()
+                .find(|(path, _)| path == signal)
+                .map(|(_, index)| index)
+                .ok_or_else(|| format!("Failed to build trace: {} is not valr2", signal))?,
+        ),
+        None => None,
+    };
+
+    let trace = instruction_trace(&mut session, instruction_ref)?;
+    let tables = session.symbols_for(&trace.instruction_path);
+    for error in &tables.errors {
+        eprintln!("warning: {}:{}: {}", error.file, error.line, error.message);
+    }
+    for warning in &tables.warnings {
+        eprintln!("warning: {}: {}", warning.file, warning.message);
+    }
+    if args.flag("json") {
+        print_json(&trace)?;
+    } else {
+        print!("{}", trace_to_csv(&trace.rows));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn golden(args: &Args) -> Result<ExitCode, String> {
+    args.allow(&[])?;
+    let [path, spf_path] = args.positional(["<waveform>", "<project.spf>"])?;
+    let project = SpfProject::load(spf_path)?;
+    let mut session = WaveformSession::open(path)?;
+    session.symbols = load_symbol_tables(&session, Some((spf_path, &project)));
+
+    let check = check_outputs(&mut session, spf_path, &project)?;
+    print_json(&check)?;
+    Ok(if check.passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn validate(args: &Args) -> Result<ExitCode, String> {
+    args.allow(&["waveform"])?;
+    let [spf_path] = args.positional(["<project.spf>"])?;
+    let project = SpfProject::load(spf_path)?;
+    let paths = args
+        .value("waveform")
+        .map(|path| WaveformSession::open(path).map(|session| var_paths(&session)))
+        .transpose()?;
+
+    let mut errors = check_project(spf_path, &project, paths.as_deref());
+    errors.extend(check_expected_files(spf_path, &project));
+    for error in &errors {
+        println!("{}:{}: {}", error.file, error.line, error.message);
+    }
+    Ok(if errors.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+    let result = Args::parse(args).and_then(|args| match command.as_deref() {
+        Some("convert") => convert(&args),
+        Some("hierarchy") => hierarchy(&args),
+        Some("compare") => compare(&args),
+        Some("export") => export(&args),
+        Some("trace") => trace(&args),
+        Some("golden") => golden(&args),
+        Some("validate") => validate(&args),
+        Some("help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(command) => Err(format!("unknown command {}\n\n{}", command, USAGE)),
+        None => Err(USAGE.to_string()),
+    });
+    result.unwrap_or_else(|error| {
+        eprintln!("polaris-cli: {}", error);
+        ExitCode::from(2)
+    })
+}
@@ -0,0 +1,182 @@
+use crate::sapho_names::{classify, SignalKind};
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "gui")]
+use tauri::State;
+use wellen::SignalValueRef;
+
+/// Fewest rising edges a signal needs before it can be called a clock.
+const MIN_EDGES: usize = 4;
+
+/// Share of periods that must be identical, so a clock that is gated for a
+/// few cycles (or stopped during reset) is still recognized.
+const MIN_REGULARITY: f64 = 0.9;
+
+/// A periodic single-bit signal. Cycle 0 starts at `first_edge`, the first
+/// rising edge, and every cycle lasts `period` time units.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockInfo {
+    pub signal_ref: usize,
+    pub path: String,
+    pub period: u64,
+    pub first_edge: u64,
+    pub last_edge: u64,
+    pub high_time: u64,
+}
+
+impl ClockInfo {
+    /// Cycle in progress at `time`; negative before the first edge.
+    pub fn cycle_at(&self, time: u64) -> i64 {
+        (time as i64 - self.first_edge as i64).div_euclid(self.period as i64)
+    }
+
+    /// Start of a cycle, None when it would come before time 0.
+    pub fn time_of_cycle(&self, cycle: i64) -> Option<u64> {
+        u64::try_from(self.first_edge as i64 + cycle * self.period as i64).ok()
+    }
+}
+
+fn level(value: &SignalValueRef) -> Option<bool> {
+    match value.to_bit_string()?.as_str() {
+        "0" | "l" | "L" => Some(false),
+        "1" | "h" | "H" => Some(true),
+        _ => None,
+    }
+}
+
+/// Check whether a signal is a clock: enough rising edges, nearly all the
+/// same distance apart.
+pub fn detect_clock(
+    session: &mut WaveformSession,
+    index: usize,
+    path: &str,
+) -> Result<Option<ClockInfo>, String> {
+    let signal_ref = session.ensure_loaded(index)?;
+    let mut rising = Vec::new();
+    let mut falling = Vec::new();
+    let mut previous = None;
+    for (time, value) in session.changes_since(signal_ref, 0)? {
+        let now = level(&value);
+        match (previous, now) {
+            (Some(false), Some(true)) => rising.push(time),
+            (Some(true), Some(false)) => falling.push(time),
+            _ => {}
+        }
+        previous = now;
+    }
+    if rising.len() < MIN_EDGES {
+        return Ok(None);
+    }
+
+    let mut periods: HashMap<u64, usize> = HashMap::new();
+    for pair in rising.windows(2) {
+        *periods.entry(pair[1] - pair[0]).or_default() += 1;
+    }
+    let (period, count) = periods
+        .into_iter()
+        .max_by_key(|&(period, count)| (count, std::cmp::Reverse(period)))
+        .unwrap();
+    if (count as f64) < MIN_REGULARITY * (rising.len() - 1) as f64 {
+        return Ok(None);
+    }
+
+    let first_edge = rising[0];
+    let high_time = falling
+        .iter()
+        .find(|&&t| t > first_edge)
+        .map_or(0, |&t| t - first_edge);
+    Ok(Some(ClockInfo {
+        signal_ref: index,
+        path: path.to_string(),
+        period,
+        first_edge,
+        last_edge: *rising.last().unwrap(),
+        high_time,
+    }))
+}
+
+/// Clocks among `candidates`, loading them only for the scan.
+fn scan_clocks(
+    session: &mut WaveformSession,
+    candidates: Vec<(usize, String)>,
+) -> Result<Vec<ClockInfo>, String> {
+    let indices: Vec<usize> = candidates.iter().map(|(index, _)| *index).collect();
+    let signal_refs = session.signal_refs(&indices)?;
+    let unloaded: Vec<usize> = indices
+        .iter()
+        .zip(&signal_refs)
+        .filter(|(_, signal_ref)| session.waveform.get_signal(**signal_ref).is_none())
+        .map(|(index, _)| *index)
+        .collect();
+    session.load_signals(&unloaded)?;
+
+    let clocks = candidates
+        .iter()
+        .map(|(index, path)| detect_clock(session, *index, path))
+        .collect::<Result<Vec<_>, _>>();
+    session.unload_signals(&unloaded)?;
+    Ok(clocks?.into_iter().flatten().collect())
+}
+
+/// Every clock among the single-bit vars of a session, those with the
+/// most cycles first. Aliases of one signal are reported once. Vars named
+/// as clocks are checked first and the others only if none of them is
+/// one. The result is kept on the session, so only the first call loads
+/// any signal.
+pub fn detect_clocks(session: &mut WaveformSession) -> Result<Vec<ClockInfo>, String> {
+    if let Some(clocks) = &session.clocks {
+        return Ok(clocks.clone());
+    }
+
+    let hierarchy = session.waveform.hierarchy();
+    let mut seen = HashSet::new();
+    let mut named = Vec::new();
+    let mut others = Vec::new();
+    for var in hierarchy
+        .all_vars()
+        .map(|var_ref| &hierarchy[var_ref])
+        .filter(|var| var.length(hierarchy) == Some(1))
+        .filter(|var| seen.insert(var.signal_ref()))
+    {
+        let path = var.full_name(hierarchy);
+        let kind = classify(var.name(hierarchy), &path).kind;
+        let candidate = (var.signal_ref().index(), path);
+        if kind == SignalKind::Clock {
+            named.push(candidate);
+        } else {
+            others.push(candidate);
+        }
+    }
+
+    let mut clocks = scan_clocks(session, named)?;
+    if clocks.is_empty() {
+        clocks = scan_clocks(session, others)?;
+    }
+    clocks.sort_by_key(|clock| {
+        (
+            std::cmp::Reverse((clock.last_edge - clock.first_edge) / clock.period),
+            clock.path.clone(),
+        )
+    });
+    session.clocks = Some(clocks.clone());
+    Ok(clocks)
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Periodic clock signals of a waveform, the most active one first
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn detect_waveform_clocks(
+    session_id: u32,
+    state: State<'_, WaveformManager>,
+) -> Result<Vec<ClockInfo>, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || detect_clocks(&mut session.lock().unwrap()))
+        .await
+        .map_err(|e| format!("Failed to detect clocks: {}", e))?
+}
@@ -151,10 +151,7 @@ pub fn delete_item(path: &str) -> io::Result<()> {
     } else if path_obj.is_dir() {
         fs::remove_dir_all(path)
     } else {
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "Unknown file type"
-        ))
+        Err(io::Error::other("Unknown file type"))
     }
 }
 
@@ -248,7 +245,7 @@ pub fn generate_processor_structure(spf_path: &str, config: &ProcessorConfig) ->
     
     // Parse JSON
     let mut spf_data: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        .map_err(io::Error::other)?;
 
     // Create processor object
     let processor_info = json!({
@@ -286,7 +283,7 @@ pub fn generate_processor_structure(spf_path: &str, config: &ProcessorConfig) ->
 
     // Save updated file
     let new_content = serde_json::to_string_pretty(&spf_data)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        .map_err(io::Error::other)?;
         
     let mut file = fs::File::create(spf_path)?;
     file.write_all(new_content.as_bytes())?;
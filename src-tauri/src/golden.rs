@@ -0,0 +1,407 @@
+use crate::clock::{detect_clocks, ClockInfo};
+use crate::instruction_trace::{sibling_var, signed_changes, signed_value_at, vars_of_kind};
+use crate::sapho_float::{FloatFormat, SaphoFloat};
+use crate::sapho_names::{classify, owning_processor, processor_scopes, SignalKind};
+use crate::spf::{OutputFormat, SpfProcessor, SpfProject};
+use crate::translation::{parse_number, read_table, signed_value, var_paths, TableError};
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::{SignalChanges, WaveformSession};
+use serde::Serialize;
+use std::path::Path;
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// Relative difference up to which a float output still matches.
+const FLOAT_TOLERANCE: f64 = 1e-6;
+
+/// One line of an expected output file, read in the format declared for
+/// its port.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExpectedValue {
+    Int(i64),
+    Float(f64),
+}
+
+/// First output that differs from the expected sequence. Outputs missing
+/// at the end of the simulation have no `actual` value nor time.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputMismatch {
+    /// Position in the output sequence, from 0
+    pub index: usize,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    pub time: Option<u64>,
+    pub cycle: Option<i64>,
+    /// C+- line `linetabs` pointed at when the value was written
+    pub source_line: Option<i64>,
+    pub source_text: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortCheck {
+    /// Processor of the project that declares the port
+    pub processor: String,
+    pub port: u32,
+    pub output_path: Option<String>,
+    pub expected_file: String,
+    pub expected_count: usize,
+    pub actual_count: usize,
+    pub first_mismatch: Option<OutputMismatch>,
+    /// Unreadable lines of the expected file, or a missing output signal
+    pub errors: Vec<TableError>,
+}
+
+/// Output streams of a simulation against the sequences the project
+/// expects from each of its processors.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoldenCheck {
+    /// Processors whose ports were checked, in the order of `ports`
+    pub processors: Vec<String>,
+    pub clock: Option<ClockInfo>,
+    pub ports: Vec<PortCheck>,
+    pub passed: bool,
+}
+
+/// Values of an expected output file with their line numbers, every line
+/// read in `format`. Blank lines are skipped.
+pub fn parse_expected(
+    text: &str,
+    file: &str,
+    format: OutputFormat,
+) -> (Vec<(usize, ExpectedValue)>, Vec<TableError>) {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let value = match format {
+            OutputFormat::Int => parse_number(line).map(ExpectedValue::Int),
+            OutputFormat::Float => line
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(ExpectedValue::Float),
+        };
+        match value {
+            Some(value) => values.push((i + 1, value)),
+            None => errors.push(TableError::new(
+                file,
+                i + 1,
+                format!(
+                    "expected {}, found {:?}",
+                    match format {
+                        OutputFormat::Int => "an integer",
+                        OutputFormat::Float => "a number",
+                    },
+                    line
+                ),
+            )),
+        }
+    }
+    (values, errors)
+}
+
+/// Expected values of one port and the problems reading them: an
+/// unreadable file or line, or float values without the processor's float
+/// format.
+fn read_expected(
+    path: &Path,
+    format: OutputFormat,
+    processor: &SpfProcessor,
+) -> (Vec<(usize, ExpectedValue)>, Vec<TableError>) {
+    let file = path.to_string_lossy();
+    let (values, mut errors) = match read_table(path) {
+        Ok(text) => parse_expected(&text, &file, format),
+        Err(error) => (Vec::new(), vec![error]),
+    };
+    if format == OutputFormat::Float && processor.float_format().is_none() {
+        errors.push(TableError::new(
+            &file,
+            0,
+            format!(
+                "float outputs need the mantissa and exponent bits of {}",
+                processor.name
+            ),
+        ));
+    }
+    (values, errors)
+}
+
+/// Problems in the expected output files every processor of a project
+/// declares, without loading a waveform.
+pub fn check_expected_files(spf_path: &str, project: &SpfProject) -> Vec<TableError> {
+    let project_dir = Path::new(spf_path).parent().unwrap_or(Path::new(""));
+    project
+        .processors
+        .iter()
+        .flat_map(|processor| {
+            processor.expected_outputs.iter().flat_map(|expected| {
+                read_expected(
+                    &project_dir.join(&expected.file),
+                    expected.format,
+                    processor,
+                )
+                .1
+            })
+        })
+        .collect()
+}
+
+/// Value in effect at `time`, None before the first change.
+fn value_at(changes: &SignalChanges, time: u64) -> Option<&str> {
+    let pos = changes
+        .times
+        .partition_point(|&t| t <= time)
+        .checked_sub(1)?;
+    Some(&changes.values[pos])
+}
+
+/// `(time, bits)` of every value written to a port: one per clock cycle in
+/// which `out_en_sim_N` is high, or one per strobe without a clock.
+fn output_samples(
+    session: &mut WaveformSession,
+    enable_ref: usize,
+    output_ref: usize,
+    clock: Option<&ClockInfo>,
+) -> Result<Vec<(u64, String)>, String> {
+    let end = session.end_time();
+    let enable = session.signal_changes(enable_ref)?;
+    let output = session.signal_changes(output_ref)?;
+
+    let mut samples = Vec::new();
+    for (i, value) in enable.values.iter().enumerate() {
+        if value != "1" {
+            continue;
+        }
+        let start = enable.times[i];
+        let stop = enable.times.get(i + 1).copied().unwrap_or(end);
+        let count = clock.map_or(1, |clock| (stop - start).div_ceil(clock.period).max(1));
+        for k in 0..count {
+            let time = start + k * clock.map_or(0, |clock| clock.period);
+            let bits = value_at(&output, time).unwrap_or_default();
+            samples.push((time, bits.to_string()));
+        }
+    }
+    Ok(samples)
+}
+
+/// Number an output stands for: a float in the processor's format when
+/// `as_float`, otherwise an integer.
+fn actual_number(bits: &str, as_float: bool, format: Option<FloatFormat>) -> Option<f64> {
+    match format.filter(|_| as_float) {
+        Some(format) => {
+            let bits = &bits[bits.len().saturating_sub(format.width())..];
+            Some(SaphoFloat::decode(bits, format).ok()?.to_f64())
+        }
+        _ => signed_value(bits).map(|value| value as f64),
+    }
+}
+
+fn matches(bits: &str, expected: ExpectedValue, format: Option<FloatFormat>) -> bool {
+    match expected {
+        ExpectedValue::Int(value) => signed_value(bits) == Some(value),
+        ExpectedValue::Float(value) => actual_number(bits, true, format).is_some_and(|actual| {
+            actual == value
+                || (actual - value).abs() <= FLOAT_TOLERANCE * actual.abs().max(value.abs())
+        }),
+    }
+}
+
+fn describe(bits: &str, as_float: bool, format: Option<FloatFormat>) -> String {
+    actual_number(bits, as_float, format).map_or_else(|| bits.to_string(), |n| n.to_string())
+}
+
+/// `out_sig_N` of a port and the `out_en_sim_N` next to it, among the
+/// signals of processor `owner` when the waveform has several.
+fn port_vars(
+    session: &WaveformSession,
+    scopes: &[(String, String)],
+    owner: Option<&str>,
+    port: u32,
+) -> Option<(String, usize, usize)> {
+    let of_port = |kind| {
+        vars_of_kind(session, kind)
+            .into_iter()
+            .filter(move |(path, _)| {
+                let name = path.rsplit('.').next().unwrap_or(path);
+                classify(name, path).port == Some(port)
+                    && (owner.is_none() || owning_processor(scopes, path) == owner)
+            })
+    };
+    of_port(SignalKind::Output).find_map(|(path, output_ref)| {
+        let scope = path.rsplit_once('.').map_or("", |(scope, _)| scope);
+        let (_, enable_ref) = of_port(SignalKind::OutputEnable)
+            .find(|(enable, _)| enable.rsplit_once('.').map(|(s, _)| s) == Some(scope))?;
+        Some((path, output_ref, enable_ref))
+    })
+}
+
+/// Compare the values written to every port the processors of a project
+/// declare expected outputs for, reporting the first mismatch of each.
+/// Each processor of the waveform is checked against its own signals.
+pub fn check_outputs(
+    session: &mut WaveformSession,
+    spf_path: &str,
+    project: &SpfProject,
+) -> Result<GoldenCheck, String> {
+    let scopes = processor_scopes(&var_paths(session));
+    let mut names: Vec<Option<String>> = Vec::new();
+    for (_, name) in &scopes {
+        if !names.iter().flatten().any(|known| known == name) {
+            names.push(Some(name.clone()));
+        }
+    }
+    if names.is_empty() {
+        names.push(None);
+    }
+
+    // Processor of the project each instance of the waveform stands for
+    let mut checked: Vec<(Option<String>, &SpfProcessor)> = Vec::new();
+    for name in names {
+        if let Some(processor) = project.processor(name.as_deref()) {
+            if !checked
+                .iter()
+                .any(|(_, known)| known.name == processor.name)
+            {
+                checked.push((name, processor));
+            }
+        }
+    }
+    if checked.is_empty() {
+        return Err(
+            "Failed to check outputs: no processor of the project matches the waveform".to_string(),
+        );
+    }
+    let matched: Vec<&str> = checked
+        .iter()
+        .map(|(_, processor)| processor.name.as_str())
+        .collect();
+    checked.retain(|(_, processor)| !processor.expected_outputs.is_empty());
+    if checked.is_empty() {
+        return Err(format!(
+            "Failed to check outputs: {} declares no expected outputs",
+            matched.join(", ")
+        ));
+    }
+
+    let clock = detect_clocks(session)?.into_iter().next();
+    let project_dir = Path::new(spf_path).parent().unwrap_or(Path::new(""));
+
+    let mut ports = Vec::new();
+    for (name, processor) in &checked {
+        let owner = name.as_deref();
+        let format = processor.float_format();
+        let source_lines = session.processor_symbols(owner).source_lines;
+        for declared in &processor.expected_outputs {
+            let file = project_dir
+                .join(&declared.file)
+                .to_string_lossy()
+                .to_string();
+            let (expected, mut errors) =
+                read_expected(Path::new(&file), declared.format, processor);
+            let Some((output_path, output_ref, enable_ref)) =
+                port_vars(session, &scopes, owner, declared.port)
+            else {
+                errors.push(TableError::new(
+                    &file,
+                    0,
+                    format!("no out_sig_{} signal in the waveform", declared.port),
+                ));
+                ports.push(PortCheck {
+                    processor: processor.name.clone(),
+                    port: declared.port,
+                    output_path: None,
+                    expected_file: file,
+                    expected_count: expected.len(),
+                    actual_count: 0,
+                    first_mismatch: None,
+                    errors,
+                });
+                continue;
+            };
+
+            let samples = output_samples(session, enable_ref, output_ref, clock.as_ref())?;
+            let linetabs = match sibling_var(session, &output_path, SignalKind::SourceLine) {
+                Some((_, index)) => signed_changes(session, index)?,
+                None => Vec::new(),
+            };
+            let first_mismatch = (0..expected.len().max(samples.len()))
+                .find(|&i| match (expected.get(i), samples.get(i)) {
+                    (Some(&(_, value)), Some((_, bits))) => !matches(bits, value, format),
+                    _ => true,
+                })
+                .map(|index| {
+                    let expected = expected.get(index).map(|&(_, value)| value);
+                    let sample = samples.get(index);
+                    let time = sample.map(|(time, _)| *time);
+                    let source_line = time.and_then(|time| signed_value_at(&linetabs, time));
+                    OutputMismatch {
+                        index,
+                        expected: expected.map(|value| match value {
+                            ExpectedValue::Int(value) => value.to_string(),
+                            ExpectedValue::Float(value) => value.to_string(),
+                        }),
+                        actual: sample.map(|(_, bits)| {
+                            describe(bits, declared.format == OutputFormat::Float, format)
+                        }),
+                        time,
+                        cycle: time
+                            .zip(clock.as_ref())
+                            .map(|(time, clock)| clock.cycle_at(time)),
+                        source_line,
+                        source_text: source_line.and_then(|line| source_lines.get(&line).cloned()),
+                    }
+                });
+
+            ports.push(PortCheck {
+                processor: processor.name.clone(),
+                port: declared.port,
+                output_path: Some(output_path),
+                expected_file: file,
+                expected_count: expected.len(),
+                actual_count: samples.len(),
+                first_mismatch,
+                errors,
+            });
+        }
+    }
+
+    let passed = ports
+        .iter()
+        .all(|port| port.first_mismatch.is_none() && port.errors.is_empty());
+    Ok(GoldenCheck {
+        processors: checked
+            .iter()
+            .map(|(_, processor)| processor.name.clone())
+            .collect(),
+        clock,
+        ports,
+        passed,
+    })
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Check the outputs of a simulation against the expected sequences of its
+/// processor in a project file
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn check_expected_outputs(
+    session_id: u32,
+    spf_path: String,
+    state: State<'_, WaveformManager>,
+) -> Result<GoldenCheck, String> {
+    let session = state.get(session_id)?;
+    let project = SpfProject::load(&spf_path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        check_outputs(&mut session.lock().unwrap(), &spf_path, &project)
+    })
+    .await
+    .map_err(|e| format!("Failed to check outputs: {}", e))?
+}
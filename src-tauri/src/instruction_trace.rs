@@ -0,0 +1,239 @@
+use crate::clock::{detect_clocks, ClockInfo};
+use crate::sapho_names::{classify, owning_processor, processor_scopes, SignalKind};
+use crate::translation::{signed_value, var_paths};
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use serde::Serialize;
+use std::fmt::Write as _;
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// One fetched instruction: a change of `valr2`, lasting until the next.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceRow {
+    pub step: usize,
+    /// Clock cycle of the fetch, when the waveform has a clock
+    pub cycle: Option<i64>,
+    pub time: u64,
+    /// Instruction address, None while `valr2` has unknown bits
+    pub pc: Option<i64>,
+    pub mnemonic: Option<String>,
+    pub operand: Option<String>,
+    /// Clock cycles until the next instruction; the last one runs to the
+    /// end of the waveform
+    pub cycles: Option<u64>,
+    /// Data stack pointer in effect at the fetch
+    pub stack_pointer: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionTrace {
+    pub instruction_path: String,
+    pub stack_pointer_path: Option<String>,
+    pub clock: Option<ClockInfo>,
+    pub rows: Vec<TraceRow>,
+}
+
+/// Path and signal of every var of one kind, in hierarchy order.
+pub fn vars_of_kind(session: &WaveformSession, kind: SignalKind) -> Vec<(String, usize)> {
+    let hierarchy = session.waveform.hierarchy();
+    hierarchy
+        .all_vars()
+        .map(|var_ref| &hierarchy[var_ref])
+        .filter_map(|var| {
+            let path = var.full_name(hierarchy);
+            (classify(var.name(hierarchy), &path).kind == kind)
+                .then(|| (path, var.signal_ref().index()))
+        })
+        .collect()
+}
+
+/// Var of `kind` in the same processor as `path`: the one sharing the
+/// longest scope prefix with it among the vars of its processor. None when
+/// that processor has no such var.
+pub fn sibling_var(
+    session: &WaveformSession,
+    path: &str,
+    kind: SignalKind,
+) -> Option<(String, usize)> {
+    let processors = processor_scopes(&var_paths(session));
+    let owner = owning_processor(&processors, path);
+    let scopes: Vec<&str> = path.split('.').collect();
+    vars_of_kind(session, kind)
+        .into_iter()
+        .filter(|(other, _)| owning_processor(&processors, other) == owner)
+        .map(|(other, index)| {
+            let shared = other
+                .split('.')
+                .zip(&scopes)
+                .take_while(|(a, b)| a == *b)
+                .count();
+            (shared, other, index)
+        })
+        .filter(|(shared, _, _)| *shared > 0)
+        .max_by_key(|(shared, other, _)| (*shared, std::cmp::Reverse(other.len())))
+        .map(|(_, other, index)| (other, index))
+}
+
+/// `(time, value)` of every change of a signal, read as two's complement.
+pub(crate) fn signed_changes(
+    session: &mut WaveformSession,
+    index: usize,
+) -> Result<Vec<(u64, Option<i64>)>, String> {
+    let changes = session.signal_changes(index)?;
+    Ok(changes
+        .times
+        .into_iter()
+        .zip(changes.values.iter().map(|bits| signed_value(bits)))
+        .collect())
+}
+
+/// Value in effect at `time` among `signed_changes`.
+pub(crate) fn signed_value_at(changes: &[(u64, Option<i64>)], time: u64) -> Option<i64> {
+    let pos = changes
+        .partition_point(|(t, _)| *t <= time)
+        .checked_sub(1)?;
+    changes[pos].1
+}
+
+/// Rebuild the instructions a processor executed from its `valr2` signal
+/// (the first one in the waveform when `instruction_ref` is None) and the
+/// opcode table next to the waveform.
+pub fn instruction_trace(
+    session: &mut WaveformSession,
+    instruction_ref: Option<usize>,
+) -> Result<InstructionTrace, String> {
+    let instructions = vars_of_kind(session, SignalKind::Instruction);
+    let (instruction_path, instruction_ref) = match instruction_ref {
+        Some(index) => instructions
+            .into_iter()
+            .find(|(_, signal_ref)| *signal_ref == index)
+            .ok_or_else(|| format!("Failed to build trace: signal {} is not valr2", index))?,
+        None => instructions
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Failed to build trace: no valr2 signal found".to_string())?,
+    };
+    let stack_pointer = sibling_var(session, &instruction_path, SignalKind::DataStackPointer);
+
+    let tables = session.symbols_for(&instruction_path);
+    let clock = detect_clocks(session)?.into_iter().next();
+    let end = session.end_time();
+
+    let pcs = signed_changes(session, instruction_ref)?;
+    let stack = match &stack_pointer {
+        Some((_, index)) => signed_changes(session, *index)?,
+        None => Vec::new(),
+    };
+
+    let mut rows = Vec::with_capacity(pcs.len());
+    for (step, &(time, pc)) in pcs.iter().enumerate() {
+        let next = pcs.get(step + 1).map_or(end, |(next, _)| *next);
+        let instruction = pc.and_then(|pc| tables.opcodes.get(&pc));
+        rows.push(TraceRow {
+            step,
+            cycle: clock.as_ref().map(|clock| clock.cycle_at(time)),
+            time,
+            pc,
+            mnemonic: instruction.map(|i| i.mnemonic.clone()),
+            operand: instruction.and_then(|i| i.operand.clone()),
+            cycles: clock.as_ref().map(|clock| (next - time) / clock.period),
+            stack_pointer: signed_value_at(&stack, time),
+        });
+    }
+
+    Ok(InstructionTrace {
+        instruction_path,
+        stack_pointer_path: stack_pointer.map(|(path, _)| path),
+        clock,
+        rows,
+    })
+}
+
+/// Whether a row matches every word of `query`. A word matches the address,
+/// the mnemonic or the operand exactly, ignoring case, so `LOD` does not
+/// find `P_LOD` but `OUT 2` finds writes to port 2.
+pub fn row_matches(row: &TraceRow, query: &str) -> bool {
+    query.split_whitespace().all(|word| {
+        row.pc.is_some_and(|pc| word.parse() == Ok(pc))
+            || row
+                .mnemonic
+                .as_deref()
+                .is_some_and(|m| m.eq_ignore_ascii_case(word))
+            || row
+                .operand
+                .as_deref()
+                .is_some_and(|o| o.eq_ignore_ascii_case(word))
+    })
+}
+
+/// CSV with a header line, for spreadsheets and scripts.
+pub fn trace_to_csv(rows: &[TraceRow]) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let mut csv = String::from("step,cycle,time,pc,mnemonic,operand,cycles,stack_pointer\n");
+    for row in rows {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{}",
+            row.step,
+            optional(row.cycle.map(|c| c.to_string())),
+            row.time,
+            optional(row.pc.map(|pc| pc.to_string())),
+            optional(row.mnemonic.clone()),
+            optional(
+                row.operand
+                    .as_ref()
+                    .map(|o| format!("\"{}\"", o.replace('"', "\"\"")))
+            ),
+            optional(row.cycles.map(|c| c.to_string())),
+            optional(row.stack_pointer.map(|sp| sp.to_string())),
+        );
+    }
+    csv
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Execution trace of a processor, optionally only the rows matching a
+/// search such as `OUT` or `SET cont`
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_instruction_trace(
+    session_id: u32,
+    signal_ref: Option<usize>,
+    query: Option<String>,
+    state: State<'_, WaveformManager>,
+) -> Result<InstructionTrace, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut trace = instruction_trace(&mut session.lock().unwrap(), signal_ref)?;
+        if let Some(query) = query.filter(|query| !query.trim().is_empty()) {
+            trace.rows.retain(|row| row_matches(row, &query));
+        }
+        Ok(trace)
+    })
+    .await
+    .map_err(|e| format!("Failed to build trace: {}", e))?
+}
+
+/// Write the execution trace of a processor to a CSV file
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn export_instruction_trace(
+    session_id: u32,
+    signal_ref: Option<usize>,
+    dst: String,
+    state: State<'_, WaveformManager>,
+) -> Result<(), String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let trace = instruction_trace(&mut session.lock().unwrap(), signal_ref)?;
+        std::fs::write(&dst, trace_to_csv(&trace.rows))
+            .map_err(|e| format!("Failed to write {}: {}", dst, e))
+    })
+    .await
+    .map_err(|e| format!("Failed to export trace: {}", e))?
+}
@@ -1,244 +1,44 @@
+pub mod clock;
+#[cfg(feature = "gui")]
 mod commands;
+#[cfg(feature = "gui")]
 mod file_system;
+pub mod golden;
+pub mod instruction_trace;
+pub mod live_reload;
+pub mod opcode_mix;
+pub mod sapho_float;
+pub mod sapho_names;
+pub mod signal_expr;
+pub mod signal_export;
+pub mod signal_stats;
+pub mod source_profile;
+pub mod spf;
+pub mod stack_analysis;
+#[cfg(feature = "gui")]
 mod terminal;
-
+pub mod translation;
+pub mod trigger;
+pub mod vcd_writer;
+pub mod waveform;
+mod waveform_codec;
+pub mod waveform_diff;
+pub mod waveform_search;
+
+#[cfg(feature = "gui")]
 use commands::*;
+#[cfg(feature = "gui")]
 use terminal::TerminalManager; // Importante para o novo sistema de terminal
+#[cfg(feature = "gui")]
+use vcd_writer::ExportManager;
+#[cfg(feature = "gui")]
+use waveform::WaveformManager;
+#[cfg(feature = "gui")]
 use tauri::Manager;
+#[cfg(feature = "gui")]
 use std::time::Duration;
 
-use std::io::Write;
-use std::collections::{HashMap, HashSet};
-use wellen::{ScopeRef, VarRef};
-
-// Função para gerar IDs padrão VCD (exatamente iguais ao do GTKWave: '!', '"', '#', etc)
-fn index_to_vcd_id(mut index: usize) -> String {
-    let mut id = String::new();
-    loop {
-        id.push(((index % 94) as u8 + 33) as char);
-        index /= 94;
-        if index == 0 { break; }
-    }
-    id
-}
-
-// Função recursiva usando os iteradores diretos de pasta
-fn write_scope(
-    hierarchy: &wellen::Hierarchy,
-    scope_ref: ScopeRef,
-    vcd_output: &mut Vec<u8>,
-    all_vars: &mut Vec<VarRef>
-) {
-    let scope = &hierarchy[scope_ref];
-    let name = scope.name(hierarchy);
-    
-    writeln!(vcd_output, "$scope module {} $end", name).unwrap();
-    
-    for var in scope.vars(hierarchy) {
-        let var_ref = var.clone();
-        let var_obj = &hierarchy[var_ref];
-        let mut var_name = var_obj.name(hierarchy).replace(" ", "_");
-        if var_name.is_empty() { var_name = format!("unnamed_{}", var_ref.index()); }
-        
-        let size = var_obj.length(hierarchy).unwrap_or(1);
-        let sig_ref = var_obj.signal_ref();
-        let id_str = index_to_vcd_id(sig_ref.index()); 
-
-        let vcd_type = match var_obj.var_type() {
-            wellen::VarType::Real => "real",
-            wellen::VarType::String => "string",
-            wellen::VarType::Integer => "integer",
-            _ => "wire",
-        };
-        
-        writeln!(vcd_output, "$var {} {} {} {} $end", vcd_type, size, id_str, var_name).unwrap();
-        all_vars.push(var_ref);
-    }
-    
-    for child in scope.scopes(hierarchy) {
-        write_scope(hierarchy, child.clone(), vcd_output, all_vars);
-    }
-    
-    writeln!(vcd_output, "$upscope $end").unwrap();
-}
-
-#[tauri::command]
-async fn read_fst_as_vcd(path: String) -> Result<String, String> {
-    let mut wave = wellen::simple::read(&path)
-        .map_err(|e| format!("Falha ao ler o arquivo binário: {:?}", e))?;
-
-    let mut vcd_output = Vec::new();
-
-    writeln!(vcd_output, "$date\n   Polaris Session\n$end").unwrap();
-    writeln!(vcd_output, "$version\n   Polaris Native Wellen Converter\n$end").unwrap();
-    
-    let mut all_vars = Vec::new();
-    
-    // =========================================================================
-    // BLOCO 1: LER A HIERARQUIA
-    // =========================================================================
-    {
-        let hierarchy = wave.hierarchy();
-        
-        let timescale_unit = match hierarchy.timescale() {
-            Some(ts) => format!("{}{:?}", ts.factor, ts.unit).to_lowercase(),
-            None => "1ns".to_string(),
-        };
-        writeln!(vcd_output, "$timescale\n\t{}\n$end", timescale_unit).unwrap();
-        
-        // Raiz
-        for var in hierarchy.vars() {
-            let var_ref = var.clone();
-            let var_obj = &hierarchy[var_ref];
-            let mut var_name = var_obj.name(hierarchy).replace(" ", "_");
-            if var_name.is_empty() { var_name = format!("unnamed_{}", var_ref.index()); }
-            
-            let size = var_obj.length(hierarchy).unwrap_or(1);
-            let sig_ref = var_obj.signal_ref();
-            let id_str = index_to_vcd_id(sig_ref.index());
-
-            let vcd_type = match var_obj.var_type() {
-                wellen::VarType::Real => "real",
-                wellen::VarType::String => "string",
-                _ => "wire",
-            };
-            
-            writeln!(vcd_output, "$var {} {} {} {} $end", vcd_type, size, id_str, var_name).unwrap();
-            all_vars.push(var_ref);
-        }
-        
-        // Pastas Principais
-        for scope_ref in hierarchy.scopes() {
-            write_scope(&hierarchy, scope_ref, &mut vcd_output, &mut all_vars);
-        }
-        
-        writeln!(vcd_output, "$enddefinitions $end").unwrap();
-    }
-
-    // =========================================================================
-    // BLOCO 2: EXTRAÇÃO FÍSICA DOS SINAIS
-    // =========================================================================
-    let all_sigs: Vec<wellen::SignalRef> = {
-        let hierarchy = wave.hierarchy();
-        let mut set = HashSet::new();
-        for v in &all_vars {
-            set.insert(hierarchy[*v].signal_ref());
-        }
-        set.into_iter().collect()
-    };
-    wave.load_signals(&all_sigs);
-
-    // =========================================================================
-    // BLOCO 3: PREENCHER A LINHA DO TEMPO
-    // =========================================================================
-    let hierarchy = wave.hierarchy();
-    let time_table = wave.time_table();
-    let mut last_state: HashMap<usize, String> = HashMap::new();
-    
-    writeln!(vcd_output, "#{}", time_table.first().unwrap_or(&0)).unwrap();
-    writeln!(vcd_output, "$dumpvars").unwrap();
-    
-    let mut dumped_in_this_step = HashSet::new();
-    
-    // --- INICIALIZAÇÃO (TEMPO 0) CORRIGIDA ---
-    for var_ref in &all_vars {
-        let var = &hierarchy[*var_ref];
-        let sig_ref = var.signal_ref();
-        
-        if !dumped_in_this_step.insert(sig_ref.index()) { continue; }
-        
-        let size = var.length(&hierarchy).unwrap_or(1);
-        let id_str = index_to_vcd_id(sig_ref.index());
-        let mut current_value = "x".repeat(size as usize);
-        
-        let is_real = var.var_type() == wellen::VarType::Real;
-        let is_string = var.var_type() == wellen::VarType::String;
-        
-        if let Some(signal) = wave.get_signal(sig_ref) {
-            if let Some(offset) = signal.get_offset(0) {
-                let value_ref = signal.get_value_at(&offset, 0);
-                // Aplica a regra de extrair texto ou bits também no tempo 0!
-                current_value = if let Some(bit_str) = value_ref.to_bit_string() {
-                    bit_str
-                } else {
-                    format!("{}", value_ref) 
-                };
-            }
-        }
-        
-        // Escreve com os prefixos r, s ou b
-        if is_real {
-            writeln!(vcd_output, "r{} {}", current_value, id_str).unwrap();
-        } else if is_string {
-            writeln!(vcd_output, "s{} {}", current_value, id_str).unwrap();
-        } else if size == 1 {
-            writeln!(vcd_output, "{}{}", current_value, id_str).unwrap();
-        } else {
-            writeln!(vcd_output, "b{} {}", current_value, id_str).unwrap();
-        }
-        
-        last_state.insert(sig_ref.index(), current_value);
-    }
-    writeln!(vcd_output, "$end").unwrap();
-
-    // --- MUDANÇAS DE ESTADO (TEMPO > 0) CORRIGIDA ---
-    for (time_index, &time_value) in time_table.iter().enumerate() {
-        if time_index == 0 { continue; }
-        
-        let mut dumped_in_this_step = HashSet::new();
-        let mut step_changes = Vec::new(); 
-        
-        for var_ref in &all_vars {
-            let var = &hierarchy[*var_ref];
-            let sig_ref = var.signal_ref();
-            
-            if !dumped_in_this_step.insert(sig_ref.index()) { continue; }
-            
-            let size = var.length(&hierarchy).unwrap_or(1);
-            let id_str = index_to_vcd_id(sig_ref.index());
-            
-            if let Some(signal) = wave.get_signal(sig_ref) {
-                if let Some(offset) = signal.get_offset(time_index as u32) {
-                    let value_ref = signal.get_value_at(&offset, 0);
-                    
-                    let is_real = var.var_type() == wellen::VarType::Real;
-                    let is_string = var.var_type() == wellen::VarType::String;
-                    
-                    let val = if let Some(bit_str) = value_ref.to_bit_string() {
-                        bit_str
-                    } else {
-                        format!("{}", value_ref) 
-                    };
-                    
-                    let changed = last_state.get(&sig_ref.index()) != Some(&val);
-                    if changed {
-                        if is_real {
-                            writeln!(step_changes, "r{} {}", val, id_str).unwrap();
-                        } else if is_string {
-                            writeln!(step_changes, "s{} {}", val, id_str).unwrap();
-                        } else if size == 1 {
-                            writeln!(step_changes, "{}{}", val, id_str).unwrap();
-                        } else {
-                            writeln!(step_changes, "b{} {}", val, id_str).unwrap();
-                        }
-                        last_state.insert(sig_ref.index(), val);
-                    }
-                }
-            }
-        }
-        
-        if !step_changes.is_empty() {
-            writeln!(vcd_output, "#{}", time_value).unwrap();
-            vcd_output.write_all(&step_changes).unwrap();
-        }
-    }
-
-    let vcd_string = String::from_utf8(vcd_output)
-        .map_err(|e| format!("Erro na formatação de saída de texto do VCD: {}", e))?;
-
-    Ok(vcd_string)
-}
-
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let mut builder = tauri::Builder::default();
@@ -258,7 +58,10 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init());
 
     // --- 2. Gerenciamento de Estado ---
-    builder = builder.manage(TerminalManager::new());
+    builder = builder
+        .manage(TerminalManager::new())
+        .manage(WaveformManager::new())
+        .manage(ExportManager::new());
 
     // --- 3. Registro de Comandos ---
     builder = builder.invoke_handler(tauri::generate_handler![
@@ -273,7 +76,38 @@ pub fn run() {
         create_project_structure,
         generate_processor,
         execute_command, 
-        read_fst_as_vcd,
+        waveform::open_waveform,
+        waveform::get_waveform_hierarchy,
+        waveform::get_waveform_hierarchy_binary,
+        waveform::load_waveform_signals,
+        waveform::load_signal_changes,
+        waveform::load_signal_changes_binary,
+        waveform::query_signal_window,
+        waveform::unload_waveform_signals,
+        waveform::close_waveform,
+        live_reload::watch_waveform,
+        live_reload::unwatch_waveform,
+        waveform_diff::compare_waveform_files,
+        golden::check_expected_outputs,
+        signal_export::export_signals,
+        clock::detect_waveform_clocks,
+        waveform_search::find_signal_transition,
+        waveform_search::find_signal_edge,
+        waveform_search::find_signal_value,
+        instruction_trace::get_instruction_trace,
+        instruction_trace::export_instruction_trace,
+        opcode_mix::get_instruction_mix,
+        sapho_float::decode_sapho_values,
+        sapho_float::encode_sapho_float,
+        signal_expr::evaluate_derived_signal,
+        signal_stats::compute_signal_stats,
+        source_profile::profile_source_lines,
+        stack_analysis::analyze_stack_usage,
+        translation::load_waveform_symbols,
+        translation::attach_signal_labels,
+        trigger::find_trigger_matches,
+        vcd_writer::export_waveform_as_vcd,
+        vcd_writer::cancel_waveform_export,
         terminal::get_platform,
         terminal::get_shell_path,
         terminal::get_current_directory,
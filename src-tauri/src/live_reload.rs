@@ -0,0 +1,526 @@
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::{value_to_string, WaveformSession};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+#[cfg(feature = "gui")]
+use std::time::Duration;
+#[cfg(feature = "gui")]
+use tauri::{AppHandle, Emitter, State};
+use wellen::simple::Waveform;
+use wellen::{Hierarchy, SignalEncoding, SignalRef, SignalValue, SignalValueRef, States};
+
+/// How often a followed file is checked for new time steps.
+#[cfg(feature = "gui")]
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Bit states in the order of wellen's numeric codes.
+const STATE_CHARS: &[u8] = b"01xzhuwl-";
+
+/// Characters VCD identifier codes are made of.
+const ID_FIRST: u8 = b'!';
+const ID_LAST: u8 = b'~';
+
+/// A value change appended to a VCD after it was opened.
+#[derive(Debug, Clone)]
+pub struct AppendedChange {
+    pub time: u64,
+    value: SignalValue,
+}
+
+impl AppendedChange {
+    pub fn value(&self) -> SignalValueRef<'_> {
+        (&self.value).into()
+    }
+}
+
+/// Time steps a simulator appended to an open VCD. The waveform holds the
+/// file up to `start`; everything after it is parsed here, a complete line
+/// at a time, and kept for the signals that are loaded. A signal loaded
+/// later reads its changes again from the appended part of the file.
+#[derive(Debug, Default)]
+pub struct VcdTail {
+    start: u64,
+    start_time: u64,
+    offset: u64,
+    time: u64,
+    in_comment: bool,
+    /// Signal and encoding of every identifier code, read on first use
+    ids: Option<HashMap<String, (SignalRef, SignalEncoding)>>,
+    /// Changes of every tracked signal, empty until it changes
+    changes: HashMap<SignalRef, Vec<AppendedChange>>,
+    /// Bumped by every watch and unwatch, so a superseded watcher stops
+    #[cfg(feature = "gui")]
+    generation: u32,
+}
+
+/// Payload of the `waveform-updated-{session_id}` event.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveformUpdateEvent {
+    pub end: u64,
+    /// Set when following failed; no more events follow
+    pub error: Option<String>,
+}
+
+fn file_length(path: &str) -> Result<u64, String> {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .map_err(|e| format!("Failed to read {}: {}", path, e))
+}
+
+/// Read a VCD that may still be growing, returning the waveform and where
+/// its tail starts. A file that grew while it was parsed is read again up
+/// to its last complete line, so the tail never repeats or skips data.
+pub fn read_vcd(path: &str) -> Result<(Waveform, VcdTail), String> {
+    let length = file_length(path)?;
+    let waveform =
+        wellen::simple::read(path).map_err(|e| format!("Failed to read waveform file: {}", e))?;
+
+    let (waveform, mut offset) = if file_length(path)? == length {
+        (waveform, length)
+    } else {
+        let mut bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        bytes.truncate(bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1));
+        let offset = bytes.len() as u64;
+        let waveform = wellen::simple::read_from_reader(Cursor::new(bytes))
+            .map_err(|e| format!("Failed to read waveform file: {}", e))?;
+        (waveform, offset)
+    };
+
+    // A last line without newline may still be half written; read it again
+    // with the tail once it is complete
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    while offset > 0 {
+        let mut byte = [0u8];
+        file.seek(SeekFrom::Start(offset - 1))
+            .and_then(|_| file.read_exact(&mut byte))
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if byte[0] == b'\n' {
+            break;
+        }
+        offset -= 1;
+    }
+
+    let time = waveform.time_table().last().copied().unwrap_or(0);
+    let tail = VcdTail {
+        start: offset,
+        start_time: time,
+        offset,
+        time,
+        ..VcdTail::default()
+    };
+    Ok((waveform, tail))
+}
+
+/// Index wellen gives a dense identifier code.
+fn id_to_index(id: &str) -> Option<usize> {
+    let mut index = 0usize;
+    for byte in id.bytes().rev() {
+        if !(ID_FIRST..=ID_LAST).contains(&byte) {
+            return None;
+        }
+        let digit = usize::from(byte - ID_FIRST) + 1;
+        index = index
+            .checked_mul(usize::from(ID_LAST - ID_FIRST) + 1)?
+            .checked_add(digit)?;
+    }
+    index.checked_sub(1)
+}
+
+/// Map the identifier codes declared in the header of a VCD to signals.
+/// wellen numbers codes densely when it can and in order of declaration
+/// otherwise; whichever numbering agrees with the declared widths is used.
+fn read_ids(
+    path: &str,
+    hierarchy: &Hierarchy,
+) -> Result<HashMap<String, (SignalRef, SignalEncoding)>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut declared: Vec<(String, u32)> = Vec::new();
+    let mut tokens: Vec<String> = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        tokens.extend(line.split_ascii_whitespace().map(str::to_string));
+        if tokens.iter().any(|token| token == "$enddefinitions") {
+            break;
+        }
+    }
+    let mut tokens = tokens.iter();
+    while let Some(token) = tokens.next() {
+        if token != "$var" {
+            continue;
+        }
+        let mut fields = tokens.by_ref().take_while(|token| *token != "$end").skip(1);
+        if let (Some(size), Some(id)) = (fields.next(), fields.next()) {
+            declared.push((id.clone(), size.parse().unwrap_or(0)));
+        }
+    }
+
+    let mut first_seen: HashMap<&str, usize> = HashMap::new();
+    for (id, _) in &declared {
+        let next = first_seen.len() + 1;
+        first_seen.entry(id).or_insert(next);
+    }
+
+    map_ids(&declared, hierarchy, id_to_index)
+        .or_else(|| map_ids(&declared, hierarchy, |id| first_seen.get(id).copied()))
+        .ok_or_else(|| {
+            format!(
+                "Failed to follow {}: its identifier codes do not match its hierarchy",
+                path
+            )
+        })
+}
+
+/// Signals of the declared `(id, size)` pairs under one numbering, or None
+/// if a number is not a signal of the hierarchy or has another width.
+fn map_ids(
+    declared: &[(String, u32)],
+    hierarchy: &Hierarchy,
+    numbering: impl Fn(&str) -> Option<usize>,
+) -> Option<HashMap<String, (SignalRef, SignalEncoding)>> {
+    declared
+        .iter()
+        .map(|(id, size)| {
+            let signal_ref = SignalRef::from_index(numbering(id)?)?;
+            match hierarchy.get_signal_tpe(signal_ref)? {
+                SignalEncoding::BitVector(width) if width != *size && width != 0 => None,
+                encoding => Some((id.clone(), (signal_ref, encoding))),
+            }
+        })
+        .collect()
+}
+
+/// Pack a bit string the way wellen stores bit vectors, with the least
+/// significant bit in the low bits of the last byte. Unknown characters
+/// are read as `x`.
+fn bit_vector(bits: &str) -> SignalValue {
+    let codes: Vec<u8> = bits
+        .bytes()
+        .map(|c| STATE_CHARS.iter().position(|&s| s == c).unwrap_or(2) as u8)
+        .collect();
+    let states = match codes.iter().max() {
+        Some(0..=1) | None => States::Two,
+        Some(2..=3) => States::Four,
+        _ => States::Nine,
+    };
+    let per_byte = states.bits_in_a_byte() as usize;
+    let mut data = vec![0u8; states.bytes_required(codes.len() as u32)];
+    let last = data.len().saturating_sub(1);
+    for (i, code) in codes.iter().rev().enumerate() {
+        data[last - i / per_byte] |= code << (i % per_byte * states.bits() as usize);
+    }
+    SignalValueRef::bit_vec(states, codes.len() as u32, &data).into()
+}
+
+/// Value of a VCD change (`b0101`, `r1.5`, `sIDLE` or a scalar such as `1`)
+/// for a signal of the given encoding.
+fn appended_value(raw: &str, encoding: SignalEncoding) -> SignalValue {
+    let body = match raw.as_bytes().first() {
+        Some(b'b' | b'B' | b'r' | b'R' | b's' | b'S') => &raw[1..],
+        _ => raw,
+    };
+    match encoding {
+        SignalEncoding::Real => SignalValueRef::Real(body.parse().unwrap_or(f64::NAN)).into(),
+        SignalEncoding::String => SignalValueRef::String(body).into(),
+        SignalEncoding::BitVector(0) => SignalValueRef::Event.into(),
+        SignalEncoding::BitVector(width) => {
+            let width = width as usize;
+            // Bytes that were not UTF-8 come in as replacement characters
+            let bits: String = body
+                .chars()
+                .map(|c| {
+                    if c.is_ascii() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        'x'
+                    }
+                })
+                .collect();
+            // VCD left-extends with 0, or with the leading x or z
+            let bits = if bits.len() >= width {
+                bits[bits.len() - width..].to_string()
+            } else {
+                let fill = bits
+                    .chars()
+                    .next()
+                    .filter(|c| matches!(c, 'x' | 'z'))
+                    .unwrap_or('0');
+                let mut padded: String = std::iter::repeat_n(fill, width - bits.len()).collect();
+                padded.push_str(&bits);
+                padded
+            };
+            bit_vector(&bits)
+        }
+    }
+}
+
+/// Walk the time steps and value changes of VCD body text, calling
+/// `on_change(time, id, raw value)` for each change.
+fn scan(
+    text: &str,
+    path: &str,
+    time: &mut u64,
+    in_comment: &mut bool,
+    mut on_change: impl FnMut(u64, &str, &str),
+) -> Result<(), String> {
+    let mut tokens = text.split_ascii_whitespace();
+    while let Some(token) = tokens.next() {
+        if *in_comment {
+            *in_comment = token != "$end";
+            continue;
+        }
+        match token.as_bytes()[0] {
+            b'#' => {
+                let next: u64 = token[1..]
+                    .parse()
+                    .map_err(|_| format!("Failed to follow {}: bad time {}", path, token))?;
+                *time = (*time).max(next);
+            }
+            b'$' => *in_comment = token == "$comment",
+            b'b' | b'B' | b'r' | b'R' | b's' | b'S' => {
+                if let Some(id) = tokens.next() {
+                    on_change(*time, id, token);
+                }
+            }
+            _ => {
+                let split = token.chars().next().map_or(0, char::len_utf8);
+                on_change(*time, &token[split..], &token[..split]);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Keep a change of a tracked signal. Delta cycles settle to their last
+/// value and, like wellen, only changes that change the value are kept.
+fn record(
+    changes: &mut HashMap<SignalRef, Vec<AppendedChange>>,
+    ids: &HashMap<String, (SignalRef, SignalEncoding)>,
+    time: u64,
+    id: &str,
+    raw: &str,
+) {
+    let Some(&(signal_ref, encoding)) = ids.get(id) else {
+        return;
+    };
+    let Some(changes) = changes.get_mut(&signal_ref) else {
+        return;
+    };
+    let value = appended_value(raw, encoding);
+    if changes.last().is_some_and(|last| last.time == time) {
+        changes.pop();
+    }
+    if changes
+        .last()
+        .is_some_and(|last| value_to_string(&last.value()) == value_to_string(&(&value).into()))
+    {
+        return;
+    }
+    changes.push(AppendedChange { time, value });
+}
+
+fn read_from(path: &str, offset: u64) -> Result<Vec<u8>, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(bytes)
+}
+
+impl VcdTail {
+    /// Time of the last time step read, at least the end of the waveform.
+    pub fn end(&self) -> u64 {
+        self.time
+    }
+
+    /// Changes of one signal appended since the file was opened.
+    pub fn changes(&self, signal_ref: SignalRef) -> &[AppendedChange] {
+        self.changes.get(&signal_ref).map_or(&[], Vec::as_slice)
+    }
+
+    fn ids(
+        &mut self,
+        path: &str,
+        hierarchy: &Hierarchy,
+    ) -> Result<&HashMap<String, (SignalRef, SignalEncoding)>, String> {
+        if self.ids.is_none() {
+            self.ids = Some(read_ids(path, hierarchy)?);
+        }
+        Ok(self.ids.as_ref().unwrap())
+    }
+
+    /// Start keeping the appended changes of newly loaded signals, reading
+    /// those already appended again from the file.
+    pub fn track(
+        &mut self,
+        path: &str,
+        hierarchy: &Hierarchy,
+        signal_refs: &[SignalRef],
+    ) -> Result<(), String> {
+        let new: Vec<SignalRef> = signal_refs
+            .iter()
+            .filter(|signal_ref| !self.changes.contains_key(signal_ref))
+            .copied()
+            .collect();
+        if new.is_empty() {
+            return Ok(());
+        }
+        if self.offset == self.start {
+            self.changes.extend(new.iter().map(|&r| (r, Vec::new())));
+            return Ok(());
+        }
+
+        let mut bytes = read_from(path, self.start)?;
+        bytes.truncate((self.offset - self.start) as usize);
+        let text = String::from_utf8_lossy(&bytes);
+        self.ids(path, hierarchy)?;
+        let ids = self.ids.as_ref().unwrap();
+        let mut changes: HashMap<SignalRef, Vec<AppendedChange>> =
+            new.iter().map(|&r| (r, Vec::new())).collect();
+        let (mut time, mut in_comment) = (self.start_time, false);
+        scan(&text, path, &mut time, &mut in_comment, |time, id, raw| {
+            record(&mut changes, ids, time, id, raw)
+        })?;
+        self.changes.extend(changes);
+        Ok(())
+    }
+
+    /// Stop keeping the appended changes of unloaded signals.
+    pub fn forget(&mut self, signal_refs: &[SignalRef]) {
+        for signal_ref in signal_refs {
+            self.changes.remove(signal_ref);
+        }
+    }
+
+    /// Parse the complete lines appended since the last read. Returns whether
+    /// anything new was found.
+    pub fn read(&mut self, path: &str, hierarchy: &Hierarchy) -> Result<bool, String> {
+        self.ids(path, hierarchy)?;
+
+        let length = std::fs::metadata(path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?
+            .len();
+        if length < self.offset {
+            return Err(format!(
+                "Failed to follow {}: the file was truncated, reopen it",
+                path
+            ));
+        }
+
+        let bytes = read_from(path, self.offset)?;
+        let Some(complete) = bytes.iter().rposition(|&b| b == b'\n') else {
+            return Ok(false);
+        };
+        self.offset += complete as u64 + 1;
+
+        let text = String::from_utf8_lossy(&bytes[..complete]);
+        let Self {
+            ids,
+            changes,
+            time,
+            in_comment,
+            ..
+        } = self;
+        let ids = ids.as_ref().unwrap();
+        scan(&text, path, time, in_comment, |time, id, raw| {
+            record(changes, ids, time, id, raw)
+        })?;
+        Ok(true)
+    }
+}
+
+/// Read what was appended to the VCD of a session since the last call and
+/// return the new end time, or None when nothing complete was added.
+pub fn read_appended(session: &mut WaveformSession) -> Result<Option<u64>, String> {
+    let WaveformSession {
+        path,
+        waveform,
+        tail,
+        ..
+    } = session;
+    let tail = tail
+        .as_mut()
+        .ok_or_else(|| format!("Failed to follow {}: only VCD files can be followed", path))?;
+    if !tail.read(path, waveform.hierarchy())? {
+        return Ok(None);
+    }
+    // Matches and clocks found so far may have changed with the new data
+    session.search.clear();
+    session.clocks = None;
+    Ok(session.tail.as_ref().map(VcdTail::end))
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Follow a VCD that is still being written, emitting
+/// `waveform-updated-{session_id}` with the new end time whenever complete
+/// time steps are appended
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn watch_waveform(
+    session_id: u32,
+    app: AppHandle,
+    state: State<WaveformManager>,
+) -> Result<(), String> {
+    let generation = {
+        let session = state.get(session_id)?;
+        let mut session = session.lock().unwrap();
+        let path = session.path.clone();
+        let tail = session
+            .tail
+            .as_mut()
+            .ok_or_else(|| format!("Failed to follow {}: only VCD files can be followed", path))?;
+        tail.generation += 1;
+        tail.generation
+    };
+
+    let sessions = state.sessions.clone();
+    let event_name = format!("waveform-updated-{}", session_id);
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        // Closing the session ends the watch
+        let Some(session) = sessions.lock().unwrap().get(&session_id).cloned() else {
+            break;
+        };
+        let mut session = session.lock().unwrap();
+        if session.tail.as_ref().map(|tail| tail.generation) != Some(generation) {
+            break;
+        }
+
+        match read_appended(&mut session) {
+            Ok(None) => {}
+            Ok(Some(end)) => {
+                let _ = app.emit(&event_name, WaveformUpdateEvent { end, error: None });
+            }
+            Err(error) => {
+                let end = session.end_time();
+                let _ = app.emit(
+                    &event_name,
+                    WaveformUpdateEvent {
+                        end,
+                        error: Some(error),
+                    },
+                );
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Stop following a session; the data read so far stays available
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn unwatch_waveform(session_id: u32, state: State<WaveformManager>) -> Result<(), String> {
+    let session = state.get(session_id)?;
+    let mut session = session.lock().unwrap();
+    if let Some(tail) = session.tail.as_mut() {
+        tail.generation += 1;
+    }
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use crate::clock::ClockInfo;
+use crate::instruction_trace::{
+    instruction_trace, sibling_var, signed_changes, signed_value_at, TraceRow,
+};
+use crate::sapho_names::SignalKind;
+use crate::source_profile::line_functions;
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// How often one mnemonic executed and how long it took.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MnemonicCount {
+    pub mnemonic: String,
+    /// Executions: one per clock cycle when the waveform has a clock, one
+    /// per fetch otherwise
+    pub count: usize,
+    pub time: u64,
+    /// Clock cycles, when the waveform has a clock
+    pub cycles: Option<u64>,
+    /// Fraction of the time of the report it belongs to
+    pub share: f64,
+}
+
+/// Instruction mix of the code of one C+- function.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionMix {
+    pub function: String,
+    pub count: usize,
+    pub time: u64,
+    pub cycles: Option<u64>,
+    pub mnemonics: Vec<MnemonicCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionMix {
+    pub instruction_path: String,
+    /// `linetabs` signal used to attribute instructions to functions
+    pub source_path: Option<String>,
+    pub clock: Option<ClockInfo>,
+    /// Instructions executed with a known mnemonic
+    pub total_count: usize,
+    /// Run time of the program, up to the halt
+    pub total_time: u64,
+    /// The `JMP` to itself the program ends in, which keeps being fetched
+    /// until the end of the waveform. It is left out of the counts.
+    pub halt: Option<TraceRow>,
+    /// Fetches of addresses missing from the opcode table or with unknown bits
+    pub unknown: usize,
+    pub mnemonics: Vec<MnemonicCount>,
+    pub functions: Vec<FunctionMix>,
+    /// Mnemonics of the program that never executed
+    pub unused: Vec<String>,
+}
+
+/// Count and total the `(mnemonic, executions, time)` of executed
+/// instructions, most frequent first.
+fn tally<'a>(
+    executed: impl Iterator<Item = (&'a str, usize, u64)>,
+    clock: Option<&ClockInfo>,
+) -> Vec<MnemonicCount> {
+    let mut totals: HashMap<&str, (usize, u64)> = HashMap::new();
+    for (mnemonic, executions, time) in executed {
+        let total = totals.entry(mnemonic).or_default();
+        total.0 += executions;
+        total.1 += time;
+    }
+    let total_time: u64 = totals.values().map(|(_, time)| time).sum();
+
+    let mut counts: Vec<MnemonicCount> = totals
+        .into_iter()
+        .map(|(mnemonic, (count, time))| MnemonicCount {
+            mnemonic: mnemonic.to_string(),
+            count,
+            time,
+            cycles: clock.map(|clock| time / clock.period),
+            share: if total_time == 0 {
+                0.0
+            } else {
+                time as f64 / total_time as f64
+            },
+        })
+        .collect();
+    counts.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.mnemonic.cmp(&b.mnemonic))
+    });
+    counts
+}
+
+/// How often each mnemonic executed and the time spent in it, overall and
+/// per C+- function where `linetabs` tells which function is running. Each
+/// instruction lasts until the next fetch; with a clock, a fetch held for
+/// several cycles is the same instruction executing once per cycle.
+pub fn instruction_mix(
+    session: &mut WaveformSession,
+    instruction_ref: Option<usize>,
+) -> Result<InstructionMix, String> {
+    let mut trace = instruction_trace(session, instruction_ref)?;
+    let end = session.end_time();
+
+    // A program ends jumping to itself: valr2 stops changing on a JMP and
+    // the fetch is held until the end of the dump. Without a clock, held
+    // means lasting longer than any instruction before it.
+    let longest = trace
+        .rows
+        .windows(2)
+        .map(|pair| pair[1].time - pair[0].time)
+        .max()
+        .unwrap_or(0);
+    let halts = trace.rows.last().is_some_and(|row| {
+        row.mnemonic.as_deref() == Some("JMP")
+            && match row.cycles {
+                Some(cycles) => cycles > 1,
+                None => end.saturating_sub(row.time) > longest,
+            }
+    });
+    let halt = if halts { trace.rows.pop() } else { None };
+    let end = halt.as_ref().map_or(end, |halt| halt.time);
+
+    let source = sibling_var(session, &trace.instruction_path, SignalKind::SourceLine);
+    let source_lines = match &source {
+        Some((_, index)) => signed_changes(session, *index)?,
+        None => Vec::new(),
+    };
+    let tables = session.symbols_for(&trace.instruction_path);
+    let functions = line_functions(&tables.source_lines);
+
+    // (mnemonic, executions, time, function) of every known instruction
+    let mut executed = Vec::with_capacity(trace.rows.len());
+    let mut unknown = 0;
+    for (step, row) in trace.rows.iter().enumerate() {
+        let Some(mnemonic) = row.mnemonic.as_deref() else {
+            unknown += 1;
+            continue;
+        };
+        let next = trace.rows.get(step + 1).map_or(end, |next| next.time);
+        let function = signed_value_at(&source_lines, row.time)
+            .and_then(|line| functions.get(&line))
+            .map(String::as_str);
+        let executions = row.cycles.map_or(1, |cycles| cycles.max(1) as usize);
+        executed.push((mnemonic, executions, next - row.time, function));
+    }
+
+    let clock = trace.clock.as_ref();
+    let mnemonics = tally(executed.iter().map(|&(m, n, time, _)| (m, n, time)), clock);
+
+    let mut names: Vec<&str> = executed.iter().filter_map(|&(_, _, _, f)| f).collect();
+    names.sort_unstable();
+    names.dedup();
+    let mut function_mixes: Vec<FunctionMix> = names
+        .into_iter()
+        .map(|name| {
+            let mnemonics = tally(
+                executed
+                    .iter()
+                    .filter(|&&(_, _, _, f)| f == Some(name))
+                    .map(|&(m, n, time, _)| (m, n, time)),
+                clock,
+            );
+            let time = mnemonics.iter().map(|m| m.time).sum();
+            FunctionMix {
+                function: name.to_string(),
+                count: mnemonics.iter().map(|m| m.count).sum(),
+                time,
+                cycles: clock.map(|clock| time / clock.period),
+                mnemonics,
+            }
+        })
+        .collect();
+    function_mixes.sort_by(|a, b| {
+        b.time
+            .cmp(&a.time)
+            .then_with(|| a.function.cmp(&b.function))
+    });
+
+    let executed_names: BTreeSet<&str> = mnemonics
+        .iter()
+        .map(|m| m.mnemonic.as_str())
+        .chain(halt.as_ref().and_then(|halt| halt.mnemonic.as_deref()))
+        .collect();
+    let unused: BTreeSet<&str> = tables
+        .opcodes
+        .values()
+        .map(|instruction| instruction.mnemonic.as_str())
+        .filter(|mnemonic| !executed_names.contains(mnemonic))
+        .collect();
+
+    Ok(InstructionMix {
+        instruction_path: trace.instruction_path,
+        source_path: source.map(|(path, _)| path),
+        total_count: mnemonics.iter().map(|m| m.count).sum(),
+        total_time: mnemonics.iter().map(|m| m.time).sum(),
+        halt,
+        unknown,
+        unused: unused.into_iter().map(str::to_string).collect(),
+        mnemonics,
+        functions: function_mixes,
+        clock: trace.clock,
+    })
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Executed instruction counts and cycles per mnemonic, overall and per
+/// function
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn get_instruction_mix(
+    session_id: u32,
+    signal_ref: Option<usize>,
+    state: State<'_, WaveformManager>,
+) -> Result<InstructionMix, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        instruction_mix(&mut session.lock().unwrap(), signal_ref)
+    })
+    .await
+    .map_err(|e| format!("Failed to compute instruction mix: {}", e))?
+}
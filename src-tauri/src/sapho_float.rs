@@ -0,0 +1,425 @@
+#[cfg(feature = "gui")]
+use crate::commands::ProcessorConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Widest mantissa supported, the most the 8-bit width header of vector
+/// and complex values can describe.
+pub const MAX_MANTISSA_BITS: u32 = 255;
+
+/// 64-bit limbs of a [`Mantissa`], enough for [`MAX_MANTISSA_BITS`].
+const MANTISSA_LIMBS: usize = 4;
+
+/// Widest exponent supported. Exact decimals of `2^±32768` are already
+/// about ten thousand digits long.
+pub const MAX_EXPONENT_BITS: u32 = 16;
+
+/// Bits of each width header in front of vector and complex values.
+const HEADER_BITS: usize = 8;
+
+/// Layout of a SAPHO float: a sign bit, a two's complement exponent and an
+/// unsigned mantissa, in that order. The value is `±mantissa * 2^exponent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FloatFormat {
+    pub mantissa_bits: u32,
+    pub exponent_bits: u32,
+}
+
+impl FloatFormat {
+    pub fn new(mantissa_bits: u32, exponent_bits: u32) -> Result<Self, String> {
+        if !(1..=MAX_MANTISSA_BITS).contains(&mantissa_bits) {
+            return Err(format!(
+                "Unsupported float format: {} mantissa bits (1 to {})",
+                mantissa_bits, MAX_MANTISSA_BITS
+            ));
+        }
+        if !(1..=MAX_EXPONENT_BITS).contains(&exponent_bits) {
+            return Err(format!(
+                "Unsupported float format: {} exponent bits (1 to {})",
+                exponent_bits, MAX_EXPONENT_BITS
+            ));
+        }
+        Ok(Self {
+            mantissa_bits,
+            exponent_bits,
+        })
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn from_config(config: &ProcessorConfig) -> Result<Self, String> {
+        Self::new(config.mantissa_bits, config.exponent_bits)
+    }
+
+    /// Bits of one encoded value.
+    pub fn width(&self) -> usize {
+        (1 + self.exponent_bits + self.mantissa_bits) as usize
+    }
+
+    pub fn min_exponent(&self) -> i64 {
+        -(1 << (self.exponent_bits - 1))
+    }
+
+    pub fn max_exponent(&self) -> i64 {
+        (1 << (self.exponent_bits - 1)) - 1
+    }
+}
+
+/// An unsigned mantissa of up to 256 bits, as little endian 64-bit limbs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Mantissa([u64; MANTISSA_LIMBS]);
+
+impl Mantissa {
+    pub fn from_u128(value: u128) -> Self {
+        Self([value as u64, (value >> 64) as u64, 0, 0])
+    }
+
+    /// Parse a mantissa written most significant bit first.
+    fn from_bits(bits: &str) -> Result<Self, String> {
+        if bits.len() > 64 * MANTISSA_LIMBS || !bits.bytes().all(|b| b == b'0' || b == b'1') {
+            return Err(format!("Failed to decode float: {} is not binary", bits));
+        }
+        let mut limbs = [0; MANTISSA_LIMBS];
+        for (i, bit) in bits.bytes().rev().enumerate() {
+            limbs[i / 64] |= u64::from(bit - b'0') << (i % 64);
+        }
+        Ok(Self(limbs))
+    }
+
+    /// The lowest `width` bits, most significant first.
+    fn to_bits(self, width: usize) -> String {
+        (0..width)
+            .rev()
+            .map(|i| if self.bit(i) { '1' } else { '0' })
+            .collect()
+    }
+
+    fn bit(&self, i: usize) -> bool {
+        i < 64 * MANTISSA_LIMBS && self.0[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; MANTISSA_LIMBS]
+    }
+
+    /// Number of significant bits, 0 for a zero mantissa.
+    pub fn bits(&self) -> u32 {
+        self.0
+            .iter()
+            .rposition(|&limb| limb != 0)
+            .map_or(0, |i| 64 * (i as u32 + 1) - self.0[i].leading_zeros())
+    }
+
+    /// Divide by `divisor` in place and return the remainder.
+    fn div_rem(&mut self, divisor: u64) -> u64 {
+        let mut rest = 0u128;
+        for limb in self.0.iter_mut().rev() {
+            let current = rest << 64 | *limb as u128;
+            *limb = (current / divisor as u128) as u64;
+            rest = current % divisor as u128;
+        }
+        rest as u64
+    }
+
+    /// `(top, shift)` with the mantissa close to `top * 2^shift`: its 64
+    /// most significant bits, the last one set if any bit below them is,
+    /// so rounding `top` to an `f64` rounds the whole mantissa correctly.
+    fn top_bits(&self) -> (u64, u32) {
+        let Some(shift) = self.bits().checked_sub(64) else {
+            return (self.0[0], 0);
+        };
+        let shift = shift as usize;
+        let top = (0..64).fold(0u64, |top, i| top | u64::from(self.bit(shift + i)) << i);
+        let sticky = (0..shift).any(|i| self.bit(i));
+        (top | u64::from(sticky), shift as u32)
+    }
+}
+
+/// A decoded SAPHO float, kept exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaphoFloat {
+    pub negative: bool,
+    pub exponent: i64,
+    pub mantissa: Mantissa,
+}
+
+fn parse_bits(bits: &str) -> Result<u128, String> {
+    u128::from_str_radix(bits, 2)
+        .map_err(|_| format!("Failed to decode float: {} is not binary", bits))
+}
+
+/// `value * 2^exponent`, in steps small enough for `powi` not to overflow
+/// before the product does.
+fn scale(mut value: f64, mut exponent: i64) -> f64 {
+    while exponent != 0 && value != 0.0 && value.is_finite() {
+        let step = exponent.clamp(-1000, 1000);
+        value *= 2f64.powi(step as i32);
+        exponent -= step;
+    }
+    value
+}
+
+impl SaphoFloat {
+    /// Decode `format.width()` bits, most significant (the sign) first.
+    pub fn decode(bits: &str, format: FloatFormat) -> Result<Self, String> {
+        if !bits.is_ascii() || bits.len() != format.width() {
+            return Err(format!(
+                "Failed to decode float: expected {} bits, got {}",
+                format.width(),
+                bits.len()
+            ));
+        }
+        let exponent_end = 1 + format.exponent_bits as usize;
+        let exponent = parse_bits(&bits[1..exponent_end])? as i64;
+        let exponent = if bits.as_bytes()[1] == b'1' {
+            exponent - (1 << format.exponent_bits)
+        } else {
+            exponent
+        };
+        Ok(Self {
+            negative: match &bits[..1] {
+                "0" => false,
+                "1" => true,
+                other => return Err(format!("Failed to decode float: {} is not binary", other)),
+            },
+            exponent,
+            mantissa: Mantissa::from_bits(&bits[exponent_end..])?,
+        })
+    }
+
+    pub fn encode(&self, format: FloatFormat) -> Result<String, String> {
+        if self.mantissa.bits() > format.mantissa_bits {
+            return Err(format!(
+                "Failed to encode float: mantissa {} needs more than {} bits",
+                exact_decimal(self.mantissa, 0),
+                format.mantissa_bits
+            ));
+        }
+        if !(format.min_exponent()..=format.max_exponent()).contains(&self.exponent) {
+            return Err(format!(
+                "Failed to encode float: exponent {} needs more than {} bits",
+                self.exponent, format.exponent_bits
+            ));
+        }
+        let exponent = self.exponent.rem_euclid(1 << format.exponent_bits);
+        Ok(format!(
+            "{}{:0ew$b}{}",
+            u8::from(self.negative),
+            exponent,
+            self.mantissa.to_bits(format.mantissa_bits as usize),
+            ew = format.exponent_bits as usize,
+        ))
+    }
+
+    /// Nearest float of `format` to `value`, keeping as many mantissa bits
+    /// as possible, up to 64 since an `f64` has no more. Zero is encoded
+    /// with a zero exponent.
+    pub fn from_f64(value: f64, format: FloatFormat) -> Result<Self, String> {
+        if !value.is_finite() {
+            return Err(format!("Failed to encode float: {} is not finite", value));
+        }
+        let negative = value.is_sign_negative() && value != 0.0;
+        let magnitude = value.abs();
+        if magnitude == 0.0 {
+            return Ok(Self {
+                negative,
+                exponent: 0,
+                mantissa: Mantissa::default(),
+            });
+        }
+
+        let precision = format.mantissa_bits.min(64);
+        let top = magnitude.log2().floor() as i64;
+        let mut exponent = (top - (precision as i64 - 1)).max(format.min_exponent());
+        let mut mantissa = scale(magnitude, -exponent).round() as u128;
+        if mantissa >> precision != 0 {
+            mantissa = mantissa.div_ceil(2);
+            exponent += 1;
+        }
+        if exponent > format.max_exponent() {
+            return Err(format!(
+                "Failed to encode float: {} is too large for {} exponent bits",
+                value, format.exponent_bits
+            ));
+        }
+        Ok(Self {
+            negative,
+            exponent,
+            mantissa: Mantissa::from_u128(mantissa),
+        })
+    }
+
+    /// Closest `f64`; only exact while the mantissa fits in 53 bits.
+    pub fn to_f64(&self) -> f64 {
+        let (top, shift) = self.mantissa.top_bits();
+        let magnitude = scale(top as f64, self.exponent + shift as i64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+}
+
+/// Every digit of `mantissa * 2^exponent` in decimal, with no rounding.
+fn exact_decimal(mantissa: Mantissa, exponent: i64) -> String {
+    // Little endian limbs in base 10^9
+    const BASE: u64 = 1_000_000_000;
+    let mut limbs = Vec::new();
+    let mut rest = mantissa;
+    while !rest.is_zero() {
+        limbs.push(rest.div_rem(BASE));
+    }
+
+    let multiply = |limbs: &mut Vec<u64>, factor: u64| {
+        let mut carry = 0;
+        for limb in limbs.iter_mut() {
+            let product = *limb * factor + carry;
+            *limb = product % BASE;
+            carry = product / BASE;
+        }
+        while carry > 0 {
+            limbs.push(carry % BASE);
+            carry /= BASE;
+        }
+    };
+    // Powers of 2^29 and 5^13 keep every product well inside a u64
+    let (base, chunk) = if exponent >= 0 {
+        (2u64, 29)
+    } else {
+        (5u64, 13)
+    };
+    let mut remaining = exponent.unsigned_abs();
+    while remaining > 0 {
+        let step = remaining.min(chunk);
+        multiply(&mut limbs, base.pow(step as u32));
+        remaining -= step;
+    }
+
+    let mut digits = match limbs.split_last() {
+        Some((top, lower)) => lower
+            .iter()
+            .rev()
+            .fold(top.to_string(), |acc, limb| format!("{}{:09}", acc, limb)),
+        None => "0".to_string(),
+    };
+    if exponent >= 0 || mantissa.is_zero() {
+        return digits;
+    }
+
+    // m * 5^k / 10^k: the last k digits are the fraction
+    let fraction_digits = exponent.unsigned_abs() as usize;
+    if digits.len() <= fraction_digits {
+        digits = format!(
+            "{}{}",
+            "0".repeat(fraction_digits + 1 - digits.len()),
+            digits
+        );
+    }
+    let (integer, fraction) = digits.split_at(digits.len() - fraction_digits);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+impl fmt::Display for SaphoFloat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative && !self.mantissa.is_zero() {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", exact_decimal(self.mantissa, self.exponent))
+    }
+}
+
+/// Decode a vector or complex value: 8 bits of mantissa width, 8 bits of
+/// exponent width, then two floats of that format. Bits after them are
+/// ignored, as the viewer always did.
+pub fn decode_pair(bits: &str) -> Result<(FloatFormat, SaphoFloat, SaphoFloat), String> {
+    if !bits.is_ascii() || bits.len() < 2 * HEADER_BITS {
+        return Err(format!(
+            "Failed to decode float pair: {} bits is shorter than its header",
+            bits.len()
+        ));
+    }
+    let mantissa_bits = parse_bits(&bits[..HEADER_BITS])? as u32;
+    let exponent_bits = parse_bits(&bits[HEADER_BITS..2 * HEADER_BITS])? as u32;
+    let format = FloatFormat::new(mantissa_bits, exponent_bits)?;
+
+    let start = 2 * HEADER_BITS;
+    let width = format.width();
+    if bits.len() < start + 2 * width {
+        return Err(format!(
+            "Failed to decode float pair: {} bits cannot hold two {}-bit floats",
+            bits.len(),
+            width
+        ));
+    }
+    let first = SaphoFloat::decode(&bits[start..start + width], format)?;
+    let second = SaphoFloat::decode(&bits[start + width..start + 2 * width], format)?;
+    Ok((format, first, second))
+}
+
+/// Inverse of `decode_pair`.
+pub fn encode_pair(
+    format: FloatFormat,
+    first: &SaphoFloat,
+    second: &SaphoFloat,
+) -> Result<String, String> {
+    Ok(format!(
+        "{:08b}{:08b}{}{}",
+        format.mantissa_bits,
+        format.exponent_bits,
+        first.encode(format)?,
+        second.encode(format)?
+    ))
+}
+
+/// Text for a value in one of the SAPHO radixes: `vector` (`[a, b]`),
+/// `complex` (`a + j b`) or `float`, which needs the processor's format.
+pub fn format_value(
+    bits: &str,
+    radix: &str,
+    format: Option<FloatFormat>,
+) -> Result<String, String> {
+    match radix {
+        "vector" => decode_pair(bits).map(|(_, a, b)| format!("[{}, {}]", a, b)),
+        "complex" => decode_pair(bits).map(|(_, re, im)| format!("{} + j {}", re, im)),
+        "float" => {
+            let format = format
+                .ok_or_else(|| "Failed to decode float: no float format given".to_string())?;
+            SaphoFloat::decode(bits, format).map(|value| value.to_string())
+        }
+        other => Err(format!(
+            "Failed to decode value: unknown SAPHO radix {}",
+            other
+        )),
+    }
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Decode raw bit strings in a SAPHO radix. Values that cannot be decoded,
+/// such as those with `x` bits, are returned unchanged.
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn decode_sapho_values(
+    values: Vec<String>,
+    radix: String,
+    format: Option<FloatFormat>,
+) -> Vec<String> {
+    values
+        .into_iter()
+        .map(|bits| format_value(&bits, &radix, format).unwrap_or(bits))
+        .collect()
+}
+
+/// Encode a number as a SAPHO float of the given format
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn encode_sapho_float(value: f64, format: FloatFormat) -> Result<String, String> {
+    let format = FloatFormat::new(format.mantissa_bits, format.exponent_bits)?;
+    SaphoFloat::from_f64(value, format)?.encode(format)
+}
@@ -0,0 +1,232 @@
+use serde::Serialize;
+
+/// What a SAPHO testbench signal is, going by the names the compiler and
+/// the generated testbench give them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SignalKind {
+    Clock,
+    Reset,
+    /// `in_sim_N`, the value offered on input port N
+    Input,
+    /// `req_in_sim_N`, the processor reading input port N
+    InputRequest,
+    /// `out_sig_N`, the value on output port N
+    Output,
+    /// `out_en_sim_N`, the processor writing output port N
+    OutputEnable,
+    /// `valr2`, the instruction address
+    Instruction,
+    /// `linetabs`, the C+- source line
+    SourceLine,
+    /// `pointeri` of the data stack
+    DataStackPointer,
+    /// `pointeri` of the instruction stack, under an `isp` scope
+    InstructionStackPointer,
+    /// `me1_f_<func>_v_<var>_e_`
+    Int,
+    /// `me2_f_<func>_v_<var>_e_`
+    Float,
+    /// `me3_f_<func>_v_<var>_e_`
+    Vector,
+    /// `comp_` followed by a variable name
+    Complex,
+    /// `arr_` followed by a variable name
+    Array,
+    /// Any other signal of the processor core
+    Flag,
+    Other,
+}
+
+impl SignalKind {
+    /// Name as serialized, used by the binary hierarchy layout.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Clock => "clock",
+            Self::Reset => "reset",
+            Self::Input => "input",
+            Self::InputRequest => "inputRequest",
+            Self::Output => "output",
+            Self::OutputEnable => "outputEnable",
+            Self::Instruction => "instruction",
+            Self::SourceLine => "sourceLine",
+            Self::DataStackPointer => "dataStackPointer",
+            Self::InstructionStackPointer => "instructionStackPointer",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Vector => "vector",
+            Self::Complex => "complex",
+            Self::Array => "array",
+            Self::Flag => "flag",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Metadata parsed from a signal name: the variable and the function it
+/// belongs to for program variables, the port number for testbench I/O.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalClass {
+    pub kind: SignalKind,
+    pub function: Option<String>,
+    pub variable: Option<String>,
+    pub port: Option<u32>,
+}
+
+impl SignalClass {
+    fn new(kind: SignalKind) -> Self {
+        Self {
+            kind,
+            function: None,
+            variable: None,
+            port: None,
+        }
+    }
+}
+
+/// Port number of `<prefix>N`.
+fn port(name: &str, prefix: &str) -> Option<u32> {
+    let digits = name.strip_prefix(prefix)?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Function and variable of `me<N>_f_<func>_v_<var>_e_`, with the kind
+/// given by N.
+fn memory_variable(name: &str) -> Option<(SignalKind, String, String)> {
+    let (kind, rest) = match name.get(..3)? {
+        "me1" => (SignalKind::Int, &name[3..]),
+        "me2" => (SignalKind::Float, &name[3..]),
+        "me3" => (SignalKind::Vector, &name[3..]),
+        _ => return None,
+    };
+    let rest = rest.strip_prefix("_f_")?;
+    let (function, variable) = rest.split_once("_v_")?;
+    let variable = variable
+        .strip_suffix("_e_")
+        .or_else(|| variable.strip_suffix("_e"))?;
+    if function.is_empty() || variable.is_empty() {
+        return None;
+    }
+    Some((kind, function.to_string(), variable.to_string()))
+}
+
+/// Processor a signal belongs to, from the `p_<name>` instance the
+/// generated testbench wraps each processor core in.
+pub fn processor_instance(path: &str) -> Option<&str> {
+    let (scopes, _) = path.rsplit_once('.')?;
+    scopes
+        .split('.')
+        .find_map(|scope| scope.strip_prefix("p_"))
+        .filter(|name| !name.is_empty())
+}
+
+/// Scope path of every `p_<name>` instance among `paths`, with its name,
+/// e.g. `("tb.proc.p_ProcDTW", "ProcDTW")`.
+pub fn processor_scopes(paths: &[String]) -> Vec<(String, String)> {
+    let mut scopes: Vec<(String, String)> = Vec::new();
+    for path in paths {
+        let Some(name) = processor_instance(path) else {
+            continue;
+        };
+        let parts: Vec<&str> = path.split('.').collect();
+        let Some(end) = parts.iter().position(|scope| scope.starts_with("p_")) else {
+            continue;
+        };
+        let scope = parts[..=end].join(".");
+        if !scopes.iter().any(|(known, _)| *known == scope) {
+            scopes.push((scope, name.to_string()));
+        }
+    }
+    scopes
+}
+
+/// Processor a signal belongs to: its own `p_<name>` instance or, for the
+/// signals the testbench declares beside the instance such as `valr2` and
+/// `linetabs`, the one instance of `scopes` sharing the most scopes with it.
+pub fn owning_processor<'a>(scopes: &'a [(String, String)], path: &str) -> Option<&'a str> {
+    if let Some(name) = processor_instance(path) {
+        return scopes
+            .iter()
+            .find(|(_, known)| known == name)
+            .map(|(_, known)| known.as_str());
+    }
+    let (parent, _) = path.rsplit_once('.')?;
+    let shared = |scope: &str| {
+        scope
+            .split('.')
+            .zip(parent.split('.'))
+            .take_while(|(a, b)| a == b)
+            .count()
+    };
+    let most = scopes.iter().map(|(scope, _)| shared(scope)).max()?;
+    let mut closest = scopes
+        .iter()
+        .filter(|(scope, _)| most > 0 && shared(scope) == most)
+        .map(|(_, name)| name.as_str());
+    let name = closest.next()?;
+    closest.all(|other| other == name).then_some(name)
+}
+
+/// Classify a signal by its name and, for stack pointers and core flags,
+/// the scopes in its dotted path. A trailing `[msb:lsb]` is ignored.
+pub fn classify(name: &str, path: &str) -> SignalClass {
+    let name = match name.split_once('[') {
+        Some((base, _)) if name.ends_with(']') => base.trim_end(),
+        _ => name,
+    };
+    let in_scope = |scope: &str| path.split('.').rev().skip(1).any(|s| s == scope);
+
+    let ports = [
+        ("req_in_sim_", SignalKind::InputRequest),
+        ("in_sim_", SignalKind::Input),
+        ("out_en_sim_", SignalKind::OutputEnable),
+        ("out_sig_", SignalKind::Output),
+    ];
+    for (prefix, kind) in ports {
+        if let Some(port) = port(name, prefix) {
+            return SignalClass {
+                port: Some(port),
+                ..SignalClass::new(kind)
+            };
+        }
+    }
+
+    let container = [("comp_", SignalKind::Complex), ("arr_", SignalKind::Array)]
+        .into_iter()
+        .find_map(|(prefix, kind)| Some((kind, name.strip_prefix(prefix)?)));
+    if let Some((kind, inner)) = container {
+        let (function, variable) = match memory_variable(inner) {
+            Some((_, function, variable)) => (Some(function), Some(variable)),
+            None => (None, Some(inner.to_string())),
+        };
+        return SignalClass {
+            kind,
+            function,
+            variable,
+            port: None,
+        };
+    }
+    if let Some((kind, function, variable)) = memory_variable(name) {
+        return SignalClass {
+            kind,
+            function: Some(function),
+            variable: Some(variable),
+            port: None,
+        };
+    }
+
+    SignalClass::new(match name {
+        "clk" => SignalKind::Clock,
+        "rst" => SignalKind::Reset,
+        "valr2" => SignalKind::Instruction,
+        "linetabs" => SignalKind::SourceLine,
+        "pointeri" if in_scope("isp") => SignalKind::InstructionStackPointer,
+        "pointeri" => SignalKind::DataStackPointer,
+        _ if in_scope("core") => SignalKind::Flag,
+        _ => SignalKind::Other,
+    })
+}
@@ -0,0 +1,442 @@
+use crate::clock::detect_clocks;
+use crate::sapho_float::{format_value, FloatFormat};
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::{timescale_to_string, SignalChanges, WaveformSession};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+#[cfg(feature = "gui")]
+use tauri::State;
+use wellen::{SignalEncoding, VarType};
+
+/// A signal to export and the radix its values are written in: `binary`,
+/// `hex`, `decimal`, `signed decimal` or the SAPHO `vector`, `complex` and
+/// `float`. Without a radix it is chosen as the viewer does.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSignal {
+    pub signal_ref: usize,
+    pub radix: Option<String>,
+    /// Format of the signal's processor, in place of the request's
+    #[serde(default)]
+    pub float_format: Option<FloatFormat>,
+}
+
+/// When the exported signals are sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Sampling {
+    /// Whenever any of them changes
+    Changes,
+    RisingEdge,
+    FallingEdge,
+}
+
+/// Signals to export over `[from, to]` (the whole waveform by default),
+/// sampled on every change or on the edges of a clock: `clock_ref`, or the
+/// detected one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalExportRequest {
+    pub signals: Vec<ExportSignal>,
+    pub sampling: Sampling,
+    #[serde(default)]
+    pub clock_ref: Option<usize>,
+    #[serde(default)]
+    pub from: Option<u64>,
+    #[serde(default)]
+    pub to: Option<u64>,
+    /// Format of values shown in the `float` radix
+    #[serde(default)]
+    pub float_format: Option<FloatFormat>,
+    /// Add the clock cycle of every sample
+    #[serde(default)]
+    pub cycles: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// Only written by builds with the `parquet` feature
+    Parquet,
+}
+
+impl ExportFormat {
+    /// Format named by the extension of a file, CSV unless it is `.json`
+    /// or `.parquet`.
+    pub fn from_path(path: &str) -> Self {
+        let path = path.to_lowercase();
+        if path.ends_with(".json") {
+            Self::Json
+        } else if path.ends_with(".parquet") {
+            Self::Parquet
+        } else {
+            Self::Csv
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalColumn {
+    pub path: String,
+    pub radix: String,
+    /// None before the signal's first change
+    pub values: Vec<Option<String>>,
+}
+
+/// Values of the exported signals at every sample time, one column per
+/// signal, the layout a dataframe is built from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalTable {
+    pub timescale: String,
+    /// Clock the samples were taken on, or the one the cycles count
+    pub clock: Option<String>,
+    pub times: Vec<u64>,
+    /// Clock cycle of every sample, when requested and the waveform has a
+    /// clock
+    pub cycles: Option<Vec<i64>>,
+    pub columns: Vec<SignalColumn>,
+}
+
+/// Path, width and default radix of a signal, from the first var using it.
+fn describe_signal(session: &WaveformSession, index: usize) -> Result<(String, u32, &str), String> {
+    let hierarchy = session.waveform.hierarchy();
+    let var = hierarchy
+        .all_vars()
+        .map(|var_ref| &hierarchy[var_ref])
+        .find(|var| var.signal_ref().index() == index)
+        .ok_or_else(|| format!("Failed to export signals: signal {} not found", index))?;
+    let width = match var.signal_encoding(hierarchy) {
+        SignalEncoding::BitVector(width) => width,
+        _ => 0,
+    };
+    let radix = if var.var_type() == VarType::Integer {
+        "decimal"
+    } else if width > 1 {
+        "hex"
+    } else {
+        "binary"
+    };
+    Ok((var.full_name(hierarchy), width, radix))
+}
+
+/// Text of a value in a radix. Values with unknown bits, reals and
+/// strings, and SAPHO values that cannot be decoded are kept as they are.
+pub fn format_radix(
+    bits: &str,
+    width: u32,
+    radix: &str,
+    float_format: Option<FloatFormat>,
+) -> String {
+    if matches!(radix, "vector" | "complex" | "float") {
+        let bits = format!("{:0>1$}", bits, width as usize);
+        return format_value(&bits, radix, float_format).unwrap_or(bits);
+    }
+    if width == 0 || !bits.chars().all(|c| c == '0' || c == '1') {
+        return bits.to_string();
+    }
+    let bits = format!("{:0>1$}", bits, width as usize);
+    match radix {
+        "hex" => {
+            let padded = format!("{:0>1$}", bits, bits.len().div_ceil(4) * 4);
+            padded
+                .as_bytes()
+                .chunks(4)
+                .map(|nibble| {
+                    let nibble = std::str::from_utf8(nibble).unwrap();
+                    format!("{:X}", u8::from_str_radix(nibble, 2).unwrap())
+                })
+                .collect()
+        }
+        "decimal" | "signed decimal" if bits.len() <= 128 => {
+            let unsigned = u128::from_str_radix(&bits, 2).unwrap();
+            if radix == "signed decimal" && bits.starts_with('1') {
+                // Two's complement of up to 128 bits
+                let magnitude = (!unsigned).wrapping_add(1) & (u128::MAX >> (128 - bits.len()));
+                format!("-{}", magnitude)
+            } else {
+                unsigned.to_string()
+            }
+        }
+        _ => bits,
+    }
+}
+
+/// Times in `[from, to]` the signals are sampled at.
+fn sample_times(
+    changes: &[SignalChanges],
+    clock: Option<&SignalChanges>,
+    sampling: Sampling,
+    from: u64,
+    to: u64,
+) -> Vec<u64> {
+    let in_range = |time: &u64| (from..=to).contains(time);
+    let mut times: Vec<u64> = match (sampling, clock) {
+        (Sampling::Changes, _) | (_, None) => std::iter::once(from)
+            .chain(changes.iter().flat_map(|c| c.times.iter().copied()))
+            .filter(in_range)
+            .collect(),
+        (edge, Some(clock)) => {
+            let (before, after) = match edge {
+                Sampling::FallingEdge => ("1", "0"),
+                _ => ("0", "1"),
+            };
+            clock
+                .values
+                .windows(2)
+                .zip(&clock.times[1..])
+                .filter(|(pair, _)| pair[0] == before && pair[1] == after)
+                .map(|(_, &time)| time)
+                .filter(in_range)
+                .collect()
+        }
+    };
+    times.sort_unstable();
+    times.dedup();
+    times
+}
+
+/// Sample the signals of a request. A sample holds the values in effect at
+/// its time, including the changes made at that time.
+pub fn sample_signals(
+    session: &mut WaveformSession,
+    request: &SignalExportRequest,
+) -> Result<SignalTable, String> {
+    let sampling = request.sampling;
+    let from = request.from.unwrap_or(0);
+    let to = request.to.unwrap_or_else(|| session.end_time());
+    if from > to {
+        return Err(format!(
+            "Failed to export signals: range starts at {} after its end {}",
+            from, to
+        ));
+    }
+
+    // Clocks are only looked for when the samples or the cycles need one
+    let needs_clock = sampling != Sampling::Changes && request.clock_ref.is_none();
+    let detected = if needs_clock || request.cycles {
+        detect_clocks(session)?
+    } else {
+        Vec::new()
+    };
+    let clock = match request.clock_ref {
+        Some(index) => Some((describe_signal(session, index)?.0, index)),
+        None => detected
+            .first()
+            .map(|clock| (clock.path.clone(), clock.signal_ref)),
+    };
+    let cycle_clock = clock
+        .as_ref()
+        .filter(|_| request.cycles)
+        .and_then(|(_, index)| detected.iter().find(|c| c.signal_ref == *index));
+    if sampling != Sampling::Changes && clock.is_none() {
+        return Err("Failed to export signals: no clock to sample on".to_string());
+    }
+
+    let mut columns = Vec::with_capacity(request.signals.len());
+    let mut changes = Vec::with_capacity(request.signals.len());
+    for signal in &request.signals {
+        let (path, width, default_radix) = describe_signal(session, signal.signal_ref)?;
+        let radix = signal.radix.as_deref().unwrap_or(default_radix).to_string();
+        let float_format = signal.float_format.or(request.float_format);
+        columns.push((path, width, radix, float_format));
+        changes.push(session.signal_changes(signal.signal_ref)?);
+    }
+    let clock_changes = match (&clock, sampling) {
+        (Some((_, index)), Sampling::RisingEdge | Sampling::FallingEdge) => {
+            Some(session.signal_changes(*index)?)
+        }
+        _ => None,
+    };
+
+    let times = sample_times(&changes, clock_changes.as_ref(), sampling, from, to);
+    let columns = columns
+        .into_iter()
+        .zip(&changes)
+        .map(|((path, width, radix, float_format), changes)| {
+            let values = times
+                .iter()
+                .map(|&time| {
+                    let pos = changes
+                        .times
+                        .partition_point(|&t| t <= time)
+                        .checked_sub(1)?;
+                    Some(format_radix(
+                        &changes.values[pos],
+                        width,
+                        &radix,
+                        float_format,
+                    ))
+                })
+                .collect();
+            SignalColumn {
+                path,
+                radix,
+                values,
+            }
+        })
+        .collect();
+
+    Ok(SignalTable {
+        timescale: timescale_to_string(session.waveform.hierarchy()),
+        clock: clock.map(|(path, _)| path),
+        cycles: cycle_clock.map(|clock| times.iter().map(|&t| clock.cycle_at(t)).collect()),
+        times,
+        columns,
+    })
+}
+
+/// Quote a CSV field when it holds a comma, quote or line break, as the
+/// `[a, b]` text of SAPHO vectors does.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// CSV with a header line of `time`, `cycle` when known, and the signal
+/// paths. Values before a signal's first change are left empty.
+pub fn table_to_csv(table: &SignalTable) -> String {
+    let mut csv = String::from("time");
+    if table.cycles.is_some() {
+        csv.push_str(",cycle");
+    }
+    for column in &table.columns {
+        csv.push(',');
+        csv.push_str(&csv_field(&column.path));
+    }
+    csv.push('\n');
+
+    for (i, time) in table.times.iter().enumerate() {
+        let _ = write!(csv, "{}", time);
+        if let Some(cycles) = &table.cycles {
+            let _ = write!(csv, ",{}", cycles[i]);
+        }
+        for column in &table.columns {
+            csv.push(',');
+            csv.push_str(&csv_field(column.values[i].as_deref().unwrap_or("")));
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn table_to_json(table: &SignalTable) -> Result<String, String> {
+    serde_json::to_string_pretty(table).map_err(|e| format!("Failed to export signals: {}", e))
+}
+
+/// Parquet file with an INT64 `time` column, `cycle` when known, and one
+/// optional UTF-8 column per signal holding the same text as the CSV.
+#[cfg(feature = "parquet")]
+pub fn table_to_parquet(table: &SignalTable) -> Result<Vec<u8>, String> {
+    use parquet::basic::{LogicalType, Repetition, Type as PhysicalType};
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::types::Type;
+    use std::sync::Arc;
+
+    let error = |e: parquet::errors::ParquetError| format!("Failed to export signals: {}", e);
+    let int_column = |name: &str| {
+        Type::primitive_type_builder(name, PhysicalType::INT64)
+            .with_repetition(Repetition::REQUIRED)
+            .build()
+            .map(Arc::new)
+    };
+    let mut fields = vec![int_column("time").map_err(error)?];
+    if table.cycles.is_some() {
+        fields.push(int_column("cycle").map_err(error)?);
+    }
+    for column in &table.columns {
+        let field = Type::primitive_type_builder(&column.path, PhysicalType::BYTE_ARRAY)
+            .with_repetition(Repetition::OPTIONAL)
+            .with_logical_type(Some(LogicalType::String))
+            .build()
+            .map_err(error)?;
+        fields.push(Arc::new(field));
+    }
+    let schema = Type::group_type_builder("signals")
+        .with_fields(fields)
+        .build()
+        .map_err(error)?;
+
+    let mut out = Vec::new();
+    let mut writer = SerializedFileWriter::new(
+        &mut out,
+        Arc::new(schema),
+        Arc::new(WriterProperties::builder().build()),
+    )
+    .map_err(error)?;
+    let mut row_group = writer.next_row_group().map_err(error)?;
+
+    let times: Vec<i64> = table.times.iter().map(|&time| time as i64).collect();
+    let mut int_columns = std::iter::once(times).chain(table.cycles.clone());
+    let mut text_columns = table.columns.iter();
+    while let Some(mut column) = row_group.next_column().map_err(error)? {
+        if let Some(values) = int_columns.next() {
+            column
+                .typed::<Int64Type>()
+                .write_batch(&values, None, None)
+                .map_err(error)?;
+        } else if let Some(signal) = text_columns.next() {
+            let levels: Vec<i16> = signal.values.iter().map(|v| v.is_some() as i16).collect();
+            let values: Vec<ByteArray> = signal
+                .values
+                .iter()
+                .flatten()
+                .map(|value| ByteArray::from(value.as_str()))
+                .collect();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&values, Some(&levels), None)
+                .map_err(error)?;
+        }
+        column.close().map_err(error)?;
+    }
+    row_group.close().map_err(error)?;
+    writer.close().map_err(error)?;
+    Ok(out)
+}
+
+/// Contents of an export file in `format`.
+pub fn write_table(table: &SignalTable, format: ExportFormat) -> Result<Vec<u8>, String> {
+    match format {
+        ExportFormat::Csv => Ok(table_to_csv(table).into_bytes()),
+        ExportFormat::Json => table_to_json(table).map(String::into_bytes),
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => table_to_parquet(table),
+        #[cfg(not(feature = "parquet"))]
+        ExportFormat::Parquet => {
+            Err("Failed to export signals: this build was made without Parquet support".to_string())
+        }
+    }
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Write the sampled values of signals to `dst` as CSV, JSON or Parquet
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn export_signals(
+    session_id: u32,
+    request: SignalExportRequest,
+    format: ExportFormat,
+    dst: String,
+    state: State<'_, WaveformManager>,
+) -> Result<(), String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut session = session.lock().unwrap();
+        let table = sample_signals(&mut session, &request)?;
+        let contents = write_table(&table, format)?;
+        std::fs::write(&dst, contents).map_err(|e| format!("Failed to write {}: {}", dst, e))
+    })
+    .await
+    .map_err(|e| format!("Failed to export signals: {}", e))?
+}
@@ -0,0 +1,764 @@
+use crate::sapho_names::{classify, owning_processor, processor_scopes, SignalKind};
+use crate::translation::{tables_key, var_paths};
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use serde::Serialize;
+use std::collections::HashMap;
+#[cfg(feature = "gui")]
+use tauri::State;
+use wellen::{Hierarchy, SignalRef, SignalValueRef, VarRef};
+
+/// Width given to expressions built only from literals, as in Verilog.
+const UNSIZED_WIDTH: u32 = 32;
+const MAX_WIDTH: u32 = 128;
+
+/// A virtual signal computed from an expression over real signals. Values
+/// are bit strings of `width` bits, all `x` while any operand is unknown.
+/// Testbench ports (`in_sim_N`, `out_sig_N`) hold two's complement numbers
+/// and are signed; as in Verilog, an expression is only signed when all of
+/// its operands are.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DerivedSignal {
+    pub name: String,
+    pub width: u32,
+    pub times: Vec<u64>,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnaryOp {
+    Not,
+    Invert,
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// Binding strength, following C and Verilog.
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 10,
+            BinaryOp::Add | BinaryOp::Sub => 9,
+            BinaryOp::Shl | BinaryOp::Shr => 8,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 7,
+            BinaryOp::Eq | BinaryOp::Ne => 6,
+            BinaryOp::BitAnd => 5,
+            BinaryOp::BitXor => 4,
+            BinaryOp::BitOr => 3,
+            BinaryOp::And => 2,
+            BinaryOp::Or => 1,
+        }
+    }
+
+    fn is_boolean(self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge
+                | BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::And
+                | BinaryOp::Or
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(u128),
+    Unary(UnaryOp),
+    Binary(BinaryOp),
+    Open,
+    Close,
+    OpenBracket,
+    CloseBracket,
+    Colon,
+}
+
+#[derive(Debug)]
+enum Expr {
+    /// Operand number in `Parser::names`, optionally sliced to `[msb:lsb]`
+    Signal(usize, Option<(u32, u32)>),
+    Literal(u128),
+    /// Values a named symbol stands for, such as every opcode address of a
+    /// mnemonic. Only valid as a side of `==` or `!=`, meaning "any of".
+    Set(Vec<i64>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+/// Mnemonic addresses of each processor, keyed like
+/// [`crate::translation::SymbolTables`].
+pub type ProcessorSymbols = HashMap<Option<String>, HashMap<String, Vec<i64>>>;
+
+/// What a name of an expression stands for: a signal of a processor, or a
+/// symbol looked up in the tables of the signal it is compared with.
+enum Operand {
+    Signal(Option<String>),
+    Symbol,
+}
+
+/// Processor compared with a symbol when the other side holds no signal.
+const NO_PROCESSOR: &Option<String> = &None;
+
+fn parse_number(text: &str) -> Result<u128, String> {
+    let lower = text.to_ascii_lowercase().replace('_', "");
+    let parsed = if let Some(hex) = lower.strip_prefix("0x") {
+        u128::from_str_radix(hex, 16)
+    } else if let Some(bits) = lower.strip_prefix("0b") {
+        u128::from_str_radix(bits, 2)
+    } else {
+        lower.parse()
+    };
+    parsed.map_err(|_| format!("Invalid number '{}'", text))
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | '$' | '.'))
+            {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens.push(Token::Number(parse_number(&text)?));
+            continue;
+        }
+
+        let (token, len) = match (c, next) {
+            ('&', Some('&')) => (Token::Binary(BinaryOp::And), 2),
+            ('|', Some('|')) => (Token::Binary(BinaryOp::Or), 2),
+            ('=', Some('=')) => (Token::Binary(BinaryOp::Eq), 2),
+            ('!', Some('=')) => (Token::Binary(BinaryOp::Ne), 2),
+            ('<', Some('=')) => (Token::Binary(BinaryOp::Le), 2),
+            ('>', Some('=')) => (Token::Binary(BinaryOp::Ge), 2),
+            ('<', Some('<')) => (Token::Binary(BinaryOp::Shl), 2),
+            ('>', Some('>')) => (Token::Binary(BinaryOp::Shr), 2),
+            ('<', _) => (Token::Binary(BinaryOp::Lt), 1),
+            ('>', _) => (Token::Binary(BinaryOp::Gt), 1),
+            ('&', _) => (Token::Binary(BinaryOp::BitAnd), 1),
+            ('|', _) => (Token::Binary(BinaryOp::BitOr), 1),
+            ('^', _) => (Token::Binary(BinaryOp::BitXor), 1),
+            ('*', _) => (Token::Binary(BinaryOp::Mul), 1),
+            ('/', _) => (Token::Binary(BinaryOp::Div), 1),
+            ('%', _) => (Token::Binary(BinaryOp::Rem), 1),
+            ('+', _) => (Token::Binary(BinaryOp::Add), 1),
+            // Decided by the parser, which knows whether an operand came before
+            ('-', _) => (Token::Binary(BinaryOp::Sub), 1),
+            ('!', _) => (Token::Unary(UnaryOp::Not), 1),
+            ('~', _) => (Token::Unary(UnaryOp::Invert), 1),
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            ('[', _) => (Token::OpenBracket, 1),
+            (']', _) => (Token::CloseBracket, 1),
+            (':', _) => (Token::Colon, 1),
+            _ => return Err(format!("Unexpected character '{}'", c)),
+        };
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Signal names in order of first use
+    names: Vec<String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(format!("Expected {}", what)),
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, String> {
+        match self.next() {
+            Some(Token::Number(n)) if n <= u32::MAX as u128 => Ok(n as u32),
+            _ => Err("Expected a bit index".to_string()),
+        }
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<Expr, String> {
+        let mut left = self.operand()?;
+        while let Some(Token::Binary(op)) = self.peek() {
+            let op = *op;
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let right = self.expression(op.precedence() + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Literal(n)),
+            Some(Token::Unary(op)) => Ok(Expr::Unary(op, Box::new(self.operand()?))),
+            Some(Token::Binary(BinaryOp::Sub)) => {
+                Ok(Expr::Unary(UnaryOp::Negate, Box::new(self.operand()?)))
+            }
+            Some(Token::Open) => {
+                let inner = self.expression(0)?;
+                self.expect(Token::Close, "')'")?;
+                Ok(inner)
+            }
+            Some(Token::Name(name)) => {
+                let operand = match self.names.iter().position(|n| *n == name) {
+                    Some(operand) => operand,
+                    None => {
+                        self.names.push(name);
+                        self.names.len() - 1
+                    }
+                };
+
+                let mut slice = None;
+                if self.peek() == Some(&Token::OpenBracket) {
+                    self.pos += 1;
+                    let msb = self.number()?;
+                    let lsb = if self.peek() == Some(&Token::Colon) {
+                        self.pos += 1;
+                        self.number()?
+                    } else {
+                        msb
+                    };
+                    self.expect(Token::CloseBracket, "']'")?;
+                    slice = Some((msb, lsb));
+                }
+                Ok(Expr::Signal(operand, slice))
+            }
+            Some(_) => Err("Expected a signal, number or '('".to_string()),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+
+/// Parse an expression, returning its tree and the signal names it uses.
+fn parse(expression: &str) -> Result<(Expr, Vec<String>), String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        pos: 0,
+        names: Vec::new(),
+    };
+    let expr = parser.expression(0)?;
+    if parser.pos < parser.tokens.len() {
+        return Err("Unexpected input after the end of the expression".to_string());
+    }
+    Ok((expr, parser.names))
+}
+
+/// Find a var by full path, or by a unique trailing part of its path. None
+/// when no var matches.
+fn resolve_signal(hierarchy: &Hierarchy, name: &str) -> Result<Option<VarRef>, String> {
+    let suffix = format!(".{}", name);
+    let mut found: Vec<(VarRef, String)> = Vec::new();
+    for var_ref in hierarchy.all_vars() {
+        let path = hierarchy[var_ref].full_name(hierarchy);
+        if path == name {
+            return Ok(Some(var_ref));
+        }
+        let signal_ref = hierarchy[var_ref].signal_ref();
+        if path.ends_with(&suffix)
+            && !found
+                .iter()
+                .any(|(other, _)| hierarchy[*other].signal_ref() == signal_ref)
+        {
+            found.push((var_ref, path));
+        }
+    }
+
+    match found.len() {
+        0 => Ok(None),
+        1 => Ok(Some(found[0].0)),
+        _ => Err(format!(
+            "Signal name {} is ambiguous: {}",
+            name,
+            found
+                .iter()
+                .map(|(_, path)| path.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+fn mask(width: u32) -> u128 {
+    if width >= MAX_WIDTH {
+        u128::MAX
+    } else {
+        (1u128 << width) - 1
+    }
+}
+
+/// Bit width of an expression. Literals take the width of what they are
+/// combined with, and an expression of literals only is 32 bits wide.
+fn width_of(expr: &Expr, widths: &[u32]) -> u32 {
+    match expr {
+        Expr::Signal(operand, None) => widths[*operand],
+        Expr::Signal(_, Some((msb, lsb))) => msb - lsb + 1,
+        Expr::Literal(_) | Expr::Set(_) => 0,
+        Expr::Unary(UnaryOp::Not, _) => 1,
+        Expr::Unary(_, inner) => width_of(inner, widths),
+        Expr::Binary(op, _, _) if op.is_boolean() => 1,
+        Expr::Binary(_, left, right) => width_of(left, widths).max(width_of(right, widths)),
+    }
+}
+
+fn sized(width: u32) -> u32 {
+    if width == 0 {
+        UNSIZED_WIDTH
+    } else {
+        width
+    }
+}
+
+/// Whether an expression is evaluated as signed: literals are, slices and
+/// comparisons are not, and anything else is when all of its operands are.
+fn is_signed(expr: &Expr, signed: &[bool]) -> bool {
+    match expr {
+        Expr::Signal(operand, None) => signed[*operand],
+        Expr::Signal(_, Some(_)) => false,
+        Expr::Literal(_) | Expr::Set(_) => true,
+        Expr::Unary(UnaryOp::Not, _) => false,
+        Expr::Unary(_, inner) => is_signed(inner, signed),
+        Expr::Binary(op, _, _) if op.is_boolean() => false,
+        Expr::Binary(_, left, right) => is_signed(left, signed) && is_signed(right, signed),
+    }
+}
+
+/// Keep the low `width` bits of a value, sign-extending them when `signed`
+/// so that the u128 holds the value as an i128.
+fn fit(value: u128, width: u32, signed: bool) -> u128 {
+    if width == 0 || width >= MAX_WIDTH {
+        return value;
+    }
+    let value = value & mask(width);
+    if signed && value >> (width - 1) & 1 == 1 {
+        value | !mask(width)
+    } else {
+        value
+    }
+}
+
+/// Evaluate with Verilog-like semantics: signed expressions hold their value
+/// sign-extended, so that the u128 reads as an i128, and unsigned operands
+/// are zero-extended. None stands for a value with unknown bits, which
+/// poisons the result except where `&&`/`||` are already decided by their
+/// other side.
+fn eval(expr: &Expr, values: &[Option<u128>], widths: &[u32], signed: &[bool]) -> Option<u128> {
+    let eval_side = |side: &Expr| eval(side, values, widths, signed);
+    let result = match expr {
+        Expr::Signal(operand, None) => fit(values[*operand]?, widths[*operand], signed[*operand]),
+        Expr::Signal(operand, Some((msb, lsb))) => (values[*operand]? >> lsb) & mask(msb - lsb + 1),
+        Expr::Literal(value) => *value,
+        // Symbols only have a value when compared with a signal
+        Expr::Set(_) => return None,
+        Expr::Unary(op, inner) => {
+            let value = eval_side(inner)?;
+            match op {
+                UnaryOp::Not => (value == 0) as u128,
+                UnaryOp::Invert => !value,
+                UnaryOp::Negate => value.wrapping_neg(),
+            }
+        }
+        Expr::Binary(BinaryOp::And, left, right) => match (eval_side(left), eval_side(right)) {
+            (Some(0), _) | (_, Some(0)) => 0,
+            (Some(_), Some(_)) => 1,
+            _ => return None,
+        },
+        Expr::Binary(BinaryOp::Or, left, right) => match (eval_side(left), eval_side(right)) {
+            (Some(l), _) if l != 0 => 1,
+            (_, Some(r)) if r != 0 => 1,
+            (Some(_), Some(_)) => 0,
+            _ => return None,
+        },
+        Expr::Binary(op, left, right)
+            if matches!(**left, Expr::Set(_)) || matches!(**right, Expr::Set(_)) =>
+        {
+            let (set, other) = match (&**left, &**right) {
+                (Expr::Set(set), other) | (other, Expr::Set(set)) => (set, other),
+                _ => unreachable!(),
+            };
+            let value = eval_side(other)?;
+            let other_mask = mask(sized(width_of(other, widths)));
+            let found = set
+                .iter()
+                .any(|&candidate| candidate as i128 as u128 & other_mask == value & other_mask);
+            (found == (*op == BinaryOp::Eq)) as u128
+        }
+        Expr::Binary(op, left, right) => {
+            // Both sides are read as signed only when both of them are;
+            // otherwise a signed side is zero-extended from its own width
+            let both_signed = is_signed(left, signed) && is_signed(right, signed);
+            let side = |side: &Expr| {
+                let value = eval_side(side)?;
+                Some(if both_signed {
+                    value
+                } else {
+                    fit(value, width_of(side, widths), false)
+                })
+            };
+            let (l, r) = (side(left)?, side(right)?);
+            let (sl, sr) = (l as i128, r as i128);
+            match op {
+                BinaryOp::Mul => l.wrapping_mul(r),
+                BinaryOp::Div if both_signed => sl.checked_div(sr)? as u128,
+                BinaryOp::Rem if both_signed => sl.checked_rem(sr)? as u128,
+                BinaryOp::Div => l.checked_div(r)?,
+                BinaryOp::Rem => l.checked_rem(r)?,
+                BinaryOp::Add => l.wrapping_add(r),
+                BinaryOp::Sub => l.wrapping_sub(r),
+                BinaryOp::Shl => l.checked_shl(r.try_into().ok()?).unwrap_or(0),
+                // A logical shift, of the bits of the left side's width
+                BinaryOp::Shr => fit(l, width_of(left, widths), false)
+                    .checked_shr(r.try_into().ok()?)
+                    .unwrap_or(0),
+                BinaryOp::Lt if both_signed => (sl < sr) as u128,
+                BinaryOp::Le if both_signed => (sl <= sr) as u128,
+                BinaryOp::Gt if both_signed => (sl > sr) as u128,
+                BinaryOp::Ge if both_signed => (sl >= sr) as u128,
+                BinaryOp::Lt => (l < r) as u128,
+                BinaryOp::Le => (l <= r) as u128,
+                BinaryOp::Gt => (l > r) as u128,
+                BinaryOp::Ge => (l >= r) as u128,
+                BinaryOp::Eq => (l == r) as u128,
+                BinaryOp::Ne => (l != r) as u128,
+                BinaryOp::BitAnd => l & r,
+                BinaryOp::BitXor => l ^ r,
+                BinaryOp::BitOr => l | r,
+                BinaryOp::And | BinaryOp::Or => unreachable!(),
+            }
+        }
+    };
+    Some(fit(result, width_of(expr, widths), is_signed(expr, signed)))
+}
+
+/// Numeric value of a bit vector, None if any bit is unknown.
+fn bits_to_u128(value: &SignalValueRef) -> Option<u128> {
+    value
+        .to_bit_string()?
+        .chars()
+        .try_fold(0u128, |acc, bit| match bit {
+            '0' | 'l' | 'L' => Some(acc << 1),
+            '1' | 'h' | 'H' => Some(acc << 1 | 1),
+            _ => None,
+        })
+}
+
+/// Processor of the first signal of an expression.
+fn expr_processor<'a>(expr: &Expr, operands: &'a [Operand]) -> Option<&'a Option<String>> {
+    match expr {
+        Expr::Signal(operand, _) => match &operands[*operand] {
+            Operand::Signal(processor) => Some(processor),
+            Operand::Symbol => None,
+        },
+        Expr::Unary(_, inner) => expr_processor(inner, operands),
+        Expr::Binary(_, left, right) => {
+            expr_processor(left, operands).or_else(|| expr_processor(right, operands))
+        }
+        _ => None,
+    }
+}
+
+/// Replace the operands that named a symbol with its values, checking that
+/// each one is directly compared with `==` or `!=`. `compared_with` is the
+/// processor of the other side of the comparison, whose tables the symbol
+/// is looked up in.
+fn bind_symbols(
+    expr: &mut Expr,
+    operands: &[Operand],
+    symbols: &ProcessorSymbols,
+    names: &[String],
+    compared_with: Option<&Option<String>>,
+) -> Result<(), String> {
+    match expr {
+        Expr::Signal(operand, slice) => match (&operands[*operand], compared_with) {
+            (Operand::Symbol, Some(processor)) if slice.is_none() => {
+                let name = &names[*operand];
+                let values = tables_key(symbols, processor.as_deref())
+                    .and_then(|key| symbols[&key].get(name))
+                    .ok_or_else(|| {
+                        format!(
+                            "{} is not a mnemonic of {}",
+                            name,
+                            processor.as_deref().unwrap_or("the waveform")
+                        )
+                    })?;
+                *expr = Expr::Set(values.clone());
+                Ok(())
+            }
+            (Operand::Symbol, _) => Err(format!(
+                "{} can only be compared with == or !=",
+                names[*operand]
+            )),
+            (Operand::Signal(_), _) => Ok(()),
+        },
+        Expr::Unary(_, inner) => bind_symbols(inner, operands, symbols, names, None),
+        Expr::Binary(op, left, right) => {
+            let compared = matches!(op, BinaryOp::Eq | BinaryOp::Ne);
+            if let (Expr::Signal(l, _), Expr::Signal(r, _)) = (&**left, &**right) {
+                if compared
+                    && matches!(operands[*l], Operand::Symbol)
+                    && matches!(operands[*r], Operand::Symbol)
+                {
+                    return Err(format!(
+                        "{} and {} are both mnemonics, compare a signal with a mnemonic",
+                        names[*l], names[*r]
+                    ));
+                }
+            }
+            let processor = |side: &Expr| {
+                compared.then(|| expr_processor(side, operands).unwrap_or(NO_PROCESSOR))
+            };
+            let (left_with, right_with) = (processor(right), processor(left));
+            bind_symbols(left, operands, symbols, names, left_with)?;
+            bind_symbols(right, operands, symbols, names, right_with)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Evaluate `expression` at every time one of its signals changes and keep
+/// the points where the result changes.
+pub fn evaluate(session: &mut WaveformSession, expression: &str) -> Result<DerivedSignal, String> {
+    evaluate_with_symbols(session, expression, &HashMap::new())
+}
+
+/// Same as [`evaluate`], where names that are not signals may also be one of
+/// `symbols`, e.g. `valr2 == OUT` with `OUT` mapped to the opcode addresses
+/// of the processor `valr2` belongs to.
+pub fn evaluate_with_symbols(
+    session: &mut WaveformSession,
+    expression: &str,
+    symbols: &ProcessorSymbols,
+) -> Result<DerivedSignal, String> {
+    let (mut expr, names) = parse(expression).map_err(|e| format!("Invalid expression: {}", e))?;
+    let scopes = processor_scopes(&var_paths(session));
+
+    // Symbols keep their operand number, with no signal and no changes
+    let mut signal_refs: Vec<Option<SignalRef>> = Vec::new();
+    let mut operands = Vec::new();
+    let mut widths = Vec::new();
+    let mut signed = Vec::new();
+    let mut ranges = Vec::new();
+    {
+        let hierarchy = session.waveform.hierarchy();
+        for name in &names {
+            let var_ref = match resolve_signal(hierarchy, name)? {
+                Some(var_ref) => var_ref,
+                None if symbols.values().any(|table| table.contains_key(name)) => {
+                    signal_refs.push(None);
+                    operands.push(Operand::Symbol);
+                    widths.push(0);
+                    signed.push(true);
+                    ranges.push(None);
+                    continue;
+                }
+                None => return Err(format!("Signal {} not found", name)),
+            };
+            let var = &hierarchy[var_ref];
+            if matches!(
+                var.var_type(),
+                wellen::VarType::Real | wellen::VarType::String
+            ) {
+                return Err(format!("Signal {} is not a bit vector", name));
+            }
+            let width = var.length(hierarchy).unwrap_or(1);
+            if width > MAX_WIDTH {
+                return Err(format!("Signal {} is wider than {} bits", name, MAX_WIDTH));
+            }
+            let path = var.full_name(hierarchy);
+            signal_refs.push(Some(var.signal_ref()));
+            operands.push(Operand::Signal(
+                owning_processor(&scopes, &path).map(str::to_string),
+            ));
+            widths.push(width);
+            signed.push(matches!(
+                classify(var.name(hierarchy), &path).kind,
+                SignalKind::Input | SignalKind::Output
+            ));
+            ranges.push(var.index().map(|index| (index.msb(), index.lsb())));
+        }
+    }
+    bind_symbols(&mut expr, &operands, symbols, &names, None)?;
+    normalize_slices(&mut expr, &ranges, &widths, &names)?;
+
+    let indices: Vec<usize> = signal_refs.iter().flatten().map(|s| s.index()).collect();
+    session.load_signals(&indices)?;
+
+    let operand_changes: Vec<Vec<(u64, Option<u128>)>> = signal_refs
+        .iter()
+        .map(|signal_ref| {
+            let Some(signal_ref) = signal_ref else {
+                return Ok(Vec::new());
+            };
+            Ok(session
+                .changes_since(*signal_ref, 0)?
+                .map(|(time, value)| (time, bits_to_u128(&value)))
+                .collect())
+        })
+        .collect::<Result<_, String>>()?;
+
+    let mut steps: Vec<u64> = operand_changes
+        .iter()
+        .flat_map(|changes| changes.iter().map(|(time, _)| *time))
+        .collect();
+    steps.sort_unstable();
+    steps.dedup();
+
+    let width = sized(width_of(&expr, &widths));
+    let mut derived = DerivedSignal {
+        name: expression.trim().to_string(),
+        width,
+        times: Vec::new(),
+        values: Vec::new(),
+    };
+
+    let mut cursors = vec![0; operand_changes.len()];
+    let mut values: Vec<Option<u128>> = vec![None; operand_changes.len()];
+    for time in steps {
+        for (operand, changes) in operand_changes.iter().enumerate() {
+            while let Some(&(change_time, value)) = changes.get(cursors[operand]) {
+                if change_time > time {
+                    break;
+                }
+                values[operand] = value;
+                cursors[operand] += 1;
+            }
+        }
+
+        let value = match eval(&expr, &values, &widths, &signed) {
+            Some(value) => format!("{:0width$b}", value & mask(width), width = width as usize),
+            None => "x".repeat(width as usize),
+        };
+        if derived.values.last() != Some(&value) {
+            derived.times.push(time);
+            derived.values.push(value);
+        }
+    }
+
+    Ok(derived)
+}
+
+/// Turn `[msb:lsb]` slices written with the var's declared indices into
+/// bit positions counted from its least significant bit.
+fn normalize_slices(
+    expr: &mut Expr,
+    ranges: &[Option<(i64, i64)>],
+    widths: &[u32],
+    names: &[String],
+) -> Result<(), String> {
+    match expr {
+        Expr::Signal(operand, Some((msb, lsb))) => {
+            let position = |bit: u32| -> Option<u32> {
+                let bit = bit as i64;
+                let position = match ranges[*operand] {
+                    Some((decl_msb, decl_lsb)) if decl_msb >= decl_lsb => bit - decl_lsb,
+                    Some((_, decl_lsb)) => decl_lsb - bit,
+                    None => bit,
+                };
+                u32::try_from(position)
+                    .ok()
+                    .filter(|&position| position < widths[*operand])
+            };
+            match (position(*msb), position(*lsb)) {
+                (Some(high), Some(low)) if high >= low => {
+                    (*msb, *lsb) = (high, low);
+                    Ok(())
+                }
+                _ => Err(format!(
+                    "Bit range [{}:{}] is out of range for {}",
+                    msb, lsb, names[*operand]
+                )),
+            }
+        }
+        Expr::Unary(_, inner) => normalize_slices(inner, ranges, widths, names),
+        Expr::Binary(_, left, right) => {
+            normalize_slices(left, ranges, widths, names)?;
+            normalize_slices(right, ranges, widths, names)
+        }
+        _ => Ok(()),
+    }
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Evaluate an expression such as `clk & req_in_sim_1` or `pointeri > 4`
+/// over an open session and return it as a virtual signal
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn evaluate_derived_signal(
+    session_id: u32,
+    expression: String,
+    state: State<'_, WaveformManager>,
+) -> Result<DerivedSignal, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        evaluate(&mut session.lock().unwrap(), &expression)
+    })
+    .await
+    .map_err(|e| format!("Failed to evaluate expression: {}", e))?
+}
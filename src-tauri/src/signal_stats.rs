@@ -0,0 +1,339 @@
+use crate::sapho_float::{decode_pair, FloatFormat, SaphoFloat};
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::{value_to_f64, value_to_string, WaveformSession};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use tauri::State;
+use wellen::SignalValueRef;
+
+/// Number of histogram bins when the caller does not ask for a count.
+const DEFAULT_BINS: usize = 16;
+
+/// How the bits of a vector are read as a number, named after the radixes
+/// the viewer offers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Unsigned,
+    Signed,
+    /// A SAPHO float of the processor's format
+    Float(FloatFormat),
+    /// SAPHO `[a, b]` vectors, summarized by their magnitude and per component
+    Vector,
+    /// SAPHO `a + j b` complex values, summarized like vectors
+    Complex,
+}
+
+impl Encoding {
+    /// `binary`, `hex` and `decimal` read vectors as unsigned numbers and
+    /// `signed decimal` as two's complement. `float` needs the processor's
+    /// float format.
+    pub fn from_radix(radix: &str, float_format: Option<FloatFormat>) -> Result<Self, String> {
+        match radix {
+            "binary" | "hex" | "decimal" => Ok(Self::Unsigned),
+            "signed decimal" => Ok(Self::Signed),
+            "float" => float_format.map(Self::Float).ok_or_else(|| {
+                "Failed to compute statistics: float values need the processor's float format"
+                    .to_string()
+            }),
+            "vector" => Ok(Self::Vector),
+            "complex" => Ok(Self::Complex),
+            other => Err(format!(
+                "Failed to compute statistics: {} values are not numbers",
+                other
+            )),
+        }
+    }
+
+    /// Names of the components of pair encodings, none for single numbers.
+    fn component_names(self) -> &'static [&'static str] {
+        match self {
+            Self::Vector => &["first", "second"],
+            Self::Complex => &["real", "imaginary"],
+            _ => &[],
+        }
+    }
+
+    /// Both components of a vector or complex value.
+    fn decode_components(self, value: &SignalValueRef) -> Option<[f64; 2]> {
+        match self {
+            Self::Vector | Self::Complex => {
+                let (_, first, second) = decode_pair(&value.to_bit_string()?).ok()?;
+                Some([first.to_f64(), second.to_f64()])
+            }
+            _ => None,
+        }
+    }
+
+    fn decode(self, value: &SignalValueRef) -> Option<f64> {
+        match (self, value) {
+            (Self::Signed, SignalValueRef::BitVec(_)) => {
+                let bits = value.to_bit_string()?;
+                let unsigned = value_to_f64(value)?;
+                Some(if bits.starts_with('1') {
+                    unsigned - 2f64.powi(bits.len() as i32)
+                } else {
+                    unsigned
+                })
+            }
+            (Self::Float(format), SignalValueRef::BitVec(_)) => {
+                let bits = value.to_bit_string()?;
+                SaphoFloat::decode(&bits, format).ok().map(|v| v.to_f64())
+            }
+            (Self::Vector | Self::Complex, _) => {
+                let [first, second] = self.decode_components(value)?;
+                Some(first.hypot(second))
+            }
+            _ => value_to_f64(value),
+        }
+    }
+}
+
+/// A signal to summarize and the radix its values are shown in.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsRequest {
+    pub signal_ref: usize,
+    pub radix: Option<String>,
+    /// Format of the signal's processor, in place of the request's
+    #[serde(default)]
+    pub float_format: Option<FloatFormat>,
+}
+
+impl StatsRequest {
+    /// How to read the signal's values, with `float_format` for the `float`
+    /// radix when the request has no format of its own.
+    pub fn encoding(&self, float_format: Option<FloatFormat>) -> Result<Encoding, String> {
+        match &self.radix {
+            Some(radix) => Encoding::from_radix(radix, self.float_format.or(float_format)),
+            None => Ok(Encoding::Unsigned),
+        }
+    }
+}
+
+/// Range and mean of one component of vector or complex values.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentStats {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub mean: Option<f64>,
+}
+
+/// Time spent with a value in `[start, end)`. The last bin includes `end`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramBin {
+    pub start: f64,
+    pub end: f64,
+    pub time: u64,
+}
+
+/// Statistics of one signal over `[from, to]`. Single bits get their high
+/// time and duty cycle, wider signals the numeric summary. Durations with
+/// an unknown (`x`/`z`) value are left out of both. The summary of vector
+/// and complex values is of their magnitude, with each component in
+/// `components`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalStats {
+    pub signal_ref: usize,
+    pub from: u64,
+    pub to: u64,
+    /// Value changes after `from`, up to and including `to`
+    pub toggles: u64,
+    pub high_time: Option<u64>,
+    pub duty_cycle: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Mean weighted by the time each value lasts
+    pub mean: Option<f64>,
+    pub histogram: Vec<HistogramBin>,
+    pub components: Vec<ComponentStats>,
+}
+
+fn is_high(value: &SignalValueRef) -> bool {
+    matches!(
+        value.to_bit_string().as_deref(),
+        Some("1") | Some("h") | Some("H")
+    )
+}
+
+fn is_low(value: &SignalValueRef) -> bool {
+    matches!(
+        value.to_bit_string().as_deref(),
+        Some("0") | Some("l") | Some("L")
+    )
+}
+
+/// Smallest and largest value, and the mean weighted by duration.
+fn summarize(segments: &[(f64, u64)]) -> (f64, f64, Option<f64>) {
+    let min = segments
+        .iter()
+        .map(|(v, _)| *v)
+        .fold(f64::INFINITY, f64::min);
+    let max = segments
+        .iter()
+        .map(|(v, _)| *v)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let known_time: u64 = segments.iter().map(|(_, d)| d).sum();
+    let mean = (known_time > 0)
+        .then(|| segments.iter().map(|(v, d)| v * *d as f64).sum::<f64>() / known_time as f64);
+    (min, max, mean)
+}
+
+fn histogram(segments: &[(f64, u64)], min: f64, max: f64, bins: usize) -> Vec<HistogramBin> {
+    let bins = if max > min { bins.max(1) } else { 1 };
+    let step = (max - min) / bins as f64;
+    let mut histogram: Vec<HistogramBin> = (0..bins)
+        .map(|i| HistogramBin {
+            start: min + step * i as f64,
+            end: if i + 1 == bins {
+                max
+            } else {
+                min + step * (i + 1) as f64
+            },
+            time: 0,
+        })
+        .collect();
+
+    for &(value, duration) in segments {
+        let bin = if step > 0.0 {
+            (((value - min) / step) as usize).min(bins - 1)
+        } else {
+            0
+        };
+        histogram[bin].time += duration;
+    }
+    histogram
+}
+
+/// Compute the statistics of one signal between `from` and `to`, both
+/// inclusive; `None` stands for the start and end of the waveform.
+pub fn signal_stats(
+    session: &mut WaveformSession,
+    index: usize,
+    encoding: Encoding,
+    from: Option<u64>,
+    to: Option<u64>,
+    bins: Option<usize>,
+) -> Result<SignalStats, String> {
+    let signal_ref = session.ensure_loaded(index)?;
+    let from = from.unwrap_or_else(|| session.waveform.time_table().first().copied().unwrap_or(0));
+    let to = to.unwrap_or_else(|| session.end_time());
+    if to < from {
+        return Err(format!(
+            "Failed to compute statistics: {} is after {}",
+            from, to
+        ));
+    }
+
+    let mut changes = session
+        .changes_since(signal_ref, from)?
+        .take_while(|(time, _)| *time <= to)
+        .peekable();
+
+    let mut toggles = 0;
+    let mut single_bit = None;
+    let mut high_time = 0;
+    let mut low_time = 0;
+    let mut segments: Vec<(f64, u64)> = Vec::new();
+    let mut components: [Vec<(f64, u64)>; 2] = Default::default();
+    let mut previous: Option<String> = None;
+    while let Some((time, value)) = changes.next() {
+        let start = time.max(from);
+        let end = changes.peek().map_or(to, |(next, _)| (*next).max(from));
+        let text = value_to_string(&value);
+        if time > from && previous.as_ref().is_some_and(|before| *before != text) {
+            toggles += 1;
+        }
+        single_bit.get_or_insert(matches!(value, SignalValueRef::BitVec(_)) && text.len() == 1);
+        previous = Some(text);
+
+        if is_high(&value) {
+            high_time += end - start;
+        } else if is_low(&value) {
+            low_time += end - start;
+        }
+        if let Some(number) = encoding.decode(&value) {
+            segments.push((number, end - start));
+        }
+        if let Some(parts) = encoding.decode_components(&value) {
+            for (segments, part) in components.iter_mut().zip(parts) {
+                segments.push((part, end - start));
+            }
+        }
+    }
+
+    let mut stats = SignalStats {
+        signal_ref: index,
+        from,
+        to,
+        toggles,
+        high_time: None,
+        duty_cycle: None,
+        min: None,
+        max: None,
+        mean: None,
+        histogram: Vec::new(),
+        components: Vec::new(),
+    };
+    if single_bit == Some(true) {
+        stats.high_time = Some(high_time);
+        let known_time = high_time + low_time;
+        stats.duty_cycle = (known_time > 0).then(|| high_time as f64 / known_time as f64);
+    } else if !segments.is_empty() {
+        let (min, max, mean) = summarize(&segments);
+        stats.min = Some(min);
+        stats.max = Some(max);
+        stats.mean = mean;
+        stats.histogram = histogram(&segments, min, max, bins.unwrap_or(DEFAULT_BINS));
+        stats.components = encoding
+            .component_names()
+            .iter()
+            .zip(&components)
+            .map(|(name, segments)| {
+                let (min, max, mean) = summarize(segments);
+                ComponentStats {
+                    name: name.to_string(),
+                    min,
+                    max,
+                    mean,
+                }
+            })
+            .collect();
+    }
+    Ok(stats)
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Statistics of several signals over the same time range, each read in
+/// its own radix. `float_format` is the format of values in the `float` radix
+/// for signals that do not bring their own
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn compute_signal_stats(
+    session_id: u32,
+    signals: Vec<StatsRequest>,
+    from: Option<u64>,
+    to: Option<u64>,
+    bins: Option<usize>,
+    float_format: Option<FloatFormat>,
+    state: State<'_, WaveformManager>,
+) -> Result<Vec<SignalStats>, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut session = session.lock().unwrap();
+        signals
+            .iter()
+            .map(|request| {
+                let encoding = request.encoding(float_format)?;
+                signal_stats(&mut session, request.signal_ref, encoding, from, to, bins)
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Failed to compute statistics: {}", e))?
+}
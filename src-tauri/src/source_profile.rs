@@ -0,0 +1,212 @@
+use crate::clock::{detect_clocks, ClockInfo};
+use crate::instruction_trace::vars_of_kind;
+use crate::sapho_names::SignalKind;
+use crate::translation::signed_value;
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use serde::Serialize;
+use std::collections::HashMap;
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// Types a C+- function can return.
+const RETURN_TYPES: [&str; 4] = ["void", "int", "float", "comp"];
+
+/// Time spent on one C+- source line.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineProfile {
+    pub line: i64,
+    pub text: String,
+    pub function: Option<String>,
+    pub time: u64,
+    /// Clock cycles, when the waveform has a clock
+    pub cycles: Option<u64>,
+    /// Times `linetabs` moved onto the line
+    pub entries: usize,
+    /// Fraction of the profiled time
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionProfile {
+    pub name: String,
+    pub time: u64,
+    pub cycles: Option<u64>,
+    pub share: f64,
+}
+
+/// Hot-line report of a simulation, hottest lines and functions first.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceProfile {
+    pub source_path: String,
+    /// `#PRNAME` of the program, naming its `.cmm` file
+    pub program: Option<String>,
+    pub clock: Option<ClockInfo>,
+    /// Time with a known source line, what the shares are relative to
+    pub total_time: u64,
+    pub lines: Vec<LineProfile>,
+    pub functions: Vec<FunctionProfile>,
+}
+
+/// Name of the function declared by a line such as `int f(int x)`.
+fn declared_function(text: &str) -> Option<&str> {
+    let (return_type, rest) = text.trim().split_once(char::is_whitespace)?;
+    if !RETURN_TYPES.contains(&return_type) {
+        return None;
+    }
+    let (name, _) = rest.trim_start().split_once('(')?;
+    let name = name.trim_end();
+    (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .then_some(name)
+}
+
+/// Function every source line belongs to, from its declaration to its
+/// closing brace. Negative entries like `-2 void main();` belong to the
+/// function they declare.
+pub fn line_functions(source_lines: &HashMap<i64, String>) -> HashMap<i64, String> {
+    let mut numbers: Vec<i64> = source_lines.keys().copied().collect();
+    numbers.sort_unstable();
+
+    let mut functions = HashMap::new();
+    let mut current: Option<String> = None;
+    let mut depth = 0i64;
+    for number in numbers {
+        let text = &source_lines[&number];
+        if number < 0 {
+            if let Some(name) = declared_function(text) {
+                functions.insert(number, name.to_string());
+            }
+            continue;
+        }
+
+        let code = text
+            .split_once("//")
+            .map_or(text.as_str(), |(code, _)| code);
+        if depth == 0 && !code.trim_end().ends_with(';') {
+            if let Some(name) = declared_function(code) {
+                current = Some(name.to_string());
+            }
+        }
+        if let Some(name) = &current {
+            functions.insert(number, name.clone());
+        }
+        let opened = depth > 0 || code.contains('{');
+        depth += code.matches('{').count() as i64 - code.matches('}').count() as i64;
+        if depth <= 0 && opened {
+            depth = 0;
+            current = None;
+        }
+    }
+    functions
+}
+
+fn share(time: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        time as f64 / total as f64
+    }
+}
+
+/// Total the time a processor spent on each C+- line, following its
+/// `linetabs` signal (the first one when `source_ref` is None) until the end
+/// of the waveform.
+pub fn profile_source(
+    session: &mut WaveformSession,
+    source_ref: Option<usize>,
+) -> Result<SourceProfile, String> {
+    let sources = vars_of_kind(session, SignalKind::SourceLine);
+    let (source_path, source_ref) = match source_ref {
+        Some(index) => sources
+            .into_iter()
+            .find(|(_, signal_ref)| *signal_ref == index)
+            .ok_or_else(|| format!("Failed to profile: signal {} is not linetabs", index))?,
+        None => sources
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Failed to profile: no linetabs signal found".to_string())?,
+    };
+
+    let tables = session.symbols_for(&source_path);
+    let functions = line_functions(&tables.source_lines);
+    let clock = detect_clocks(session)?.into_iter().next();
+    let end = session.end_time();
+
+    let changes = session.signal_changes(source_ref)?;
+    let mut totals: HashMap<i64, (u64, usize)> = HashMap::new();
+    for (i, (&time, bits)) in changes.times.iter().zip(&changes.values).enumerate() {
+        let Some(line) = signed_value(bits) else {
+            continue;
+        };
+        let next = changes.times.get(i + 1).copied().unwrap_or(end);
+        let total = totals.entry(line).or_default();
+        total.0 += next - time;
+        total.1 += 1;
+    }
+
+    let total_time: u64 = totals.values().map(|(time, _)| time).sum();
+    let cycles = |time: u64| clock.as_ref().map(|clock| time / clock.period);
+
+    let mut function_times: HashMap<String, u64> = HashMap::new();
+    let mut lines: Vec<LineProfile> = totals
+        .into_iter()
+        .map(|(line, (time, entries))| {
+            let function = functions.get(&line).cloned();
+            if let Some(name) = &function {
+                *function_times.entry(name.clone()).or_default() += time;
+            }
+            LineProfile {
+                line,
+                text: tables.source_lines.get(&line).cloned().unwrap_or_default(),
+                function,
+                time,
+                cycles: cycles(time),
+                entries,
+                share: share(time, total_time),
+            }
+        })
+        .collect();
+    lines.sort_by_key(|line| (std::cmp::Reverse(line.time), line.line));
+
+    let mut functions: Vec<FunctionProfile> = function_times
+        .into_iter()
+        .map(|(name, time)| FunctionProfile {
+            name,
+            time,
+            cycles: cycles(time),
+            share: share(time, total_time),
+        })
+        .collect();
+    functions.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(SourceProfile {
+        source_path,
+        program: tables.directive("#PRNAME").map(str::to_string),
+        clock,
+        total_time,
+        lines,
+        functions,
+    })
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Time and cycles spent on every C+- source line and function
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn profile_source_lines(
+    session_id: u32,
+    signal_ref: Option<usize>,
+    state: State<'_, WaveformManager>,
+) -> Result<SourceProfile, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        profile_source(&mut session.lock().unwrap(), signal_ref)
+    })
+    .await
+    .map_err(|e| format!("Failed to profile source lines: {}", e))?
+}
@@ -0,0 +1,125 @@
+use crate::sapho_float::FloatFormat;
+use serde::Deserialize;
+
+/// The parts of a `.spf` project file that the waveform analyses read.
+/// Fields that are missing, as in projects made by older versions, are left
+/// empty rather than rejected.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpfProject {
+    #[serde(default)]
+    pub project_name: Option<String>,
+    #[serde(default)]
+    pub processors: Vec<SpfProcessor>,
+    #[serde(default)]
+    pub settings: SpfSettings,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpfSettings {
+    #[serde(default)]
+    pub default_processor: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpfProcessor {
+    pub name: String,
+    #[serde(default)]
+    pub data_stack_size: Option<u32>,
+    #[serde(default)]
+    pub instruction_stack_size: Option<u32>,
+    #[serde(default)]
+    pub mantissa_bits: Option<u32>,
+    #[serde(default)]
+    pub exponent_bits: Option<u32>,
+    #[serde(default)]
+    pub relative_path: Option<String>,
+    #[serde(default)]
+    pub translation: SpfTranslation,
+    #[serde(default)]
+    pub enums: Vec<SpfEnum>,
+    #[serde(default)]
+    pub expected_outputs: Vec<SpfExpectedOutput>,
+}
+
+/// Translation tables of a processor, relative to the `.spf` file. Tables
+/// left out are looked for next to the waveform.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpfTranslation {
+    /// Replaces `trad_opcode.txt`
+    #[serde(default)]
+    pub opcodes: Option<String>,
+    /// Replaces `trad_cmm.txt`
+    #[serde(default)]
+    pub source_lines: Option<String>,
+}
+
+/// Value labels for one signal, e.g. the states of an FSM register.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpfEnum {
+    /// Path of the signal, or its trailing scopes such as `core.state`
+    pub signal: String,
+    /// Table of `<value> <label>` lines, relative to the `.spf` file
+    pub file: String,
+}
+
+/// Values a simulation must write to one output port, in order.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpfExpectedOutput {
+    /// `N` of `out_sig_N`
+    pub port: u32,
+    /// One value per line, relative to the `.spf` file, usually in the
+    /// processor's `Simulation` folder
+    pub file: String,
+    #[serde(default)]
+    pub format: OutputFormat,
+}
+
+/// How the values of an expected output file are written and compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Integers, compared with the output read as two's complement
+    #[default]
+    Int,
+    /// Numbers compared with the output decoded in the processor's float
+    /// format
+    Float,
+}
+
+impl SpfProject {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("Failed to parse project file: {}", e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        Self::parse(&text)
+    }
+
+    /// Processor called `name`, falling back to the default processor and
+    /// then to the only one of the project.
+    pub fn processor(&self, name: Option<&str>) -> Option<&SpfProcessor> {
+        let by_name = |name: &str| self.processors.iter().find(|p| p.name == name);
+        name.and_then(by_name)
+            .or_else(|| self.settings.default_processor.as_deref().and_then(by_name))
+            .or(match self.processors.as_slice() {
+                [only] => Some(only),
+                _ => None,
+            })
+    }
+}
+
+impl SpfProcessor {
+    /// Format of the processor's floats, when the project gives valid
+    /// mantissa and exponent widths.
+    pub fn float_format(&self) -> Option<FloatFormat> {
+        FloatFormat::new(self.mantissa_bits?, self.exponent_bits?).ok()
+    }
+}
@@ -0,0 +1,189 @@
+use crate::instruction_trace::{signed_changes, vars_of_kind};
+use crate::sapho_names::{owning_processor, processor_scopes, SignalKind};
+use crate::spf::SpfProject;
+use crate::translation::var_paths;
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use serde::Serialize;
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// Names of the stack status flags of the processor core.
+const STACK_FLAGS: [&str; 2] = ["fl_full", "fl_max"];
+
+/// A configured stack this many times deeper than its peak is reported as
+/// much too large.
+const OVERSIZE_FACTOR: i64 = 2;
+
+/// Depth reached by one stack, compared with its configured size.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackUsage {
+    /// `dataStackPointer` or `instructionStackPointer`
+    pub kind: SignalKind,
+    pub path: String,
+    pub processor: Option<String>,
+    pub peak: Option<i64>,
+    /// First time the peak was reached
+    pub peak_time: Option<u64>,
+    pub configured: Option<u32>,
+    /// Where the configured size came from: a `.spf` file or the `#NDSTAC`
+    /// and `#SDEPTH` directives of the program
+    pub configured_from: Option<String>,
+    pub warning: Option<String>,
+}
+
+/// Interval during which a stack flag had a non-zero value.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagAssertion {
+    pub path: String,
+    pub value: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackAnalysis {
+    pub stacks: Vec<StackUsage>,
+    pub flags: Vec<FlagAssertion>,
+}
+
+/// Highest value of a stack pointer and when it was first reached.
+fn peak(changes: &[(u64, Option<i64>)]) -> Option<(i64, u64)> {
+    changes
+        .iter()
+        .filter_map(|&(time, value)| Some((value?, time)))
+        .min_by_key(|&(value, time)| (std::cmp::Reverse(value), time))
+}
+
+fn size_warning(peak: Option<i64>, configured: Option<u32>) -> Option<String> {
+    let (peak, size) = (peak?, i64::from(configured?));
+    if peak > size {
+        Some(format!(
+            "Stack too small: depth {} exceeds the configured {}",
+            peak, size
+        ))
+    } else if size > OVERSIZE_FACTOR * peak.max(1) {
+        Some(format!(
+            "Stack much larger than needed: depth {} of the configured {}",
+            peak, size
+        ))
+    } else {
+        None
+    }
+}
+
+/// Intervals where a flag is non-zero, the last one running to `end`.
+fn assertions(path: &str, changes: &[(u64, String)], end: u64) -> Vec<FlagAssertion> {
+    let mut found = Vec::new();
+    for (i, (start, value)) in changes.iter().enumerate() {
+        if !value.contains('1') {
+            continue;
+        }
+        found.push(FlagAssertion {
+            path: path.to_string(),
+            value: value.clone(),
+            start: *start,
+            end: changes.get(i + 1).map_or(end, |(next, _)| *next),
+        });
+    }
+    found
+}
+
+/// Follow the data and instruction stack pointers of every processor in a
+/// session, and the times its stack flags were raised. Sizes come from the
+/// project file when given, otherwise from the directives of the program.
+pub fn analyze_stacks(
+    session: &mut WaveformSession,
+    spf: Option<(&str, &SpfProject)>,
+) -> Result<StackAnalysis, String> {
+    let end = session.end_time();
+    let scopes = processor_scopes(&var_paths(session));
+
+    let mut pointers = vars_of_kind(session, SignalKind::DataStackPointer)
+        .into_iter()
+        .map(|var| (SignalKind::DataStackPointer, var))
+        .collect::<Vec<_>>();
+    pointers.extend(
+        vars_of_kind(session, SignalKind::InstructionStackPointer)
+            .into_iter()
+            .map(|var| (SignalKind::InstructionStackPointer, var)),
+    );
+
+    let mut stacks = Vec::new();
+    for (kind, (path, index)) in pointers {
+        let processor = owning_processor(&scopes, &path).map(str::to_string);
+        let tables = session.processor_symbols(processor.as_deref());
+        let from_spf = spf.and_then(|(spf_path, project)| {
+            let config = project.processor(processor.as_deref())?;
+            let size = match kind {
+                SignalKind::DataStackPointer => config.data_stack_size,
+                _ => config.instruction_stack_size,
+            }?;
+            Some((size, spf_path.to_string()))
+        });
+        let from_directive = || {
+            let directive = match kind {
+                SignalKind::DataStackPointer => "#NDSTAC",
+                _ => "#SDEPTH",
+            };
+            let size = tables.directive(directive)?.parse().ok()?;
+            Some((size, tables.cmm_file.clone()?))
+        };
+        let (configured, configured_from) = from_spf.or_else(from_directive).unzip();
+
+        let changes = signed_changes(session, index)?;
+        let peak = peak(&changes);
+        stacks.push(StackUsage {
+            kind,
+            path,
+            processor,
+            peak: peak.map(|(value, _)| value),
+            peak_time: peak.map(|(_, time)| time),
+            warning: size_warning(peak.map(|(value, _)| value), configured),
+            configured,
+            configured_from,
+        });
+    }
+
+    let flag_vars: Vec<(String, usize)> = vars_of_kind(session, SignalKind::Flag)
+        .into_iter()
+        .filter(|(path, _)| {
+            path.rsplit('.')
+                .next()
+                .is_some_and(|name| STACK_FLAGS.contains(&name))
+        })
+        .collect();
+    let mut flags = Vec::new();
+    for (path, index) in flag_vars {
+        let changes = session.signal_changes(index)?;
+        let changes: Vec<(u64, String)> = changes.times.into_iter().zip(changes.values).collect();
+        flags.extend(assertions(&path, &changes, end));
+    }
+    flags.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.path.cmp(&b.path)));
+
+    Ok(StackAnalysis { stacks, flags })
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Peak stack depths against the configured sizes, and stack flag events
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn analyze_stack_usage(
+    session_id: u32,
+    spf_path: Option<String>,
+    state: State<'_, WaveformManager>,
+) -> Result<StackAnalysis, String> {
+    let session = state.get(session_id)?;
+    let project = spf_path.as_deref().map(SpfProject::load).transpose()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let spf = spf_path.as_deref().zip(project.as_ref());
+        analyze_stacks(&mut session.lock().unwrap(), spf)
+    })
+    .await
+    .map_err(|e| format!("Failed to analyze stacks: {}", e))?
+}
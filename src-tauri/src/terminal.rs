@@ -95,8 +95,6 @@ pub fn create_pty(
     cwd: String,
     state: State<TerminalManager>,
 ) -> Result<u32, String> {
-    use portable_pty::PtySystem;
-    
     let pty_system = native_pty_system();
     
     let pair = pty_system
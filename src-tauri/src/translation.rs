@@ -0,0 +1,586 @@
+use crate::sapho_float::FloatFormat;
+use crate::sapho_names::{classify, owning_processor, processor_scopes, SignalKind};
+use crate::spf::{SpfProcessor, SpfProject};
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// Opcode translation table the SAPHO toolchain writes next to a simulation.
+pub const OPCODE_TABLE: &str = "trad_opcode.txt";
+
+/// C+- source line table written next to the opcode table.
+pub const CMM_TABLE: &str = "trad_cmm.txt";
+
+/// One line of the opcode table, e.g. `1 LOD 75`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Instruction {
+    pub mnemonic: String,
+    pub operand: Option<String>,
+}
+
+/// A line of a table that could not be read. Line 0 stands for the whole
+/// file, e.g. when it is missing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl TableError {
+    pub(crate) fn new(file: &str, line: usize, message: String) -> Self {
+        Self {
+            file: file.to_string(),
+            line,
+            message,
+        }
+    }
+}
+
+/// What signal values stand for: `valr2` instructions by address, `linetabs`
+/// C+- source lines by number (negative numbers are internal entries such as
+/// `-1 INTERNO`) and labels attached to any other signal, by path.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationTables {
+    pub opcodes: HashMap<i64, Instruction>,
+    pub source_lines: HashMap<i64, String>,
+    pub enums: HashMap<String, HashMap<i64, String>>,
+    /// Files the tables were read from
+    pub opcode_file: Option<String>,
+    pub cmm_file: Option<String>,
+    /// Format of the processor's floats, from its project
+    pub float_format: Option<FloatFormat>,
+    pub errors: Vec<TableError>,
+    /// Tables neither the project nor the waveform's directory provides
+    pub warnings: Vec<TableError>,
+}
+
+/// Symbol tables of every processor of a session, keyed by the name of its
+/// `p_<name>` instance. A waveform without instances has one entry for None.
+pub type SymbolTables = HashMap<Option<String>, TranslationTables>;
+
+/// Tables of one processor, as the viewer receives them.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessorTables {
+    pub processor: Option<String>,
+    #[serde(flatten)]
+    pub tables: TranslationTables,
+}
+
+/// Key of the entry that holds the tables for the signals of `processor`:
+/// its own, else the one for signals outside any instance, else the only
+/// entry there is.
+pub fn tables_key<T>(
+    tables: &HashMap<Option<String>, T>,
+    processor: Option<&str>,
+) -> Option<Option<String>> {
+    let own = processor.map(str::to_string);
+    if tables.contains_key(&own) {
+        return Some(own);
+    }
+    if tables.contains_key(&None) {
+        return Some(None);
+    }
+    match tables.keys().collect::<Vec<_>>().as_slice() {
+        [only] => Some((*only).clone()),
+        _ => None,
+    }
+}
+
+/// Every entry of `symbols`, ordered by processor.
+pub fn processor_tables(symbols: &SymbolTables) -> Vec<ProcessorTables> {
+    let mut tables: Vec<ProcessorTables> = symbols
+        .iter()
+        .map(|(processor, tables)| ProcessorTables {
+            processor: processor.clone(),
+            tables: tables.clone(),
+        })
+        .collect();
+    tables.sort_by(|a, b| a.processor.cmp(&b.processor));
+    tables
+}
+
+/// Number at the start of a table line: decimal, possibly negative, or
+/// `0x`/`0b` prefixed.
+pub(crate) fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Read `<number> <rest>` lines. Blank lines are skipped; lines without a
+/// number and repeated numbers are reported with their line number.
+fn numbered_lines<'a>(text: &'a str, file: &str) -> (Vec<(i64, &'a str)>, Vec<TableError>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    let mut first_seen: HashMap<i64, usize> = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim_start();
+        if line.trim_end().is_empty() {
+            continue;
+        }
+        let (number, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let Some(number) = parse_number(number) else {
+            errors.push(TableError::new(
+                file,
+                line_number,
+                format!("expected a number, found {:?}", number),
+            ));
+            continue;
+        };
+        if let Some(first) = first_seen.insert(number, line_number) {
+            errors.push(TableError::new(
+                file,
+                line_number,
+                format!("{} is already defined on line {}", number, first),
+            ));
+            continue;
+        }
+        entries.push((number, rest.trim()));
+    }
+    (entries, errors)
+}
+
+/// Value of a bit string read as two's complement, as the viewer shows
+/// `valr2` and `linetabs`. None for unknown bits or more than 64 bits.
+pub fn signed_value(bits: &str) -> Option<i64> {
+    if bits.is_empty() || bits.len() > 64 {
+        return None;
+    }
+    let unsigned = u64::from_str_radix(bits, 2).ok()?;
+    Some(if bits.starts_with('1') && bits.len() < 64 {
+        unsigned as i64 - (1i64 << bits.len())
+    } else {
+        unsigned as i64
+    })
+}
+
+pub(crate) fn read_table(path: &Path) -> Result<String, TableError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| TableError::new(&path.to_string_lossy(), 0, format!("{}", e)))
+}
+
+impl TranslationTables {
+    /// Opcode table entries, and the lines that are not `<address>
+    /// <mnemonic> [operand]`.
+    pub fn parse_opcodes(text: &str, file: &str) -> (HashMap<i64, Instruction>, Vec<TableError>) {
+        let (entries, mut errors) = numbered_lines(text, file);
+        let mut opcodes = HashMap::new();
+        for (address, rest) in entries {
+            let mut words = rest.split_whitespace();
+            let Some(mnemonic) = words.next() else {
+                errors.push(TableError::new(
+                    file,
+                    line_of(text, address),
+                    format!("address {} has no mnemonic", address),
+                ));
+                continue;
+            };
+            let operand = words.collect::<Vec<_>>().join(" ");
+            opcodes.insert(
+                address,
+                Instruction {
+                    mnemonic: mnemonic.to_string(),
+                    operand: (!operand.is_empty()).then_some(operand),
+                },
+            );
+        }
+        errors.sort_by_key(|error| error.line);
+        (opcodes, errors)
+    }
+
+    /// Source line table entries; the text of a line may be empty.
+    pub fn parse_source_lines(text: &str, file: &str) -> (HashMap<i64, String>, Vec<TableError>) {
+        let (entries, errors) = numbered_lines(text, file);
+        let lines = entries
+            .into_iter()
+            .map(|(number, rest)| (number, rest.to_string()))
+            .collect();
+        (lines, errors)
+    }
+
+    /// Value labels such as `3 FETCH`, for an FSM state register. Values may
+    /// be written in hex (`0x3`) or binary (`0b11`); every label needs text.
+    pub fn parse_enum(text: &str, file: &str) -> (HashMap<i64, String>, Vec<TableError>) {
+        let (entries, mut errors) = numbered_lines(text, file);
+        let mut labels = HashMap::new();
+        for (value, label) in entries {
+            if label.is_empty() {
+                errors.push(TableError::new(
+                    file,
+                    line_of(text, value),
+                    format!("value {} has no label", value),
+                ));
+            } else {
+                labels.insert(value, label.to_string());
+            }
+        }
+        errors.sort_by_key(|error| error.line);
+        (labels, errors)
+    }
+
+    fn read_opcodes(&mut self, path: &Path) -> Result<(), TableError> {
+        let text = read_table(path)?;
+        let file = path.to_string_lossy().to_string();
+        let (opcodes, errors) = Self::parse_opcodes(&text, &file);
+        self.opcodes = opcodes;
+        self.errors.extend(errors);
+        self.opcode_file = Some(file);
+        Ok(())
+    }
+
+    fn read_source_lines(&mut self, path: &Path) -> Result<(), TableError> {
+        let text = read_table(path)?;
+        let file = path.to_string_lossy().to_string();
+        let (source_lines, errors) = Self::parse_source_lines(&text, &file);
+        self.source_lines = source_lines;
+        self.errors.extend(errors);
+        self.cmm_file = Some(file);
+        Ok(())
+    }
+
+    /// Read the given tables, or those next to the waveform when None.
+    /// Given files that cannot be read are errors; missing files next to the
+    /// waveform are only warnings, the waveform is still usable without them.
+    fn read(waveform_path: &str, opcodes: Option<PathBuf>, source_lines: Option<PathBuf>) -> Self {
+        let mut tables = Self::default();
+        let beside = |name: &str| Path::new(waveform_path).parent().map(|dir| dir.join(name));
+        match opcodes {
+            Some(path) => {
+                if let Err(error) = tables.read_opcodes(&path) {
+                    tables.errors.push(error);
+                }
+            }
+            None => {
+                if let Some(path) = beside(OPCODE_TABLE) {
+                    if let Err(error) = tables.read_opcodes(&path) {
+                        tables.warnings.push(TableError::new(
+                            &error.file,
+                            0,
+                            format!(
+                                "no opcode table, instructions are shown by address ({})",
+                                error.message
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        match source_lines {
+            Some(path) => {
+                if let Err(error) = tables.read_source_lines(&path) {
+                    tables.errors.push(error);
+                }
+            }
+            None => {
+                if let Some(path) = beside(CMM_TABLE) {
+                    if let Err(error) = tables.read_source_lines(&path) {
+                        tables.warnings.push(TableError::new(
+                            &error.file,
+                            0,
+                            format!(
+                                "no C+- line table, source lines are shown by number ({})",
+                                error.message
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        tables
+    }
+
+    /// Tables found next to a waveform.
+    pub fn load(waveform_path: &str) -> Self {
+        Self::read(waveform_path, None, None)
+    }
+
+    /// Tables a processor of a project declares, with paths relative to the
+    /// `.spf` file. Tables it does not declare are looked for next to the
+    /// waveform, and its enum tables are attached to the signals in `paths`.
+    pub fn load_declared(
+        waveform_path: &str,
+        spf_path: &str,
+        processor: &SpfProcessor,
+        paths: &[String],
+    ) -> Self {
+        let project_dir = Path::new(spf_path).parent().unwrap_or(Path::new(""));
+        let declared = |file: &Option<String>| file.as_ref().map(|file| project_dir.join(file));
+        let mut tables = Self::read(
+            waveform_path,
+            declared(&processor.translation.opcodes),
+            declared(&processor.translation.source_lines),
+        );
+
+        for table in &processor.enums {
+            let file = project_dir.join(&table.file);
+            match read_table(&file) {
+                Ok(text) => {
+                    let errors =
+                        tables.attach_enum(paths, &table.signal, &text, &file.to_string_lossy());
+                    tables.errors.extend(errors);
+                }
+                Err(error) => tables.errors.push(error),
+            }
+        }
+        tables.float_format = processor.float_format();
+        tables
+    }
+
+    /// Attach value labels to the signal whose path is or ends with
+    /// `signal`. Returns the problems found in the table; the valid labels
+    /// are attached anyway.
+    pub fn attach_enum(
+        &mut self,
+        paths: &[String],
+        signal: &str,
+        text: &str,
+        file: &str,
+    ) -> Vec<TableError> {
+        let Some(path) = paths.iter().find(|path| {
+            *path == signal
+                || path
+                    .strip_suffix(signal)
+                    .is_some_and(|scope| scope.ends_with('.'))
+        }) else {
+            return vec![TableError::new(
+                file,
+                0,
+                format!("no signal matches {}", signal),
+            )];
+        };
+        let (labels, errors) = Self::parse_enum(text, file);
+        self.enums.insert(path.clone(), labels);
+        errors
+    }
+
+    /// Value of a compiler directive of the source, e.g. `ProcDTW` for
+    /// `#PRNAME` or `5` for `#NDSTAC`.
+    pub fn directive(&self, name: &str) -> Option<&str> {
+        let mut lines: Vec<_> = self.source_lines.iter().collect();
+        lines.sort_unstable_by_key(|(number, _)| **number);
+        lines.into_iter().find_map(|(_, text)| {
+            let value = text.trim().strip_prefix(name)?;
+            value.starts_with(char::is_whitespace).then(|| value.trim())
+        })
+    }
+
+    /// Every mnemonic mapped to the addresses that use it, so an expression
+    /// like `valr2 == OUT` matches any `OUT` instruction.
+    pub fn mnemonic_addresses(&self) -> HashMap<String, Vec<i64>> {
+        let mut symbols: HashMap<String, Vec<i64>> = HashMap::new();
+        for (&address, instruction) in &self.opcodes {
+            if instruction
+                .mnemonic
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                symbols
+                    .entry(instruction.mnemonic.clone())
+                    .or_default()
+                    .push(address);
+            }
+        }
+        for addresses in symbols.values_mut() {
+            addresses.sort_unstable();
+        }
+        symbols
+    }
+}
+
+/// Line of the first entry for `number`, to point errors found after
+/// parsing at the right place.
+fn line_of(text: &str, number: i64) -> usize {
+    text.lines()
+        .position(|line| {
+            let line = line.trim_start();
+            let first = line.split_whitespace().next().unwrap_or("");
+            parse_number(first) == Some(number)
+        })
+        .map_or(0, |i| i + 1)
+}
+
+/// Full path of every var of a session.
+pub fn var_paths(session: &WaveformSession) -> Vec<String> {
+    let hierarchy = session.waveform.hierarchy();
+    hierarchy
+        .all_vars()
+        .map(|var_ref| hierarchy[var_ref].full_name(hierarchy))
+        .collect()
+}
+
+/// Tables for every processor instance of a session: those it declares in
+/// a project file, or the ones next to the waveform. Enum tables are only
+/// attached to the signals of their own processor. Missing tables are only
+/// warned about when the waveform has a processor to translate.
+pub fn load_symbol_tables(
+    session: &WaveformSession,
+    spf: Option<(&str, &SpfProject)>,
+) -> SymbolTables {
+    let paths = var_paths(session);
+    let scopes = processor_scopes(&paths);
+    let mut names: Vec<Option<&str>> = scopes
+        .iter()
+        .map(|(_, name)| Some(name.as_str()))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    if names.is_empty() {
+        names.push(None);
+    }
+    let has_processor = !scopes.is_empty()
+        || paths.iter().any(|path| {
+            let name = path.rsplit('.').next().unwrap_or(path);
+            classify(name, path).kind == SignalKind::Instruction
+        });
+
+    names
+        .into_iter()
+        .map(|name| {
+            let processor =
+                spf.and_then(|(spf_path, project)| Some((spf_path, project.processor(name)?)));
+            let mut tables = match processor {
+                Some((spf_path, processor)) => {
+                    let own_paths: Vec<String> = paths
+                        .iter()
+                        .filter(|path| name.is_none() || owning_processor(&scopes, path) == name)
+                        .cloned()
+                        .collect();
+                    TranslationTables::load_declared(&session.path, spf_path, processor, &own_paths)
+                }
+                None => TranslationTables::load(&session.path),
+            };
+            if !has_processor {
+                tables.warnings.clear();
+            }
+            (name.map(str::to_string), tables)
+        })
+        .collect()
+}
+
+/// Problems in the tables every processor of a project declares, without
+/// loading a waveform. Enum tables are matched against `paths` when given.
+pub fn check_project(
+    spf_path: &str,
+    project: &SpfProject,
+    paths: Option<&[String]>,
+) -> Vec<TableError> {
+    let mut errors = Vec::new();
+    if let Some(name) = &project.settings.default_processor {
+        if !project.processors.iter().any(|p| &p.name == name) {
+            errors.push(TableError::new(
+                spf_path,
+                0,
+                format!("default processor {} is not in the project", name),
+            ));
+        }
+    }
+
+    let project_dir = Path::new(spf_path).parent().unwrap_or(Path::new(""));
+    for processor in &project.processors {
+        let declared = |file: &Option<String>| file.as_ref().map(|file| project_dir.join(file));
+        if let Some(path) = declared(&processor.translation.opcodes) {
+            match read_table(&path) {
+                Ok(text) => errors
+                    .extend(TranslationTables::parse_opcodes(&text, &path.to_string_lossy()).1),
+                Err(error) => errors.push(error),
+            }
+        }
+        if let Some(path) = declared(&processor.translation.source_lines) {
+            match read_table(&path) {
+                Ok(text) => errors.extend(
+                    TranslationTables::parse_source_lines(&text, &path.to_string_lossy()).1,
+                ),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        for table in &processor.enums {
+            let path = project_dir.join(&table.file);
+            let file = path.to_string_lossy();
+            match (read_table(&path), paths) {
+                (Ok(text), Some(paths)) => errors.extend(TranslationTables::default().attach_enum(
+                    paths,
+                    &table.signal,
+                    &text,
+                    &file,
+                )),
+                (Ok(text), None) => errors.extend(TranslationTables::parse_enum(&text, &file).1),
+                (Err(error), _) => errors.push(error),
+            }
+        }
+    }
+    errors
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Reload the symbol tables of every processor of a session, from the
+/// project file when given, and return them with any parse errors
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn load_waveform_symbols(
+    session_id: u32,
+    spf_path: Option<String>,
+    state: State<'_, WaveformManager>,
+) -> Result<Vec<ProcessorTables>, String> {
+    let session = state.get(session_id)?;
+    let project = spf_path.as_deref().map(SpfProject::load).transpose()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut session = session.lock().unwrap();
+        let spf = spf_path.as_deref().zip(project.as_ref());
+        session.symbols = load_symbol_tables(&session, spf);
+        Ok(processor_tables(&session.symbols))
+    })
+    .await
+    .map_err(|e| format!("Failed to load symbol tables: {}", e))?
+}
+
+/// Attach value labels read from `file` to a signal, given by its path, in
+/// the tables of its processor
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn attach_signal_labels(
+    session_id: u32,
+    signal_path: String,
+    file: String,
+    state: State<'_, WaveformManager>,
+) -> Result<Vec<ProcessorTables>, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let text = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read {}: {}", file, e))?;
+        let mut session = session.lock().unwrap();
+        let paths = var_paths(&session);
+        let scopes = processor_scopes(&paths);
+        let processor = owning_processor(&scopes, &signal_path);
+        let key = tables_key(&session.symbols, processor)
+            .unwrap_or_else(|| processor.map(str::to_string));
+        let tables = session.symbols.entry(key).or_default();
+        tables.errors.retain(|error| error.file != file);
+        let errors = tables.attach_enum(&paths, &signal_path, &text, &file);
+        tables.errors.extend(errors);
+        Ok(processor_tables(&session.symbols))
+    })
+    .await
+    .map_err(|e| format!("Failed to attach labels: {}", e))?
+}
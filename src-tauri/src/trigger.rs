@@ -0,0 +1,84 @@
+use crate::signal_expr::{evaluate_with_symbols, ProcessorSymbols};
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use serde::Serialize;
+#[cfg(feature = "gui")]
+use tauri::State;
+
+/// A stretch of time during which a trigger condition holds. `end` is when
+/// it stops holding, or the last time of the waveform if it never does.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerMatch {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Every interval where `condition` is true (non-zero and fully known)
+/// that starts within `[from, to]`, its end clipped to `to`. A match already
+/// in progress at `from` is not one of them, so searching from inside a
+/// match finds the next one. At most `limit` matches are returned, the
+/// earliest first.
+pub fn find_triggers(
+    session: &mut WaveformSession,
+    condition: &str,
+    from: Option<u64>,
+    to: Option<u64>,
+    limit: Option<usize>,
+) -> Result<Vec<TriggerMatch>, String> {
+    let symbols: ProcessorSymbols = session
+        .symbols
+        .iter()
+        .map(|(processor, tables)| (processor.clone(), tables.mnemonic_addresses()))
+        .collect();
+    let derived = evaluate_with_symbols(session, condition, &symbols)?;
+    let last_time = session.end_time();
+    let from = from.unwrap_or(0);
+    let to = to.unwrap_or(last_time);
+    let limit = limit.unwrap_or(usize::MAX);
+
+    let mut matches = Vec::new();
+    for (i, (&start, value)) in derived.times.iter().zip(&derived.values).enumerate() {
+        if matches.len() >= limit || start > to {
+            break;
+        }
+        let holds = value.contains('1') && value.chars().all(|c| c == '0' || c == '1');
+        if !holds {
+            continue;
+        }
+
+        if start < from {
+            continue;
+        }
+        let end = derived.times.get(i + 1).copied().unwrap_or(last_time);
+        matches.push(TriggerMatch {
+            start,
+            end: end.min(to),
+        });
+    }
+    Ok(matches)
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Find the intervals where a condition such as `fl_full == 1 && rst == 0`
+/// holds. Opcode mnemonics from `trad_opcode.txt` can be compared with
+/// `==`/`!=`, as in `valr2 == OUT`
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn find_trigger_matches(
+    session_id: u32,
+    condition: String,
+    from: Option<u64>,
+    to: Option<u64>,
+    limit: Option<usize>,
+    state: State<'_, WaveformManager>,
+) -> Result<Vec<TriggerMatch>, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        find_triggers(&mut session.lock().unwrap(), &condition, from, to, limit)
+    })
+    .await
+    .map_err(|e| format!("Failed to search trigger: {}", e))?
+}
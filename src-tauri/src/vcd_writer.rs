@@ -0,0 +1,467 @@
+use crate::waveform::{timescale_to_string, WaveformSession};
+#[cfg(feature = "gui")]
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "gui")]
+use tauri::{AppHandle, Emitter, State};
+use wellen::{
+    Hierarchy, ScopeRef, ScopeType, SignalRef, SignalValueRef, VarDirection, VarRef, VarType,
+};
+
+pub const EXPORT_CANCELLED: &str = "Export cancelled";
+
+/// Cancel flags of the exports currently running, keyed by the token the
+/// frontend passed to `export_waveform_as_vcd`.
+pub struct ExportManager {
+    pub cancel_flags: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+impl Default for ExportManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportManager {
+    pub fn new() -> Self {
+        Self {
+            cancel_flags: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+#[derive(Clone, Serialize)]
+struct ExportProgressEvent {
+    percent: u32,
+}
+
+fn write_error(e: std::io::Error) -> String {
+    format!("Failed to write VCD file: {}", e)
+}
+
+// Same identifiers GTKWave uses: '!', '"', '#', ...
+fn index_to_vcd_id(mut index: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push(((index % 94) as u8 + 33) as char);
+        index /= 94;
+        if index == 0 {
+            break;
+        }
+    }
+    id
+}
+
+/// `$scope` keyword understood by wellen and GTKWave for each scope type.
+/// GHW generics and VHDL arrays have no VCD spelling and fall back to module.
+fn scope_keyword(scope_type: ScopeType) -> &'static str {
+    match scope_type {
+        ScopeType::Task => "task",
+        ScopeType::Function => "function",
+        ScopeType::Begin => "begin",
+        ScopeType::Fork => "fork",
+        ScopeType::Generate => "generate",
+        ScopeType::Struct => "struct",
+        ScopeType::Union => "union",
+        ScopeType::Class => "class",
+        ScopeType::Interface => "interface",
+        ScopeType::Package => "package",
+        ScopeType::Program => "program",
+        ScopeType::VhdlArchitecture => "vhdl_architecture",
+        ScopeType::VhdlProcedure => "vhdl_procedure",
+        ScopeType::VhdlFunction => "vhdl_function",
+        ScopeType::VhdlRecord => "vhdl_record",
+        ScopeType::VhdlProcess => "vhdl_process",
+        ScopeType::VhdlBlock => "vhdl_block",
+        ScopeType::VhdlForGenerate => "vhdl_for_generate",
+        ScopeType::VhdlIfGenerate => "vhdl_if_generate",
+        ScopeType::VhdlGenerate => "vhdl_generate",
+        ScopeType::VhdlPackage => "vhdl_package",
+        ScopeType::Unknown => "unknown",
+        ScopeType::Clocking => "clocking",
+        ScopeType::SvArray => "sv_array",
+        _ => "module",
+    }
+}
+
+/// `$var` keyword for each var type. The VHDL types are written as their
+/// closest Verilog type and restored from the `$attrbegin` written before them.
+fn var_keyword(var_type: VarType) -> &'static str {
+    match var_type {
+        VarType::Event | VarType::EventParameter => "event",
+        VarType::Integer => "integer",
+        VarType::Parameter => "parameter",
+        VarType::Real => "real",
+        VarType::Reg => "reg",
+        VarType::Supply0 => "supply0",
+        VarType::Supply1 => "supply1",
+        VarType::Time => "time",
+        VarType::Tri => "tri",
+        VarType::TriAnd => "triand",
+        VarType::TriOr => "trior",
+        VarType::TriReg => "trireg",
+        VarType::Tri0 => "tri0",
+        VarType::Tri1 => "tri1",
+        VarType::WAnd => "wand",
+        VarType::Wire => "wire",
+        VarType::WOr => "wor",
+        VarType::String => "string",
+        VarType::Port => "port",
+        VarType::SparseArray => "sparray",
+        VarType::RealTime => "realtime",
+        VarType::RealParameter => "real_parameter",
+        VarType::Bit | VarType::BitVector | VarType::Boolean => "bit",
+        VarType::Logic => "logic",
+        VarType::Int => "int",
+        VarType::ShortInt => "shortint",
+        VarType::LongInt => "longint",
+        VarType::Byte => "byte",
+        VarType::Enum => "enum",
+        VarType::ShortReal => "shortreal",
+        VarType::StdLogic
+        | VarType::StdLogicVector
+        | VarType::StdULogic
+        | VarType::StdULogicVector => "logic",
+    }
+}
+
+/// FST VHDL data type code for the `$attrbegin misc 02` record that wellen
+/// merges back into the var type (GTKWave's `FST_SVT_*`/`FST_SDT_*` layout).
+fn vhdl_data_type(var_type: VarType) -> u64 {
+    match var_type {
+        VarType::Boolean => 1,
+        VarType::Bit => 2,
+        VarType::BitVector => 3,
+        VarType::StdULogic => 4,
+        VarType::StdULogicVector => 5,
+        VarType::StdLogic => 6,
+        VarType::StdLogicVector => 7,
+        VarType::Integer => 10,
+        VarType::Real => 11,
+        VarType::Time => 14,
+        VarType::String => 16,
+        _ => 0,
+    }
+}
+
+const VHDL_VAR_SIGNAL: u64 = 1;
+const VHDL_DATA_TYPE_BITS: u64 = 10;
+
+fn direction_name(direction: VarDirection) -> Option<&'static str> {
+    match direction {
+        VarDirection::Input => Some("input"),
+        VarDirection::Output => Some("output"),
+        VarDirection::InOut => Some("inout"),
+        VarDirection::Buffer => Some("buffer"),
+        VarDirection::Linkage => Some("linkage"),
+        VarDirection::Unknown | VarDirection::Implicit => None,
+    }
+}
+
+/// VCD identifiers per signal. Aliases share a signal, so the first var seen
+/// for a signal gets a fresh identifier and every later var reuses it.
+struct VcdIds {
+    ids: HashMap<SignalRef, String>,
+    dumped: Vec<(SignalRef, String)>,
+}
+
+impl VcdIds {
+    fn new() -> Self {
+        Self {
+            ids: HashMap::new(),
+            dumped: Vec::new(),
+        }
+    }
+
+    fn get(&mut self, signal_ref: SignalRef) -> String {
+        if let Some(id) = self.ids.get(&signal_ref) {
+            return id.clone();
+        }
+        let id = index_to_vcd_id(self.dumped.len());
+        self.ids.insert(signal_ref, id.clone());
+        self.dumped.push((signal_ref, id.clone()));
+        id
+    }
+}
+
+fn write_var<W: Write>(
+    hierarchy: &Hierarchy,
+    var_ref: VarRef,
+    out: &mut W,
+    ids: &mut VcdIds,
+) -> std::io::Result<()> {
+    let var = &hierarchy[var_ref];
+    let mut name = var.name(hierarchy).replace(' ', "_");
+    if name.is_empty() {
+        name = format!("unnamed_{}", var_ref.index());
+    }
+    if let Some(index) = var.index() {
+        if index.msb() == index.lsb() {
+            name = format!("{} [{}]", name, index.lsb());
+        } else {
+            name = format!("{} [{}:{}]", name, index.msb(), index.lsb());
+        }
+    }
+
+    if let Some(direction) = direction_name(var.direction()) {
+        writeln!(out, "$comment direction {} $end", direction)?;
+    }
+    if let Some(type_name) = var.vhdl_type_name(hierarchy) {
+        writeln!(
+            out,
+            "$attrbegin misc 02 {} {} $end",
+            type_name.replace(' ', "_"),
+            (VHDL_VAR_SIGNAL << VHDL_DATA_TYPE_BITS) | vhdl_data_type(var.var_type())
+        )?;
+    }
+
+    let width = match var.var_type() {
+        VarType::Real | VarType::RealParameter | VarType::RealTime | VarType::ShortReal => 64,
+        _ => var.length(hierarchy).unwrap_or(1),
+    };
+    writeln!(
+        out,
+        "$var {} {} {} {} $end",
+        var_keyword(var.var_type()),
+        width,
+        ids.get(var.signal_ref()),
+        name
+    )
+}
+
+fn write_scope<W: Write>(
+    hierarchy: &Hierarchy,
+    scope_ref: ScopeRef,
+    out: &mut W,
+    ids: &mut VcdIds,
+) -> std::io::Result<()> {
+    let scope = &hierarchy[scope_ref];
+    writeln!(
+        out,
+        "$scope {} {} $end",
+        scope_keyword(scope.scope_type()),
+        scope.name(hierarchy).replace(' ', "_")
+    )?;
+
+    for var_ref in scope.vars(hierarchy) {
+        write_var(hierarchy, var_ref, out, ids)?;
+    }
+    for child in scope.scopes(hierarchy) {
+        write_scope(hierarchy, child, out, ids)?;
+    }
+
+    writeln!(out, "$upscope $end")
+}
+
+fn write_value<W: Write>(value: SignalValueRef, id: &str, out: &mut W) -> std::io::Result<()> {
+    match value {
+        SignalValueRef::Event => writeln!(out, "1{}", id),
+        SignalValueRef::Real(real) => writeln!(out, "r{} {}", real, id),
+        SignalValueRef::String(string) => writeln!(out, "s{} {}", string.replace(' ', "_"), id),
+        SignalValueRef::BitVec(_) => {
+            let bits = value.to_bit_string().unwrap_or_default();
+            if bits.len() == 1 {
+                writeln!(out, "{}{}", bits, id)
+            } else {
+                writeln!(out, "b{} {}", bits, id)
+            }
+        }
+    }
+}
+
+fn write_header<W: Write>(hierarchy: &Hierarchy, out: &mut W) -> std::io::Result<VcdIds> {
+    let date = match hierarchy.date().trim() {
+        "" => "Polaris Session",
+        date => date,
+    };
+    writeln!(out, "$date\n   {}\n$end", date)?;
+    writeln!(out, "$version\n   Polaris Native Wellen Converter\n$end")?;
+    writeln!(
+        out,
+        "$timescale\n\t{}\n$end",
+        timescale_to_string(hierarchy)
+    )?;
+
+    let mut ids = VcdIds::new();
+    for var_ref in hierarchy.vars() {
+        write_var(hierarchy, var_ref, out, &mut ids)?;
+    }
+    for scope_ref in hierarchy.scopes() {
+        write_scope(hierarchy, scope_ref, out, &mut ids)?;
+    }
+    writeln!(out, "$enddefinitions $end")?;
+    Ok(ids)
+}
+
+/// Write a whole waveform as VCD text, keeping scope kinds, var types, index
+/// ranges and aliases. Each signal is dumped once under its first var and
+/// only its settled (last delta) value per time step is written, including
+/// what was appended to a followed file. `on_progress` receives the
+/// percentage of the time range already written and `cancel` is polled
+/// between time steps.
+pub fn write_vcd<W: Write>(
+    session: &mut WaveformSession,
+    out: &mut W,
+    cancel: &AtomicBool,
+    mut on_progress: impl FnMut(u32),
+) -> Result<(), String> {
+    let ids = write_header(session.waveform.hierarchy(), out).map_err(write_error)?;
+
+    if cancel.load(Ordering::Relaxed) {
+        return Err(EXPORT_CANCELLED.to_string());
+    }
+    on_progress(0);
+    let indices: Vec<usize> = ids
+        .dumped
+        .iter()
+        .map(|(signal_ref, _)| signal_ref.index())
+        .collect();
+    session.load_signals(&indices)?;
+
+    let start = session.waveform.time_table().first().copied();
+    let end = session.end_time();
+    let mut changes = ids
+        .dumped
+        .iter()
+        .map(|(signal_ref, id)| {
+            Ok((
+                id.as_str(),
+                session.changes_since(*signal_ref, 0)?.peekable(),
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    // Min-heap of (next change time, position in `changes`)
+    let mut pending: BinaryHeap<Reverse<(u64, usize)>> = changes
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(pos, (_, iter))| Some(Reverse((iter.peek()?.0, pos))))
+        .collect();
+
+    if let Some(start) = start {
+        writeln!(out, "#{}\n$dumpvars", start).map_err(write_error)?;
+    }
+    let start = start.unwrap_or(0);
+    let mut last_percent = 0;
+    let mut current = start;
+    while let Some(&Reverse((time, _))) = pending.peek() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(EXPORT_CANCELLED.to_string());
+        }
+
+        if time != current {
+            if current == start {
+                writeln!(out, "$end").map_err(write_error)?;
+            }
+            writeln!(out, "#{}", time).map_err(write_error)?;
+            current = time;
+
+            let percent = ((time - start) as u128 * 100 / (end - start).max(1) as u128) as u32;
+            if percent != last_percent {
+                last_percent = percent;
+                on_progress(percent);
+            }
+        }
+
+        while let Some(&Reverse((next, pos))) = pending.peek() {
+            if next != time {
+                break;
+            }
+            pending.pop();
+            let (id, iter) = &mut changes[pos];
+            if let Some((_, value)) = iter.next() {
+                write_value(value, id, out).map_err(write_error)?;
+            }
+            if let Some((next, _)) = iter.peek() {
+                pending.push(Reverse((*next, pos)));
+            }
+        }
+    }
+    if current == start && !session.waveform.time_table().is_empty() {
+        writeln!(out, "$end").map_err(write_error)?;
+    }
+    if last_percent != 100 {
+        on_progress(100);
+    }
+
+    Ok(())
+}
+
+/// Convert any waveform file wellen can read into a VCD file on disk. The
+/// partial output is removed if the export fails or is cancelled.
+pub fn export_vcd(
+    src: &str,
+    dst: &str,
+    cancel: &AtomicBool,
+    on_progress: impl FnMut(u32),
+) -> Result<(), String> {
+    let mut session = WaveformSession::open(src)?;
+    if cancel.load(Ordering::Relaxed) {
+        return Err(EXPORT_CANCELLED.to_string());
+    }
+
+    let file = File::create(dst).map_err(|e| format!("Failed to create VCD file: {}", e))?;
+    let mut out = BufWriter::new(file);
+
+    let result = write_vcd(&mut session, &mut out, cancel, on_progress)
+        .and_then(|_| out.flush().map_err(write_error));
+    if result.is_err() {
+        drop(out);
+        let _ = std::fs::remove_file(dst);
+    }
+    result
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Export a VCD/FST/GHW file as VCD, emitting `vcd-export-progress-{cancel_token}`
+/// events as the time table is written
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn export_waveform_as_vcd(
+    src: String,
+    dst: String,
+    cancel_token: String,
+    app: AppHandle,
+    state: State<'_, ExportManager>,
+) -> Result<(), String> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    state
+        .cancel_flags
+        .lock()
+        .unwrap()
+        .insert(cancel_token.clone(), cancel.clone());
+
+    let event_name = format!("vcd-export-progress-{}", cancel_token);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        export_vcd(&src, &dst, &cancel, |percent| {
+            let _ = app.emit(&event_name, ExportProgressEvent { percent });
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to export waveform: {}", e));
+
+    state.cancel_flags.lock().unwrap().remove(&cancel_token);
+    result?
+}
+
+/// Ask a running export to stop; it fails with "Export cancelled"
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn cancel_waveform_export(
+    cancel_token: String,
+    state: State<ExportManager>,
+) -> Result<(), String> {
+    state
+        .cancel_flags
+        .lock()
+        .unwrap()
+        .get(&cancel_token)
+        .map(|cancel| cancel.store(true, Ordering::Relaxed))
+        .ok_or_else(|| format!("Export {} not found", cancel_token))
+}
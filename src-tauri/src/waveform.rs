@@ -0,0 +1,716 @@
+use crate::clock::ClockInfo;
+use crate::live_reload::{read_vcd, AppendedChange, VcdTail};
+use crate::sapho_names::{classify, owning_processor, processor_scopes, SignalClass};
+use crate::translation::{
+    load_symbol_tables, tables_key, var_paths, SymbolTables, TranslationTables,
+};
+use crate::waveform_codec;
+use crate::waveform_search::SearchIndex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "gui")]
+use tauri::ipc::Response;
+#[cfg(feature = "gui")]
+use tauri::State;
+use wellen::simple::Waveform;
+use wellen::{
+    FileFormat, Hierarchy, ScopeRef, Signal, SignalRef, SignalValueRef, TimeTableIdx,
+    TimescaleUnit, VarRef,
+};
+
+/// An open waveform file whose hierarchy and loaded signals stay in memory
+/// until the session is closed.
+pub struct WaveformSession {
+    pub path: String,
+    pub waveform: Waveform,
+    pub search: SearchIndex,
+    pub symbols: SymbolTables,
+    /// Clocks found by [`crate::clock::detect_clocks`], once it has run
+    pub clocks: Option<Vec<ClockInfo>>,
+    /// Changes appended to a VCD since it was opened; None for other formats
+    pub tail: Option<VcdTail>,
+}
+
+pub struct WaveformManager {
+    pub sessions: Arc<Mutex<HashMap<u32, Arc<Mutex<WaveformSession>>>>>,
+    pub next_id: Arc<Mutex<u32>>,
+}
+
+impl Default for WaveformManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WaveformManager {
+    pub fn new() -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(Mutex::new(1)),
+        }
+    }
+
+    /// Look up a session by id. The returned handle is locked independently
+    /// of the session table, so a slow load does not block other sessions.
+    pub fn get(&self, session_id: u32) -> Result<Arc<Mutex<WaveformSession>>, String> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .cloned()
+            .ok_or_else(|| format!("Waveform session {} not found", session_id))
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VarNode {
+    pub name: String,
+    pub path: String,
+    pub var_ref: usize,
+    pub signal_ref: usize,
+    pub var_type: String,
+    pub width: u32,
+    pub class: SignalClass,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeNode {
+    pub name: String,
+    pub path: String,
+    pub scope_type: String,
+    pub scopes: Vec<ScopeNode>,
+    pub vars: Vec<VarNode>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveformHierarchy {
+    pub session_id: u32,
+    pub path: String,
+    pub file_format: String,
+    pub timescale: String,
+    pub time_range: TimeRange,
+    pub scopes: Vec<ScopeNode>,
+    pub vars: Vec<VarNode>,
+}
+
+/// Value changes of a single signal as two parallel arrays, which is much
+/// smaller over IPC than one object per change.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalChanges {
+    pub signal_ref: usize,
+    pub times: Vec<u64>,
+    pub values: Vec<String>,
+}
+
+/// A pixel column holding more than one transition. `min`/`max` cover every
+/// value seen in the column and are only set for numeric signals.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DenseBucket {
+    pub start: u64,
+    pub end: u64,
+    pub transitions: u32,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Changes of one signal inside a time window, decimated to at most one
+/// entry per bucket. The first change is the value in effect at `start`,
+/// even if it happened earlier. Buckets with several transitions keep only
+/// their last value and are described in `dense`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalWindow {
+    pub signal_ref: usize,
+    pub start: u64,
+    pub end: u64,
+    pub times: Vec<u64>,
+    pub values: Vec<String>,
+    pub dense: Vec<DenseBucket>,
+}
+
+/// Format a wellen timescale the way it appears in a VCD header (`1ns`, `10ps`).
+pub fn timescale_to_string(hierarchy: &Hierarchy) -> String {
+    match hierarchy.timescale() {
+        Some(ts) => {
+            let unit = match ts.unit {
+                TimescaleUnit::ZeptoSeconds => "zs",
+                TimescaleUnit::AttoSeconds => "as",
+                TimescaleUnit::FemtoSeconds => "fs",
+                TimescaleUnit::PicoSeconds => "ps",
+                TimescaleUnit::NanoSeconds => "ns",
+                TimescaleUnit::MicroSeconds => "us",
+                TimescaleUnit::MilliSeconds => "ms",
+                TimescaleUnit::Seconds => "s",
+                TimescaleUnit::Unknown => "ns",
+            };
+            format!("{}{}", ts.factor, unit)
+        }
+        None => "1ns".to_string(),
+    }
+}
+
+/// Render a value the same way it would appear in a VCD body, without the
+/// `b`/`r`/`s` prefix.
+pub fn value_to_string(value: &SignalValueRef) -> String {
+    match value {
+        SignalValueRef::Event => "1".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Interpret a value as a number for min/max summaries. Bit vectors with
+/// unknown bits and strings have no numeric value.
+pub fn value_to_f64(value: &SignalValueRef) -> Option<f64> {
+    match value {
+        SignalValueRef::Real(real) => Some(*real),
+        SignalValueRef::BitVec(_) => {
+            value
+                .to_bit_string()?
+                .chars()
+                .try_fold(0.0, |acc, bit| match bit {
+                    '0' => Some(acc * 2.0),
+                    '1' => Some(acc * 2.0 + 1.0),
+                    _ => None,
+                })
+        }
+        _ => None,
+    }
+}
+
+/// Walk the changes of a signal starting with the one in effect at time
+/// index `from` (or the first change, if `from` comes before it). Delta
+/// cycles are collapsed to their settled (last) value.
+pub fn settled_changes(
+    signal: &Signal,
+    from: TimeTableIdx,
+) -> impl Iterator<Item = (TimeTableIdx, SignalValueRef<'_>)> {
+    let mut offset = signal
+        .get_offset(from)
+        .or_else(|| signal.get_offset(signal.get_first_time_idx()?));
+
+    std::iter::from_fn(move || {
+        let current = offset.take()?;
+        let value = signal.get_value_at(&current, current.elements - 1);
+        offset = current
+            .next_index
+            .and_then(|next| signal.get_offset(next.get()));
+        Some((signal.get_time_idx_at(&current), value))
+    })
+}
+
+fn widen(range: Option<(f64, f64)>, value: Option<f64>) -> Option<(f64, f64)> {
+    match (range, value) {
+        (Some((min, max)), Some(value)) => Some((min.min(value), max.max(value))),
+        (None, Some(value)) => Some((value, value)),
+        (range, None) => range,
+    }
+}
+
+fn var_node(hierarchy: &Hierarchy, var_ref: VarRef) -> VarNode {
+    let var = &hierarchy[var_ref];
+    let name = var.name(hierarchy).to_string();
+    let path = var.full_name(hierarchy);
+    VarNode {
+        class: classify(&name, &path),
+        name,
+        path,
+        var_ref: var_ref.index(),
+        signal_ref: var.signal_ref().index(),
+        var_type: format!("{:?}", var.var_type()).to_lowercase(),
+        width: var.length(hierarchy).unwrap_or(1),
+    }
+}
+
+fn scope_node(hierarchy: &Hierarchy, scope_ref: ScopeRef) -> ScopeNode {
+    let scope = &hierarchy[scope_ref];
+    ScopeNode {
+        name: scope.name(hierarchy).to_string(),
+        path: scope.full_name(hierarchy),
+        scope_type: format!("{:?}", scope.scope_type()).to_lowercase(),
+        scopes: scope
+            .scopes(hierarchy)
+            .map(|child| scope_node(hierarchy, child))
+            .collect(),
+        vars: scope
+            .vars(hierarchy)
+            .map(|var| var_node(hierarchy, var))
+            .collect(),
+    }
+}
+
+/// Detect whether a file is VCD, FST or GHW from its content, regardless of
+/// its extension.
+pub fn detect_format(path: &str) -> Result<FileFormat, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open waveform file: {}", e))?;
+    match wellen::viewers::detect_file_format(&mut BufReader::new(file)) {
+        FileFormat::Unknown => Err(format!("Unsupported waveform format: {}", path)),
+        format => Ok(format),
+    }
+}
+
+impl WaveformSession {
+    /// Read the header and time table of a VCD, FST or GHW file. Signal data
+    /// is only loaded on demand through [`WaveformSession::load_signals`].
+    pub fn open(path: &str) -> Result<Self, String> {
+        // wellen panics on unreadable files, so check the file up front
+        let (waveform, tail) = match detect_format(path)? {
+            FileFormat::Vcd => {
+                let (waveform, tail) = read_vcd(path)?;
+                (waveform, Some(tail))
+            }
+            _ => {
+                let waveform = wellen::simple::read(path)
+                    .map_err(|e| format!("Failed to read waveform file: {}", e))?;
+                (waveform, None)
+            }
+        };
+
+        let mut session = Self {
+            path: path.to_string(),
+            waveform,
+            search: SearchIndex::default(),
+            symbols: SymbolTables::new(),
+            clocks: None,
+            tail,
+        };
+        session.symbols = load_symbol_tables(&session, None);
+        Ok(session)
+    }
+
+    /// Name of the processor a signal belongs to, see [`owning_processor`].
+    pub fn processor_of(&self, path: &str) -> Option<String> {
+        let scopes = processor_scopes(&var_paths(self));
+        owning_processor(&scopes, path).map(str::to_string)
+    }
+
+    /// Symbol tables of a processor; empty when none were loaded for it.
+    pub fn processor_symbols(&self, processor: Option<&str>) -> TranslationTables {
+        tables_key(&self.symbols, processor)
+            .and_then(|key| self.symbols.get(&key))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Symbol tables of the processor the signal at `path` belongs to.
+    pub fn symbols_for(&self, path: &str) -> TranslationTables {
+        self.processor_symbols(self.processor_of(path).as_deref())
+    }
+
+    /// Last time of the waveform, including time steps appended since it was
+    /// opened.
+    pub fn end_time(&self) -> u64 {
+        let end = self.waveform.time_table().last().copied().unwrap_or(0);
+        self.tail.as_ref().map_or(end, |tail| tail.end().max(end))
+    }
+
+    pub fn hierarchy(&self, session_id: u32) -> WaveformHierarchy {
+        let hierarchy = self.waveform.hierarchy();
+        let time_table = self.waveform.time_table();
+
+        WaveformHierarchy {
+            session_id,
+            path: self.path.clone(),
+            file_format: format!("{:?}", hierarchy.file_format()).to_lowercase(),
+            timescale: timescale_to_string(hierarchy),
+            time_range: TimeRange {
+                start: time_table.first().copied().unwrap_or(0),
+                end: self.end_time(),
+            },
+            scopes: hierarchy
+                .scopes()
+                .map(|scope| scope_node(hierarchy, scope))
+                .collect(),
+            vars: hierarchy
+                .vars()
+                .map(|var| var_node(hierarchy, var))
+                .collect(),
+        }
+    }
+
+    /// Convert raw indices coming from the frontend into signal references,
+    /// rejecting anything that does not exist in this file.
+    pub fn signal_refs(&self, indices: &[usize]) -> Result<Vec<SignalRef>, String> {
+        let hierarchy = self.waveform.hierarchy();
+        indices
+            .iter()
+            .map(|&index| {
+                SignalRef::from_index(index)
+                    .filter(|signal_ref| hierarchy.get_signal_tpe(*signal_ref).is_some())
+                    .ok_or_else(|| format!("Unknown signal reference {}", index))
+            })
+            .collect()
+    }
+
+    pub fn load_signals(&mut self, indices: &[usize]) -> Result<(), String> {
+        let signal_refs = self.signal_refs(indices)?;
+        self.waveform.load_signals_multi_threaded(&signal_refs);
+        self.track_appended(&signal_refs)
+    }
+
+    pub fn unload_signals(&mut self, indices: &[usize]) -> Result<(), String> {
+        let signal_refs = self.signal_refs(indices)?;
+        self.waveform.unload_signals(&signal_refs);
+        self.search.forget(&signal_refs);
+        if let Some(tail) = self.tail.as_mut() {
+            tail.forget(&signal_refs);
+        }
+        Ok(())
+    }
+
+    /// Make sure a signal is in memory and return its reference.
+    pub fn ensure_loaded(&mut self, index: usize) -> Result<SignalRef, String> {
+        let signal_ref = self.signal_refs(&[index])?[0];
+        if self.waveform.get_signal(signal_ref).is_none() {
+            self.waveform.load_signals(&[signal_ref]);
+        }
+        self.track_appended(&[signal_ref])?;
+        Ok(signal_ref)
+    }
+
+    /// Keep the changes appended to the file for loaded signals.
+    fn track_appended(&mut self, signal_refs: &[SignalRef]) -> Result<(), String> {
+        let Self {
+            path,
+            waveform,
+            tail,
+            ..
+        } = self;
+        match tail {
+            Some(tail) => tail.track(path, waveform.hierarchy(), signal_refs),
+            None => Ok(()),
+        }
+    }
+
+    fn signal(&self, signal_ref: SignalRef) -> Result<&Signal, String> {
+        self.waveform
+            .get_signal(signal_ref)
+            .ok_or_else(|| format!("Failed to load signal {}", signal_ref.index()))
+    }
+
+    /// Changes of a signal appended since the file was opened, without a
+    /// first one that repeats the loaded value it follows.
+    fn appended(&self, signal_ref: SignalRef) -> &[AppendedChange] {
+        let appended = self
+            .tail
+            .as_ref()
+            .map_or(&[][..], |tail| tail.changes(signal_ref));
+        let (Some(first), Some(signal)) = (appended.first(), self.waveform.get_signal(signal_ref))
+        else {
+            return appended;
+        };
+
+        let time_table = self.waveform.time_table();
+        let at = time_table
+            .partition_point(|&t| t <= first.time)
+            .saturating_sub(1);
+        let repeated = settled_changes(signal, at as TimeTableIdx)
+            .next()
+            .is_some_and(|(time_idx, value)| {
+                time_table[time_idx as usize] <= first.time
+                    && value_to_string(&value) == value_to_string(&first.value())
+            });
+        if repeated {
+            &appended[1..]
+        } else {
+            appended
+        }
+    }
+
+    /// Settled changes of a loaded signal starting with the one in effect at
+    /// `start` (or the first one, if `start` comes before it), followed by
+    /// those appended since the file was opened. Everything that reads
+    /// signal values goes through here, so a followed file is seen whole.
+    pub fn changes_since(
+        &self,
+        signal_ref: SignalRef,
+        start: u64,
+    ) -> Result<impl Iterator<Item = (u64, SignalValueRef<'_>)>, String> {
+        let time_table = self.waveform.time_table();
+        let signal = self.signal(signal_ref)?;
+        let appended = self.appended(signal_ref);
+
+        // Last time step at or before `start`, which holds the initial value
+        let from = time_table
+            .partition_point(|&t| t <= start)
+            .saturating_sub(1);
+        let appended_from = appended
+            .partition_point(|change| change.time <= start)
+            .saturating_sub(1);
+        // Appended changes replace loaded ones from their first time on
+        let loaded_until = match appended.get(appended_from) {
+            Some(change) if change.time <= start => 0,
+            _ => appended.first().map_or(u64::MAX, |change| change.time),
+        };
+
+        let loaded = settled_changes(signal, from as TimeTableIdx)
+            .map(|(time_idx, value)| (time_table[time_idx as usize], value))
+            .take_while(move |(time, _)| *time < loaded_until);
+        let appended = appended[appended_from..]
+            .iter()
+            .map(|change| (change.time, change.value()));
+        Ok(loaded.chain(appended))
+    }
+
+    /// Collect every change of one signal, loading it first if needed.
+    pub fn signal_changes(&mut self, index: usize) -> Result<SignalChanges, String> {
+        let signal_ref = self.ensure_loaded(index)?;
+
+        let mut changes = SignalChanges {
+            signal_ref: index,
+            times: Vec::new(),
+            values: Vec::new(),
+        };
+        for (time, value) in self.changes_since(signal_ref, 0)? {
+            changes.times.push(time);
+            changes.values.push(value_to_string(&value));
+        }
+
+        Ok(changes)
+    }
+
+    /// Same as [`WaveformSession::signal_changes`], packed with
+    /// [`waveform_codec::encode_signal_changes`].
+    pub fn encoded_signal_changes(&mut self, index: usize) -> Result<Vec<u8>, String> {
+        let signal_ref = self.ensure_loaded(index)?;
+        let encoding = self
+            .waveform
+            .hierarchy()
+            .get_signal_tpe(signal_ref)
+            .ok_or_else(|| format!("Unknown signal reference {}", index))?;
+        let changes: Vec<_> = self.changes_since(signal_ref, 0)?.collect();
+        Ok(waveform_codec::encode_signal_changes(
+            index, encoding, &changes,
+        ))
+    }
+
+    /// Collect the changes of one signal within `[start, end]`, reduced to at
+    /// most `buckets` entries so a zoomed-out view never has to draw more
+    /// transitions than it has pixels.
+    pub fn signal_window(
+        &mut self,
+        index: usize,
+        start: u64,
+        end: u64,
+        buckets: usize,
+    ) -> Result<SignalWindow, String> {
+        let signal_ref = self.ensure_loaded(index)?;
+
+        let end = end.max(start);
+        let buckets = buckets.max(1) as u128;
+        let span = (end - start) as u128 + 1;
+        let bucket_of = |time: u64| (time.saturating_sub(start) as u128 * buckets / span) as usize;
+
+        let mut window = SignalWindow {
+            signal_ref: index,
+            start,
+            end,
+            times: Vec::new(),
+            values: Vec::new(),
+            dense: Vec::new(),
+        };
+
+        let mut changes = self
+            .changes_since(signal_ref, start)?
+            .take_while(|(time, _)| *time <= end)
+            .peekable();
+
+        // Numeric value in effect before the current bucket, so a column's
+        // range also covers the level it starts from
+        let mut previous = None;
+        if let Some((time, value)) = changes.next_if(|(time, _)| *time <= start) {
+            previous = value_to_f64(&value);
+            window.times.push(time);
+            window.values.push(value_to_string(&value));
+        }
+
+        while let Some((first_time, value)) = changes.next() {
+            let bucket = bucket_of(first_time);
+            let mut range = widen(previous.map(|p| (p, p)), value_to_f64(&value));
+            let mut last = (first_time, value);
+            let mut transitions = 1;
+
+            while let Some((time, value)) = changes.next_if(|(time, _)| bucket_of(*time) == bucket)
+            {
+                range = widen(range, value_to_f64(&value));
+                transitions += 1;
+                last = (time, value);
+            }
+
+            if transitions > 1 {
+                window.dense.push(DenseBucket {
+                    start: first_time,
+                    end: last.0,
+                    transitions,
+                    min: range.map(|(min, _)| min),
+                    max: range.map(|(_, max)| max),
+                });
+            }
+            previous = value_to_f64(&last.1);
+            window.times.push(last.0);
+            window.values.push(value_to_string(&last.1));
+        }
+
+        Ok(window)
+    }
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Open a VCD/FST/GHW file and keep it in memory as a new session
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn open_waveform(path: String, state: State<'_, WaveformManager>) -> Result<u32, String> {
+    let session = tauri::async_runtime::spawn_blocking(move || WaveformSession::open(&path))
+        .await
+        .map_err(|e| format!("Failed to open waveform: {}", e))??;
+
+    let mut next_id = state.next_id.lock().unwrap();
+    let id = *next_id;
+    *next_id += 1;
+
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .insert(id, Arc::new(Mutex::new(session)));
+
+    Ok(id)
+}
+
+/// List the scopes and variables of an open session
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn get_waveform_hierarchy(
+    session_id: u32,
+    state: State<WaveformManager>,
+) -> Result<WaveformHierarchy, String> {
+    let session = state.get(session_id)?;
+    let session = session.lock().unwrap();
+    Ok(session.hierarchy(session_id))
+}
+
+/// Hierarchy of an open session in the binary layout of `waveform_codec`
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn get_waveform_hierarchy_binary(
+    session_id: u32,
+    state: State<WaveformManager>,
+) -> Result<Response, String> {
+    let session = state.get(session_id)?;
+    let session = session.lock().unwrap();
+    Ok(Response::new(waveform_codec::encode_hierarchy(
+        &session.hierarchy(session_id),
+    )))
+}
+
+/// Load the value changes of the given signals into the session
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn load_waveform_signals(
+    session_id: u32,
+    signal_refs: Vec<usize>,
+    state: State<'_, WaveformManager>,
+) -> Result<(), String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || session.lock().unwrap().load_signals(&signal_refs))
+        .await
+        .map_err(|e| format!("Failed to load signals: {}", e))?
+}
+
+/// Fetch the value changes of a single signal, loading it on first use
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn load_signal_changes(
+    session_id: u32,
+    signal_ref: usize,
+    state: State<'_, WaveformManager>,
+) -> Result<SignalChanges, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || session.lock().unwrap().signal_changes(signal_ref))
+        .await
+        .map_err(|e| format!("Failed to load signal changes: {}", e))?
+}
+
+/// Fetch the value changes of a single signal as packed binary
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn load_signal_changes_binary(
+    session_id: u32,
+    signal_ref: usize,
+    state: State<'_, WaveformManager>,
+) -> Result<Response, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        session
+            .lock()
+            .unwrap()
+            .encoded_signal_changes(signal_ref)
+            .map(Response::new)
+    })
+    .await
+    .map_err(|e| format!("Failed to load signal changes: {}", e))?
+}
+
+/// Fetch the changes of one signal between `start` and `end`, decimated to
+/// at most `buckets` entries (usually the canvas width in pixels)
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn query_signal_window(
+    session_id: u32,
+    signal_ref: usize,
+    start: u64,
+    end: u64,
+    buckets: usize,
+    state: State<'_, WaveformManager>,
+) -> Result<SignalWindow, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        session
+            .lock()
+            .unwrap()
+            .signal_window(signal_ref, start, end, buckets)
+    })
+    .await
+    .map_err(|e| format!("Failed to query signal window: {}", e))?
+}
+
+/// Drop previously loaded signals to free memory
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn unload_waveform_signals(
+    session_id: u32,
+    signal_refs: Vec<usize>,
+    state: State<WaveformManager>,
+) -> Result<(), String> {
+    let session = state.get(session_id)?;
+    let mut session = session.lock().unwrap();
+    session.unload_signals(&signal_refs)
+}
+
+/// Close a session and release its memory
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn close_waveform(session_id: u32, state: State<WaveformManager>) -> Result<(), String> {
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .remove(&session_id)
+        .map(|_| ())
+        .ok_or_else(|| format!("Waveform session {} not found", session_id))
+}
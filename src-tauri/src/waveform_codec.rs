@@ -0,0 +1,193 @@
+#[cfg(feature = "gui")]
+use crate::waveform::{ScopeNode, VarNode, WaveformHierarchy};
+use wellen::{SignalEncoding, SignalValueRef};
+
+// Binary layouts shared with the decoder in src/scripts/waveformCodec.js.
+// All numbers are little endian and times are f64 (exact up to 2^53).
+//
+// Signal changes:
+//   u8  kind (KIND_BITS, KIND_REAL, KIND_STRING)
+//   u8  bits per state for KIND_BITS (1, 2 or 4), 0 otherwise
+//   u16 reserved
+//   u32 width in bits
+//   u32 change count
+//   u32 signal reference
+//   f64 times[count]
+//   values: packed bit states, f64 reals, or u32 length + UTF-8 strings
+//
+// Hierarchy:
+//   u32 session id, f64 start, f64 end, str path, str file format, str timescale
+//   records, depth first: RECORD_SCOPE name type, RECORD_END_SCOPE,
+//   RECORD_VAR name type u32 var_ref u32 signal_ref u32 width
+//              str kind, str function, str variable, u32 port
+//   where str is a u16 length followed by UTF-8 bytes, an empty str is a
+//   missing function or variable and NO_PORT a missing port
+
+pub const KIND_BITS: u8 = 0;
+pub const KIND_REAL: u8 = 1;
+pub const KIND_STRING: u8 = 2;
+
+#[cfg(feature = "gui")]
+pub const RECORD_SCOPE: u8 = 0;
+#[cfg(feature = "gui")]
+pub const RECORD_END_SCOPE: u8 = 1;
+#[cfg(feature = "gui")]
+pub const RECORD_VAR: u8 = 2;
+
+const HEADER_SIZE: usize = 16;
+
+#[cfg(feature = "gui")]
+pub const NO_PORT: u32 = u32::MAX;
+
+/// Bit states in code order. Two-state values only use the first two codes
+/// and four-state values the first four, so they fit in 1 or 2 bits.
+const STATE_CHARS: &[u8] = b"01xzhuwl-";
+
+#[cfg(feature = "gui")]
+fn write_str(out: &mut Vec<u8>, value: &str) {
+    let bytes = &value.as_bytes()[..value.len().min(u16::MAX as usize)];
+    out.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn state_code(state: u8, bits_per_state: u32) -> u8 {
+    let code = STATE_CHARS
+        .iter()
+        .position(|&c| c == state.to_ascii_lowercase())
+        .unwrap_or(2) as u8;
+    if u32::from(code) < (1 << bits_per_state) {
+        code
+    } else {
+        2
+    }
+}
+
+/// Bits per state a bit string needs: two-state, four-state or nine-state.
+fn states_bits(bit_string: &str) -> u32 {
+    let needed = bit_string
+        .bytes()
+        .map(|state| state_code(state, 4))
+        .max()
+        .unwrap_or(0);
+    match needed {
+        0..=1 => 1,
+        2..=3 => 2,
+        _ => 4,
+    }
+}
+
+/// Pack a bit string MSB first, filling each byte from its high bits.
+fn pack_bits(out: &mut Vec<u8>, bit_string: &str, width: u32, bits_per_state: u32) {
+    let bytes = (width * bits_per_state).div_ceil(8) as usize;
+    let start = out.len();
+    out.resize(start + bytes, 0);
+
+    for (i, state) in bit_string.bytes().take(width as usize).enumerate() {
+        let bit_offset = i as u32 * bits_per_state;
+        let shift = 8 - bits_per_state - (bit_offset % 8);
+        out[start + (bit_offset / 8) as usize] |= state_code(state, bits_per_state) << shift;
+    }
+}
+
+/// Encode the `(time, value)` changes of a signal, as returned by
+/// [`crate::waveform::WaveformSession::changes_since`].
+pub fn encode_signal_changes(
+    signal_ref: usize,
+    encoding: SignalEncoding,
+    changes: &[(u64, SignalValueRef)],
+) -> Vec<u8> {
+    let (kind, width) = match encoding {
+        SignalEncoding::Real => (KIND_REAL, 64),
+        SignalEncoding::String => (KIND_STRING, 0),
+        SignalEncoding::BitVector(width) => (KIND_BITS, width.max(1)),
+    };
+    let bit_string = |value: &SignalValueRef| match value {
+        SignalValueRef::Event => "1".to_string(),
+        _ => value.to_bit_string().unwrap_or_default(),
+    };
+    let bits_per_state = match kind {
+        KIND_BITS => changes
+            .iter()
+            .map(|(_, value)| states_bits(&bit_string(value)))
+            .max()
+            .unwrap_or(4),
+        _ => 0,
+    };
+    let count = changes.len();
+
+    let mut out = Vec::with_capacity(HEADER_SIZE + count * 16);
+    out.push(kind);
+    out.push(bits_per_state as u8);
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&(count as u32).to_le_bytes());
+    out.extend_from_slice(&(signal_ref as u32).to_le_bytes());
+
+    for (time, _) in changes {
+        out.extend_from_slice(&(*time as f64).to_le_bytes());
+    }
+
+    for (_, value) in changes {
+        match (kind, value) {
+            (KIND_REAL, SignalValueRef::Real(real)) => out.extend_from_slice(&real.to_le_bytes()),
+            (KIND_REAL, _) => out.extend_from_slice(&f64::NAN.to_le_bytes()),
+            (KIND_STRING, SignalValueRef::String(string)) => {
+                out.extend_from_slice(&(string.len() as u32).to_le_bytes());
+                out.extend_from_slice(string.as_bytes());
+            }
+            (KIND_STRING, _) => out.extend_from_slice(&0u32.to_le_bytes()),
+            _ => pack_bits(&mut out, &bit_string(value), width, bits_per_state),
+        }
+    }
+
+    out
+}
+
+#[cfg(feature = "gui")]
+fn encode_var(out: &mut Vec<u8>, var: &VarNode) {
+    out.push(RECORD_VAR);
+    write_str(out, &var.name);
+    write_str(out, &var.var_type);
+    out.extend_from_slice(&(var.var_ref as u32).to_le_bytes());
+    out.extend_from_slice(&(var.signal_ref as u32).to_le_bytes());
+    out.extend_from_slice(&var.width.to_le_bytes());
+    write_str(out, var.class.kind.as_str());
+    write_str(out, var.class.function.as_deref().unwrap_or(""));
+    write_str(out, var.class.variable.as_deref().unwrap_or(""));
+    out.extend_from_slice(&var.class.port.unwrap_or(NO_PORT).to_le_bytes());
+}
+
+#[cfg(feature = "gui")]
+fn encode_scope(out: &mut Vec<u8>, scope: &ScopeNode) {
+    out.push(RECORD_SCOPE);
+    write_str(out, &scope.name);
+    write_str(out, &scope.scope_type);
+    for var in &scope.vars {
+        encode_var(out, var);
+    }
+    for child in &scope.scopes {
+        encode_scope(out, child);
+    }
+    out.push(RECORD_END_SCOPE);
+}
+
+/// Encode a hierarchy as a flat, depth-first record stream.
+#[cfg(feature = "gui")]
+pub fn encode_hierarchy(hierarchy: &WaveformHierarchy) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&hierarchy.session_id.to_le_bytes());
+    out.extend_from_slice(&(hierarchy.time_range.start as f64).to_le_bytes());
+    out.extend_from_slice(&(hierarchy.time_range.end as f64).to_le_bytes());
+    write_str(&mut out, &hierarchy.path);
+    write_str(&mut out, &hierarchy.file_format);
+    write_str(&mut out, &hierarchy.timescale);
+
+    for var in &hierarchy.vars {
+        encode_var(&mut out, var);
+    }
+    for scope in &hierarchy.scopes {
+        encode_scope(&mut out, scope);
+    }
+
+    out
+}
@@ -0,0 +1,456 @@
+use crate::sapho_float::{decode_pair, format_value, FloatFormat, SaphoFloat};
+use crate::sapho_names::{
+    classify, owning_processor, processor_instance, processor_scopes, SignalClass, SignalKind,
+};
+#[cfg(feature = "gui")]
+use crate::spf::SpfProject;
+#[cfg(feature = "gui")]
+use crate::translation::load_symbol_tables;
+use crate::translation::{tables_key, var_paths};
+use crate::waveform::{timescale_to_string, SignalChanges, WaveformSession};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use wellen::SignalEncoding;
+
+/// Mismatches listed when the caller does not set a limit.
+const DEFAULT_LIMIT: usize = 1000;
+
+/// A signal of the first waveform paired with one of the second by hand.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalMapping {
+    pub a: String,
+    pub b: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffOptions {
+    /// Largest absolute difference between float values still taken as equal
+    #[serde(default)]
+    pub tolerance: f64,
+    /// Same, as a fraction of the largest part of either value
+    #[serde(default)]
+    pub relative_tolerance: f64,
+    /// Format of `float` variables whose processor has none in its symbol
+    /// tables; without either they are compared bit by bit
+    #[serde(default)]
+    pub float_format: Option<FloatFormat>,
+    /// Mismatches to list, [`DEFAULT_LIMIT`] when None
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// How two signals were paired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchedBy {
+    Mapping,
+    Path,
+    /// Same processor, kind, function, variable and port
+    Class,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalComparison {
+    pub path_a: String,
+    pub path_b: String,
+    pub matched_by: MatchedBy,
+    pub mismatches: usize,
+    pub first_mismatch: Option<u64>,
+}
+
+/// An interval in which two paired signals held different values.
+/// Float-encoded values are shown decoded.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Mismatch {
+    pub path_a: String,
+    pub path_b: String,
+    pub start: u64,
+    pub end: u64,
+    pub value_a: Option<String>,
+    pub value_b: Option<String>,
+}
+
+/// Differences between two runs over the time both of them cover.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WaveformDiff {
+    pub timescale: String,
+    pub end_a: u64,
+    pub end_b: u64,
+    pub signals: Vec<SignalComparison>,
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    /// Earliest mismatches first, up to the limit
+    pub mismatches: Vec<Mismatch>,
+    /// Mismatches left out because of the limit
+    pub truncated: usize,
+}
+
+struct DiffVar {
+    path: String,
+    signal_ref: usize,
+    class: SignalClass,
+    real: bool,
+    /// Format of the processor the var belongs to
+    float_format: Option<FloatFormat>,
+}
+
+/// Every var of a session, with the float format of its processor's symbol
+/// tables, or `fallback` where they have none.
+fn diff_vars(session: &WaveformSession, fallback: Option<FloatFormat>) -> Vec<DiffVar> {
+    let scopes = processor_scopes(&var_paths(session));
+    let hierarchy = session.waveform.hierarchy();
+    hierarchy
+        .all_vars()
+        .map(|var_ref| &hierarchy[var_ref])
+        .map(|var| {
+            let path = var.full_name(hierarchy);
+            let float_format = tables_key(&session.symbols, owning_processor(&scopes, &path))
+                .and_then(|key| session.symbols[&key].float_format)
+                .or(fallback);
+            DiffVar {
+                class: classify(var.name(hierarchy), &path),
+                real: var.signal_encoding(hierarchy) == SignalEncoding::Real,
+                signal_ref: var.signal_ref().index(),
+                float_format,
+                path,
+            }
+        })
+        .collect()
+}
+
+/// What identifies a signal regardless of its scopes, for the kinds the
+/// classifier recognizes by name.
+fn class_key(var: &DiffVar) -> Option<String> {
+    if matches!(var.class.kind, SignalKind::Flag | SignalKind::Other) {
+        return None;
+    }
+    let class = &var.class;
+    Some(format!(
+        "{}/{}/{}/{}/{}",
+        processor_instance(&var.path).unwrap_or(""),
+        class.kind.as_str(),
+        class.function.as_deref().unwrap_or(""),
+        class.variable.as_deref().unwrap_or(""),
+        class.port.map_or(String::new(), |port| port.to_string())
+    ))
+}
+
+/// Keys that belong to exactly one var.
+fn unique_keys(vars: &[&DiffVar]) -> HashMap<String, usize> {
+    let mut keys: HashMap<String, Option<usize>> = HashMap::new();
+    for (i, var) in vars.iter().enumerate() {
+        if let Some(key) = class_key(var) {
+            keys.entry(key)
+                .and_modify(|found| *found = None)
+                .or_insert(Some(i));
+        }
+    }
+    keys.into_iter()
+        .filter_map(|(key, i)| Some((key, i?)))
+        .collect()
+}
+
+/// Var indices paired between two waveforms, and the paths left unpaired.
+struct Pairing {
+    pairs: Vec<(usize, usize, MatchedBy)>,
+    only_in_a: Vec<String>,
+    only_in_b: Vec<String>,
+}
+
+/// Pair the vars of two waveforms: by the mapping first, then by path, then
+/// by class key.
+fn pair_vars(
+    vars_a: &[DiffVar],
+    vars_b: &[DiffVar],
+    signal_map: &[SignalMapping],
+) -> Result<Pairing, String> {
+    let index = |vars: &[DiffVar], path: &str, side: &str| {
+        vars.iter()
+            .position(|var| var.path == path)
+            .ok_or_else(|| format!("Failed to compare: {} is not a signal of {}", path, side))
+    };
+
+    let mut pairs = Vec::new();
+    let (mut used_a, mut used_b) = (HashSet::new(), HashSet::new());
+    for mapping in signal_map {
+        let (a, b) = (
+            index(vars_a, &mapping.a, "a")?,
+            index(vars_b, &mapping.b, "b")?,
+        );
+        if used_a.insert(a) && used_b.insert(b) {
+            pairs.push((a, b, MatchedBy::Mapping));
+        }
+    }
+
+    let paths_b: HashMap<&str, usize> = vars_b
+        .iter()
+        .enumerate()
+        .map(|(i, var)| (var.path.as_str(), i))
+        .collect();
+    for (a, var) in vars_a.iter().enumerate() {
+        if let Some(&b) = paths_b.get(var.path.as_str()) {
+            if !used_a.contains(&a) && used_b.insert(b) {
+                used_a.insert(a);
+                pairs.push((a, b, MatchedBy::Path));
+            }
+        }
+    }
+
+    let rest = |vars: &[DiffVar], used: &HashSet<usize>| -> Vec<usize> {
+        (0..vars.len()).filter(|i| !used.contains(i)).collect()
+    };
+    let (rest_a, rest_b) = (rest(vars_a, &used_a), rest(vars_b, &used_b));
+    let keys_b = unique_keys(&rest_b.iter().map(|&i| &vars_b[i]).collect::<Vec<_>>());
+    let keys_a = unique_keys(&rest_a.iter().map(|&i| &vars_a[i]).collect::<Vec<_>>());
+    for (key, i) in keys_a {
+        if let Some(&j) = keys_b.get(&key) {
+            let (a, b) = (rest_a[i], rest_b[j]);
+            used_a.insert(a);
+            used_b.insert(b);
+            pairs.push((a, b, MatchedBy::Class));
+        }
+    }
+    pairs.sort_unstable_by_key(|&(a, b, _)| (a, b));
+
+    let unpaired = |vars: &[DiffVar], used: &HashSet<usize>| -> Vec<String> {
+        rest(vars, used)
+            .into_iter()
+            .map(|i| vars[i].path.clone())
+            .collect()
+    };
+    Ok(Pairing {
+        pairs,
+        only_in_a: unpaired(vars_a, &used_a),
+        only_in_b: unpaired(vars_b, &used_b),
+    })
+}
+
+/// Numbers a float-encoded value stands for: both halves of vectors and
+/// complex values, SAPHO floats of a known format and reals.
+fn float_numbers(value: &str, var: &DiffVar) -> Option<Vec<f64>> {
+    if var.real {
+        return value.parse().ok().map(|number| vec![number]);
+    }
+    match var.class.kind {
+        SignalKind::Vector | SignalKind::Complex => {
+            let (_, first, second) = decode_pair(value).ok()?;
+            Some(vec![first.to_f64(), second.to_f64()])
+        }
+        SignalKind::Float => {
+            let format = var.float_format?;
+            let bits = &value[value.len().saturating_sub(format.width())..];
+            Some(vec![SaphoFloat::decode(bits, format).ok()?.to_f64()])
+        }
+        _ => None,
+    }
+}
+
+fn decoded(value: &str, var: &DiffVar) -> String {
+    let text = match (var.class.kind, var.float_format) {
+        (SignalKind::Vector, _) => format_value(value, "vector", None),
+        (SignalKind::Complex, _) => format_value(value, "complex", None),
+        (SignalKind::Float, Some(format)) => {
+            // Float variables can be wider than the format, as wide as an int
+            let start = value.len().saturating_sub(format.width());
+            format_value(&value[start..], "float", Some(format))
+        }
+        _ => return value.to_string(),
+    };
+    text.unwrap_or_else(|_| value.to_string())
+}
+
+/// Whether two decoded values are equal within the tolerances. The relative
+/// tolerance scales with the largest part, so a complex value's imaginary
+/// part is compared against the magnitude of the whole number.
+fn within(xs: &[f64], ys: &[f64], options: &DiffOptions) -> bool {
+    let scale = xs
+        .iter()
+        .chain(ys)
+        .fold(0.0f64, |scale, x| scale.max(x.abs()));
+    let allowed = options.tolerance.max(options.relative_tolerance * scale);
+    xs.len() == ys.len()
+        && xs
+            .iter()
+            .zip(ys)
+            .all(|(&x, &y)| x == y || (x - y).abs() <= allowed)
+}
+
+/// Intervals `[start, end]` where two change lists hold values that are not
+/// `same`, up to `end`.
+fn differing_intervals<'a>(
+    a: &'a SignalChanges,
+    b: &'a SignalChanges,
+    end: u64,
+    same: impl Fn(&str, &str) -> bool,
+) -> Vec<(u64, u64, Option<&'a str>, Option<&'a str>)> {
+    let mut times: Vec<u64> = a.times.iter().chain(&b.times).copied().collect();
+    times.sort_unstable();
+    times.dedup();
+
+    let (mut i, mut j) = (0, 0);
+    let (mut value_a, mut value_b): (Option<&str>, Option<&str>) = (None, None);
+    let mut open: Option<(u64, Option<&str>, Option<&str>)> = None;
+    let mut intervals = Vec::new();
+    for time in times.into_iter().take_while(|&time| time <= end) {
+        while a.times.get(i) == Some(&time) {
+            value_a = Some(&a.values[i]);
+            i += 1;
+        }
+        while b.times.get(j) == Some(&time) {
+            value_b = Some(&b.values[j]);
+            j += 1;
+        }
+        let differs = match (value_a, value_b) {
+            (Some(x), Some(y)) => !same(x, y),
+            (x, y) => x != y,
+        };
+
+        if let Some((start, open_a, open_b)) = open {
+            if differs && (open_a, open_b) == (value_a, value_b) {
+                continue;
+            }
+            intervals.push((start, time, open_a, open_b));
+            open = None;
+        }
+        if differs {
+            open = Some((time, value_a, value_b));
+        }
+    }
+    if let Some((start, open_a, open_b)) = open {
+        intervals.push((start, end, open_a, open_b));
+    }
+    intervals
+}
+
+/// Compare two runs signal by signal. Signals are paired through
+/// `signal_map`, then by hierarchy path, then by what the naming classifier
+/// makes of them; float-encoded values are equal within the tolerances.
+pub fn compare_waveforms(
+    a: &mut WaveformSession,
+    b: &mut WaveformSession,
+    signal_map: &[SignalMapping],
+    options: &DiffOptions,
+) -> Result<WaveformDiff, String> {
+    let timescale = timescale_to_string(a.waveform.hierarchy());
+    let timescale_b = timescale_to_string(b.waveform.hierarchy());
+    if timescale != timescale_b {
+        return Err(format!(
+            "Failed to compare: timescales differ ({} and {})",
+            timescale, timescale_b
+        ));
+    }
+
+    let fallback = options.float_format;
+    let (vars_a, vars_b) = (diff_vars(a, fallback), diff_vars(b, fallback));
+    let Pairing {
+        pairs,
+        only_in_a,
+        only_in_b,
+    } = pair_vars(&vars_a, &vars_b, signal_map)?;
+    let (end_a, end_b) = (a.end_time(), b.end_time());
+    let end = end_a.min(end_b);
+
+    let mut indices_a: Vec<usize> = pairs
+        .iter()
+        .map(|&(i, _, _)| vars_a[i].signal_ref)
+        .collect();
+    let mut indices_b: Vec<usize> = pairs
+        .iter()
+        .map(|&(_, j, _)| vars_b[j].signal_ref)
+        .collect();
+    for indices in [&mut indices_a, &mut indices_b] {
+        indices.sort_unstable();
+        indices.dedup();
+    }
+    a.load_signals(&indices_a)?;
+    b.load_signals(&indices_b)?;
+
+    let mut signals = Vec::new();
+    let mut mismatches = Vec::new();
+    for (i, j, matched_by) in pairs {
+        let (var_a, var_b) = (&vars_a[i], &vars_b[j]);
+        let changes_a = a.signal_changes(var_a.signal_ref)?;
+        let changes_b = b.signal_changes(var_b.signal_ref)?;
+
+        let same = |x: &str, y: &str| {
+            if x == y {
+                return true;
+            }
+            match (float_numbers(x, var_a), float_numbers(y, var_b)) {
+                (Some(xs), Some(ys)) => within(&xs, &ys, options),
+                _ => false,
+            }
+        };
+        let intervals = differing_intervals(&changes_a, &changes_b, end, same);
+
+        signals.push(SignalComparison {
+            path_a: var_a.path.clone(),
+            path_b: var_b.path.clone(),
+            matched_by,
+            mismatches: intervals.len(),
+            first_mismatch: intervals.first().map(|&(start, ..)| start),
+        });
+        mismatches.extend(intervals.into_iter().map(|(start, end, x, y)| Mismatch {
+            path_a: var_a.path.clone(),
+            path_b: var_b.path.clone(),
+            start,
+            end,
+            value_a: x.map(|x| decoded(x, var_a)),
+            value_b: y.map(|y| decoded(y, var_b)),
+        }));
+    }
+
+    mismatches.sort_by(|x, y| x.start.cmp(&y.start).then_with(|| x.path_a.cmp(&y.path_a)));
+    let limit = options.limit.unwrap_or(DEFAULT_LIMIT);
+    let truncated = mismatches.len().saturating_sub(limit);
+    mismatches.truncate(limit);
+
+    Ok(WaveformDiff {
+        timescale,
+        end_a,
+        end_b,
+        signals,
+        only_in_a,
+        only_in_b,
+        mismatches,
+        truncated,
+    })
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Compare two waveform files, listing where paired signals differ. Float
+/// formats come from the processors of the project file when given
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn compare_waveform_files(
+    a: String,
+    b: String,
+    signal_map: Option<Vec<SignalMapping>>,
+    options: Option<DiffOptions>,
+    spf_path: Option<String>,
+) -> Result<WaveformDiff, String> {
+    let project = spf_path.as_deref().map(SpfProject::load).transpose()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let spf = spf_path.as_deref().zip(project.as_ref());
+        let mut session_a = WaveformSession::open(&a)?;
+        let mut session_b = WaveformSession::open(&b)?;
+        if spf.is_some() {
+            session_a.symbols = load_symbol_tables(&session_a, spf);
+            session_b.symbols = load_symbol_tables(&session_b, spf);
+        }
+        compare_waveforms(
+            &mut session_a,
+            &mut session_b,
+            &signal_map.unwrap_or_default(),
+            &options.unwrap_or_default(),
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to compare waveforms: {}", e))?
+}
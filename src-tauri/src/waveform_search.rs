@@ -0,0 +1,339 @@
+#[cfg(feature = "gui")]
+use crate::waveform::WaveformManager;
+use crate::waveform::WaveformSession;
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "gui")]
+use tauri::State;
+use wellen::{SignalRef, SignalValueRef};
+
+/// Condition a search waits for. A match is the change at which the
+/// condition becomes true, so staying high for many cycles is one match.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Condition {
+    Rising,
+    Falling,
+    Equals(String),
+    NotEquals(String),
+}
+
+/// Most conditions the index keeps; the oldest one is dropped first.
+pub const MAX_CACHED_CONDITIONS: usize = 32;
+
+/// Times at which each searched condition becomes true, built once
+/// per signal and condition so later searches are a binary search.
+#[derive(Default)]
+pub struct SearchIndex {
+    matches: HashMap<(SignalRef, Condition), Vec<u64>>,
+    /// Keys of `matches` from the oldest to the newest
+    order: VecDeque<(SignalRef, Condition)>,
+}
+
+impl SearchIndex {
+    fn insert(&mut self, key: (SignalRef, Condition), matches: Vec<u64>) {
+        if self.order.len() >= MAX_CACHED_CONDITIONS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.matches.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.matches.insert(key, matches);
+    }
+
+    /// Number of conditions currently cached.
+    pub fn cached_conditions(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Drop the conditions of signals that were unloaded.
+    pub fn forget(&mut self, signal_refs: &[SignalRef]) {
+        self.order
+            .retain(|(signal_ref, _)| !signal_refs.contains(signal_ref));
+        self.matches
+            .retain(|(signal_ref, _), _| !signal_refs.contains(signal_ref));
+    }
+
+    /// Drop every condition, as when new data is read.
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.matches.clear();
+    }
+}
+
+/// A value typed by the user: `0x1f` (hex), `0b101` or `b101` (bits of
+/// `01xz`, zero extended to the signal width), plain digits (decimal), or
+/// anything else compared literally.
+enum ValuePattern {
+    Number(u128),
+    Bits(String),
+    Text(String),
+}
+
+impl ValuePattern {
+    fn parse(value: &str) -> Self {
+        let value = value.trim();
+        let lower = value.to_ascii_lowercase();
+        if let Some(hex) = lower.strip_prefix("0x") {
+            if let Ok(number) = u128::from_str_radix(hex, 16) {
+                return Self::Number(number);
+            }
+        }
+        let bits = lower.strip_prefix("0b").or_else(|| lower.strip_prefix('b'));
+        if let Some(bits) = bits.filter(|bits| {
+            !bits.is_empty() && bits.bytes().all(|b| matches!(b, b'0' | b'1' | b'x' | b'z'))
+        }) {
+            return Self::Bits(bits.to_string());
+        }
+        match value.parse::<u128>() {
+            Ok(number) => Self::Number(number),
+            Err(_) => Self::Text(value.to_string()),
+        }
+    }
+
+    fn matches(&self, value: &SignalValueRef) -> bool {
+        match (self, value) {
+            (_, SignalValueRef::Real(real)) => match self {
+                Self::Number(number) => *number as f64 == *real,
+                Self::Text(text) => text.parse::<f64>() == Ok(*real),
+                Self::Bits(_) => false,
+            },
+            (Self::Text(text), SignalValueRef::String(string)) => text == string,
+            (_, SignalValueRef::String(_)) => false,
+            (_, SignalValueRef::Event) => matches!(self, Self::Number(1)),
+            (_, SignalValueRef::BitVec(_)) => {
+                let bits = value.to_bit_string().unwrap_or_default();
+                match self {
+                    Self::Number(number) => bits
+                        .trim_start_matches('0')
+                        .chars()
+                        .try_fold(0u128, |acc, bit| match bit {
+                            '0' => acc.checked_mul(2),
+                            '1' => acc.checked_mul(2)?.checked_add(1),
+                            _ => None,
+                        })
+                        .is_some_and(|bits| bits == *number),
+                    Self::Bits(pattern) | Self::Text(pattern) => {
+                        let pattern = pattern.trim_start_matches('0');
+                        pattern.len() <= bits.len()
+                            && bits[..bits.len() - pattern.len()]
+                                .bytes()
+                                .all(|b| b == b'0')
+                            && bits[bits.len() - pattern.len()..].eq_ignore_ascii_case(pattern)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Level of the least significant bit, None when it is unknown.
+fn lsb_level(value: &SignalValueRef) -> Option<bool> {
+    match value {
+        SignalValueRef::BitVec(_) => match value.to_bit_string()?.chars().last()? {
+            '0' | 'l' | 'L' => Some(false),
+            '1' | 'h' | 'H' => Some(true),
+            _ => None,
+        },
+        SignalValueRef::Event => Some(true),
+        _ => None,
+    }
+}
+
+fn build_matches(
+    session: &WaveformSession,
+    signal_ref: SignalRef,
+    condition: &Condition,
+) -> Result<Vec<u64>, String> {
+    let holds: Box<dyn Fn(&SignalValueRef) -> bool> = match condition {
+        Condition::Rising => Box::new(|value| lsb_level(value) == Some(true)),
+        Condition::Falling => Box::new(|value| lsb_level(value) == Some(false)),
+        Condition::Equals(value) => {
+            let pattern = ValuePattern::parse(value);
+            Box::new(move |value| pattern.matches(value))
+        }
+        Condition::NotEquals(value) => {
+            let pattern = ValuePattern::parse(value);
+            Box::new(move |value| !pattern.matches(value))
+        }
+    };
+    // An edge needs a known level before it, a value match only needs to
+    // differ from the previous value
+    let is_edge = matches!(condition, Condition::Rising | Condition::Falling);
+
+    let mut matches = Vec::new();
+    let mut previous: Option<bool> = None;
+    for (time, value) in session.changes_since(signal_ref, 0)? {
+        let now = holds(&value);
+        let entered = match previous {
+            Some(before) => now && !before && (!is_edge || lsb_level(&value).is_some()),
+            None => now && !is_edge,
+        };
+        if entered {
+            matches.push(time);
+        }
+        previous = if is_edge {
+            lsb_level(&value).map(|_| now)
+        } else {
+            Some(now)
+        };
+    }
+    Ok(matches)
+}
+
+/// First match strictly after `time` (or last one strictly before it).
+fn find_match(
+    session: &mut WaveformSession,
+    index: usize,
+    condition: Condition,
+    time: u64,
+    forward: bool,
+) -> Result<Option<u64>, String> {
+    let signal_ref = session.ensure_loaded(index)?;
+    let key = (signal_ref, condition);
+    if !session.search.matches.contains_key(&key) {
+        let matches = build_matches(session, signal_ref, &key.1)?;
+        session.search.insert(key.clone(), matches);
+    }
+
+    let matches = &session.search.matches[&key];
+    let found = if forward {
+        matches.get(matches.partition_point(|&t| t <= time))
+    } else {
+        let pos = matches.partition_point(|&t| t < time);
+        pos.checked_sub(1).and_then(|pos| matches.get(pos))
+    };
+    Ok(found.copied())
+}
+
+/// Time of the next change of a signal strictly after `time`, or of the
+/// previous one strictly before it. Both start from a binary search over
+/// the time table and the signal's own change list.
+pub fn find_transition(
+    session: &mut WaveformSession,
+    index: usize,
+    time: u64,
+    forward: bool,
+) -> Result<Option<u64>, String> {
+    let signal_ref = session.ensure_loaded(index)?;
+    if forward {
+        Ok(session
+            .changes_since(signal_ref, time)?
+            .map(|(t, _)| t)
+            .find(|&t| t > time))
+    } else if time == 0 {
+        Ok(None)
+    } else {
+        Ok(session
+            .changes_since(signal_ref, time - 1)?
+            .next()
+            .map(|(t, _)| t)
+            .filter(|&t| t < time))
+    }
+}
+
+/// Next (or previous) time the least significant bit rises from 0 to 1, or
+/// falls from 1 to 0. Changes from or to `x`/`z` are not edges.
+pub fn find_edge(
+    session: &mut WaveformSession,
+    index: usize,
+    time: u64,
+    rising: bool,
+    forward: bool,
+) -> Result<Option<u64>, String> {
+    let condition = if rising {
+        Condition::Rising
+    } else {
+        Condition::Falling
+    };
+    find_match(session, index, condition, time, forward)
+}
+
+/// Next (or previous) time a signal starts being equal, or starts being
+/// different, from `value`.
+pub fn find_value(
+    session: &mut WaveformSession,
+    index: usize,
+    time: u64,
+    value: &str,
+    equal: bool,
+    forward: bool,
+) -> Result<Option<u64>, String> {
+    let condition = if equal {
+        Condition::Equals(value.to_string())
+    } else {
+        Condition::NotEquals(value.to_string())
+    };
+    find_match(session, index, condition, time, forward)
+}
+
+// ===== TAURI COMMANDS =====
+
+/// Time of the next (or previous) change of a signal from `time`
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn find_signal_transition(
+    session_id: u32,
+    signal_ref: usize,
+    time: u64,
+    forward: bool,
+    state: State<'_, WaveformManager>,
+) -> Result<Option<u64>, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        find_transition(&mut session.lock().unwrap(), signal_ref, time, forward)
+    })
+    .await
+    .map_err(|e| format!("Failed to search signal: {}", e))?
+}
+
+/// Time of the next (or previous) rising or falling edge of a signal from `time`
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn find_signal_edge(
+    session_id: u32,
+    signal_ref: usize,
+    time: u64,
+    rising: bool,
+    forward: bool,
+    state: State<'_, WaveformManager>,
+) -> Result<Option<u64>, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        find_edge(
+            &mut session.lock().unwrap(),
+            signal_ref,
+            time,
+            rising,
+            forward,
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to search signal: {}", e))?
+}
+
+/// Time at which a signal next (or previously) becomes equal, or different,
+/// to `value`
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub async fn find_signal_value(
+    session_id: u32,
+    signal_ref: usize,
+    time: u64,
+    value: String,
+    equal: bool,
+    forward: bool,
+    state: State<'_, WaveformManager>,
+) -> Result<Option<u64>, String> {
+    let session = state.get(session_id)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        find_value(
+            &mut session.lock().unwrap(),
+            signal_ref,
+            time,
+            &value,
+            equal,
+            forward,
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to search signal: {}", e))?
+}
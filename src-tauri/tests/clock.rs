@@ -0,0 +1,73 @@
+//! Clock detection on the example dumps and conversions between time and
+//! clock cycles.
+
+mod common;
+
+use common::open_example;
+use polaris_lib::clock::{detect_clocks, ClockInfo};
+
+fn clocks(name: &str) -> Vec<ClockInfo> {
+    let mut session = open_example(name);
+    detect_clocks(&mut session).unwrap()
+}
+
+#[test]
+fn finds_the_processor_clock() {
+    let clocks = clocks("ProcDTW_tb.vcd");
+    assert_eq!(clocks.len(), 1);
+    let clk = &clocks[0];
+    assert_eq!(clk.path, "ProcDTW_tb.clk");
+    assert_eq!(
+        (clk.period, clk.first_edge, clk.high_time),
+        (10000, 5000, 5000)
+    );
+}
+
+#[test]
+fn converts_between_time_and_cycles() {
+    let clk = &clocks("ProcDTW_tb.vcd")[0];
+    assert_eq!(clk.cycle_at(0), -1);
+    assert_eq!(clk.cycle_at(5000), 0);
+    assert_eq!(clk.cycle_at(14999), 0);
+    assert_eq!(clk.cycle_at(15000), 1);
+    assert_eq!(clk.time_of_cycle(500), Some(5_005_000));
+    assert_eq!(clk.time_of_cycle(-1), None);
+    assert_eq!(
+        clk.time_of_cycle(clk.cycle_at(clk.last_edge)),
+        Some(clk.last_edge)
+    );
+}
+
+#[test]
+fn irregular_bits_are_not_clocks() {
+    // basic.vcd bits go through every VHDL level once, GHW's sig_bit toggles
+    // every nanosecond
+    assert!(clocks("basic.vcd").is_empty());
+    let ghw = clocks("basic.ghw");
+    assert_eq!(ghw.len(), 1);
+    assert_eq!((ghw[0].period, ghw[0].first_edge), (2000000, 1000000));
+}
+
+#[test]
+fn detection_is_cached_and_leaves_signals_unloaded() {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    let clocks = detect_clocks(&mut session).unwrap();
+    assert_eq!(session.clocks.as_ref(), Some(&clocks));
+
+    let hierarchy = session.waveform.hierarchy();
+    let loaded: Vec<String> = hierarchy
+        .all_vars()
+        .map(|var_ref| &hierarchy[var_ref])
+        .filter(|var| session.waveform.get_signal(var.signal_ref()).is_some())
+        .map(|var| var.full_name(hierarchy))
+        .collect();
+    assert!(loaded.is_empty(), "{:?}", loaded);
+
+    // Signals loaded before the scan stay loaded
+    let clk = clocks[0].signal_ref;
+    session.clocks = None;
+    session.load_signals(&[clk]).unwrap();
+    assert_eq!(detect_clocks(&mut session).unwrap(), clocks);
+    let signal_ref = session.signal_refs(&[clk]).unwrap()[0];
+    assert!(session.waveform.get_signal(signal_ref).is_some());
+}
@@ -0,0 +1,292 @@
+//! Helpers shared by the integration tests: a parser for the GTKWave `.dump`
+//! reference files in `examples/` and checks that compare a waveform session
+//! against one of them.
+
+#![allow(dead_code)]
+
+use polaris_lib::waveform::{
+    ScopeNode, SignalChanges, VarNode, WaveformHierarchy, WaveformSession,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use wellen::VarRef;
+
+pub fn example(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../examples")
+        .join(name)
+}
+
+/// Open a waveform file, panicking if it cannot be read.
+pub fn open_file(path: &Path) -> WaveformSession {
+    WaveformSession::open(path.to_str().unwrap()).expect("Failed to open waveform")
+}
+
+/// Open one of the files in `examples/`.
+pub fn open_example(name: &str) -> WaveformSession {
+    open_file(&example(name))
+}
+
+pub fn scratch_file(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+pub struct Fac {
+    pub name: String,
+    pub msi: i64,
+    pub lsi: i64,
+    /// `(time, value)` pairs as printed, including GTKWave's negative
+    /// initial entries and the sentinels at the end of time
+    pub transitions: Vec<(i64, String)>,
+}
+
+pub struct Dump {
+    pub scale: u32,
+    pub dimension: String,
+    pub range: (i64, i64),
+    pub facs: Vec<Fac>,
+    pub aliases: Vec<Vec<String>>,
+}
+
+pub fn read_dump(name: &str) -> Dump {
+    let text = std::fs::read_to_string(example(name)).expect("Failed to read dump file");
+    let mut dump = Dump {
+        scale: 1,
+        dimension: String::new(),
+        range: (0, 0),
+        facs: Vec::new(),
+        aliases: Vec::new(),
+    };
+
+    let mut section = "";
+    for line in text.lines() {
+        if matches!(line, "Time" | "Tree" | "Facs" | "Aliases") {
+            section = line;
+            continue;
+        }
+        if line.starts_with("----") || line.trim().is_empty() {
+            continue;
+        }
+
+        match section {
+            "Time" => {
+                if let Some(scale) = line.strip_prefix("scale: ") {
+                    dump.scale = scale.parse().unwrap();
+                } else if let Some(dimension) = line.strip_prefix("dimension: ") {
+                    dump.dimension = dimension.to_string();
+                } else if let Some(range) = line.strip_prefix("range: ") {
+                    let (start, end) = range.split_once(" - ").unwrap();
+                    dump.range = (start.parse().unwrap(), end.parse().unwrap());
+                }
+            }
+            "Facs" => {
+                if !line.starts_with(' ') {
+                    dump.facs.push(Fac {
+                        name: line.to_string(),
+                        msi: 0,
+                        lsi: 0,
+                        transitions: Vec::new(),
+                    });
+                    continue;
+                }
+                let fac = dump.facs.last_mut().unwrap();
+                let line = line.trim();
+                if let Some(range) = line.strip_prefix("msi, lsi: ") {
+                    let (msi, lsi) = range.split_once(", ").unwrap();
+                    fac.msi = msi.parse().unwrap();
+                    fac.lsi = lsi.parse().unwrap();
+                } else if let Some((value, time)) = line.rsplit_once(" @ ") {
+                    fac.transitions
+                        .push((time.parse().unwrap(), value.trim_matches('"').to_string()));
+                }
+            }
+            "Aliases" => dump
+                .aliases
+                .push(line.split(", ").map(str::to_string).collect()),
+            _ => {}
+        }
+    }
+    dump
+}
+
+/// Timescale as printed by `timescale_to_string`, e.g. `1ns`.
+pub fn dump_timescale(dump: &Dump) -> String {
+    let unit = match dump.dimension.as_str() {
+        "GW_TIME_DIMENSION_BASE" => "s",
+        "GW_TIME_DIMENSION_MILLI" => "ms",
+        "GW_TIME_DIMENSION_MICRO" => "us",
+        "GW_TIME_DIMENSION_NANO" => "ns",
+        "GW_TIME_DIMENSION_PICO" => "ps",
+        "GW_TIME_DIMENSION_FEMTO" => "fs",
+        other => panic!("Unknown dimension {}", other),
+    };
+    format!("{}{}", dump.scale, unit)
+}
+
+fn collect_vars<'a>(scopes: &'a [ScopeNode], vars: &mut Vec<&'a VarNode>) {
+    for scope in scopes {
+        vars.extend(&scope.vars);
+        collect_vars(&scope.scopes, vars);
+    }
+}
+
+/// Every var of a hierarchy, root vars first and then depth first.
+pub fn all_vars(hierarchy: &WaveformHierarchy) -> Vec<&VarNode> {
+    let mut vars: Vec<&VarNode> = hierarchy.vars.iter().collect();
+    collect_vars(&hierarchy.scopes, &mut vars);
+    vars
+}
+
+/// Var path a dump fac refers to. GTKWave prefixes GHW paths with `top.` and
+/// appends `[msb:lsb]` or, for vectors split per bit, `[n]` to the name.
+fn fac_path<'a>(paths: &HashSet<&str>, name: &'a str) -> &'a str {
+    let name = match name.strip_prefix("top.") {
+        Some(stripped) if !paths.contains(name) => stripped,
+        _ => name,
+    };
+    match name.rsplit_once('[') {
+        Some((base, _)) if !paths.contains(name) => base,
+        _ => name,
+    }
+}
+
+/// Check that the facs and aliases of a dump match the vars of a session.
+pub fn compare_hierarchy_with_dump(hierarchy: &WaveformHierarchy, dump: &Dump) -> Vec<String> {
+    let mut errors = Vec::new();
+    if hierarchy.timescale != dump_timescale(dump) {
+        errors.push(format!(
+            "timescale {} does not match {}",
+            hierarchy.timescale,
+            dump_timescale(dump)
+        ));
+    }
+
+    let vars = all_vars(hierarchy);
+    let by_path: HashMap<&str, &VarNode> =
+        vars.iter().map(|var| (var.path.as_str(), *var)).collect();
+    let paths: HashSet<&str> = by_path.keys().copied().collect();
+
+    let covered: HashSet<&str> = dump
+        .facs
+        .iter()
+        .map(|fac| fac_path(&paths, &fac.name))
+        .collect();
+    for path in &covered {
+        if !paths.contains(path) {
+            errors.push(format!("{}: in the dump but not in the hierarchy", path));
+        }
+    }
+    for path in &paths {
+        if !covered.contains(path) {
+            errors.push(format!("{}: in the hierarchy but not in the dump", path));
+        }
+    }
+
+    for group in &dump.aliases {
+        let signal_refs: HashSet<Option<usize>> = group
+            .iter()
+            .map(|name| {
+                by_path
+                    .get(fac_path(&paths, name))
+                    .map(|var| var.signal_ref)
+            })
+            .collect();
+        if signal_refs.len() != 1 || signal_refs.contains(&None) {
+            errors.push(format!("{}: not aliases of one signal", group.join(", ")));
+        }
+    }
+    errors
+}
+
+/// Value in effect at `time`, None before the first change.
+pub fn value_at(changes: &SignalChanges, time: u64) -> Option<&str> {
+    let pos = changes
+        .times
+        .partition_point(|&t| t <= time)
+        .checked_sub(1)?;
+    Some(&changes.values[pos])
+}
+
+/// Position in the var's bit string of the single bit named by a `name[n]`
+/// fac, None when the fac covers the whole var.
+fn bit_position(session: &WaveformSession, var: &VarNode, fac_name: &str) -> Option<usize> {
+    let hierarchy = session.waveform.hierarchy();
+    let index = hierarchy[VarRef::from_index(var.var_ref)?].index()?;
+    let (_, bit) = fac_name.strip_suffix(']')?.rsplit_once('[')?;
+    let bit: i64 = bit.parse().ok()?;
+    Some(if index.msb() >= index.lsb() {
+        (index.msb() - bit) as usize
+    } else {
+        (bit - index.msb()) as usize
+    })
+}
+
+fn same_value(actual: &str, expected: &str) -> bool {
+    actual.eq_ignore_ascii_case(expected)
+        || expected.contains('.')
+            && matches!(
+                (actual.parse::<f64>(), expected.parse::<f64>()),
+                (Ok(a), Ok(b)) if (a - b).abs() < 1e-6
+            )
+}
+
+/// Compare every fac of a dump with the changes the session reports for the
+/// matching var, sampling at every time either side changes inside the
+/// dump's range. Returns one message per mismatch.
+pub fn compare_values_with_dump(session: &mut WaveformSession, dump: &Dump) -> Vec<String> {
+    let mut errors = Vec::new();
+    let hierarchy = session.hierarchy(0);
+    let vars = all_vars(&hierarchy);
+    let by_path: HashMap<&str, &VarNode> =
+        vars.iter().map(|var| (var.path.as_str(), *var)).collect();
+    let paths: HashSet<&str> = by_path.keys().copied().collect();
+    let (_, range_end) = dump.range;
+
+    for fac in &dump.facs {
+        let Some(var) = by_path.get(fac_path(&paths, &fac.name)) else {
+            errors.push(format!("{}: no matching var", fac.name));
+            continue;
+        };
+        let bit = bit_position(session, var, &fac.name);
+        let changes = session
+            .signal_changes(var.signal_ref)
+            .expect("Failed to load signal changes");
+
+        let mut times: Vec<i64> = changes
+            .times
+            .iter()
+            .map(|&t| t as i64)
+            .filter(|&t| t < range_end)
+            .chain(
+                fac.transitions
+                    .iter()
+                    .map(|(t, _)| *t)
+                    .filter(|&t| t >= 0 && t <= range_end),
+            )
+            .collect();
+        times.sort_unstable();
+        times.dedup();
+
+        for time in times {
+            let (dump_time, expected) = fac
+                .transitions
+                .iter()
+                .rev()
+                .find(|(t, _)| *t <= time)
+                .unwrap();
+            let actual = value_at(&changes, time as u64).map(|value| match bit {
+                Some(pos) => &value[pos..pos + 1],
+                None => value,
+            });
+            match actual {
+                None if *dump_time < 0 => {}
+                Some(actual) if same_value(actual, expected) => {}
+                actual => errors.push(format!(
+                    "{} @ {}: expected {}, got {:?}",
+                    fac.name, time, expected, actual
+                )),
+            }
+        }
+    }
+    errors
+}
@@ -0,0 +1,232 @@
+//! Outputs of the DTW processor testbench against expected sequences kept in
+//! a project, as `out(2, 10); out(2, -122);` writes them.
+
+mod common;
+
+use common::{open_example, open_file, scratch_file};
+use polaris_lib::golden::{check_expected_files, check_outputs, GoldenCheck};
+use polaris_lib::spf::SpfProject;
+
+/// Write a project whose processor, with the extra `fields`, expects
+/// `files` given as `(port, format, contents)`.
+fn write_project(name: &str, fields: &str, files: &[(u32, &str, &str)]) -> (String, SpfProject) {
+    let dir = scratch_file(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("ProcDTW/Simulation")).unwrap();
+    let mut outputs = Vec::new();
+    for (port, format, text) in files {
+        let file = format!("ProcDTW/Simulation/out_{}.txt", port);
+        std::fs::write(dir.join(&file), text).unwrap();
+        outputs.push(format!(
+            r#"{{ "port": {}, "file": "{}", "format": "{}" }}"#,
+            port, file, format
+        ));
+    }
+    let spf = format!(
+        r#"{{ "processors": [{{ "name": "ProcDTW", {} "expectedOutputs": [{}] }}] }}"#,
+        fields,
+        outputs.join(", ")
+    );
+    let spf_path = dir.join("Test.spf");
+    std::fs::write(&spf_path, &spf).unwrap();
+    (
+        spf_path.to_str().unwrap().to_string(),
+        SpfProject::parse(&spf).unwrap(),
+    )
+}
+
+/// Check the testbench against a project whose processor expects integer
+/// `files`, given as `(port, contents)`.
+fn check_with(name: &str, files: &[(u32, &str)]) -> Result<GoldenCheck, String> {
+    let files: Vec<_> = files
+        .iter()
+        .map(|&(port, text)| (port, "int", text))
+        .collect();
+    let (spf_path, project) = write_project(name, "", &files);
+    let mut session = open_example("ProcDTW_tb.vcd");
+    check_outputs(&mut session, &spf_path, &project)
+}
+
+#[test]
+fn matching_outputs_pass() {
+    let check = check_with("golden_pass", &[(2, "10\n-122\n")]).unwrap();
+    assert!(check.passed);
+    assert_eq!(check.processors, ["ProcDTW"]);
+    assert_eq!(check.ports[0].processor, "ProcDTW");
+    let port = &check.ports[0];
+    assert_eq!(
+        port.output_path.as_deref(),
+        Some("ProcDTW_tb.proc.out_sig_2")
+    );
+    assert_eq!((port.expected_count, port.actual_count), (2, 2));
+    assert_eq!(port.first_mismatch, None);
+}
+
+#[test]
+fn first_mismatch_points_at_the_source_line() {
+    let check = check_with("golden_value", &[(2, "10\n-121\n")]).unwrap();
+    assert!(!check.passed);
+    let mismatch = check.ports[0].first_mismatch.as_ref().unwrap();
+    assert_eq!(mismatch.index, 1);
+    assert_eq!(mismatch.expected.as_deref(), Some("-121"));
+    assert_eq!(mismatch.actual.as_deref(), Some("-122"));
+    assert_eq!(mismatch.time, Some(7655000));
+    let clock = check.clock.as_ref().unwrap();
+    assert_eq!(mismatch.cycle, Some(clock.cycle_at(7655000)));
+    assert_eq!(mismatch.source_line, Some(31));
+    assert_eq!(
+        mismatch.source_text.as_deref().map(str::trim),
+        Some("out(2,-122);")
+    );
+}
+
+#[test]
+fn missing_and_extra_outputs_are_mismatches() {
+    let check = check_with("golden_missing", &[(2, "10\n-122\n5\n")]).unwrap();
+    let mismatch = check.ports[0].first_mismatch.as_ref().unwrap();
+    assert_eq!(mismatch.index, 2);
+    assert_eq!(mismatch.expected.as_deref(), Some("5"));
+    assert_eq!((mismatch.actual.as_deref(), mismatch.time), (None, None));
+
+    let check = check_with("golden_extra", &[(2, "10\n")]).unwrap();
+    let mismatch = check.ports[0].first_mismatch.as_ref().unwrap();
+    assert_eq!(mismatch.index, 1);
+    assert_eq!(mismatch.expected, None);
+    assert_eq!(mismatch.actual.as_deref(), Some("-122"));
+}
+
+#[test]
+fn unreadable_expectations_fail_the_check() {
+    let check = check_with("golden_errors", &[(2, "10\nten\n-122\n"), (7, "1\n")]).unwrap();
+    assert!(!check.passed);
+    let errors = &check.ports[0].errors;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, 2);
+    // The valid lines are still compared
+    assert_eq!(check.ports[0].first_mismatch, None);
+
+    let missing = &check.ports[1];
+    assert_eq!(missing.output_path, None);
+    assert!(missing.errors[0].message.contains("out_sig_7"));
+}
+
+#[test]
+fn project_without_expectations_is_an_error() {
+    let error = check_with("golden_none", &[]).unwrap_err();
+    assert!(error.contains("no expected outputs"), "{}", error);
+}
+
+#[test]
+fn lines_are_read_in_the_format_of_their_port() {
+    // An integer port does not take fractions, even where the text is a number
+    let check = check_with("golden_int", &[(2, "10\n-122.5\n-122\n")]).unwrap();
+    assert!(!check.passed);
+    let errors = &check.ports[0].errors;
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, 2);
+    assert!(
+        errors[0].message.contains("an integer"),
+        "{}",
+        errors[0].message
+    );
+
+    // Whole numbers of a float port are compared as floats: 10 has a zero
+    // exponent and reads the same, the two's complement -122 does not
+    let (spf_path, project) = write_project(
+        "golden_float",
+        r#""mantissaBits": 16, "exponentBits": 6,"#,
+        &[(2, "float", "10\n-122\n")],
+    );
+    assert_eq!(check_expected_files(&spf_path, &project), []);
+    let mut session = open_example("ProcDTW_tb.vcd");
+    let check = check_outputs(&mut session, &spf_path, &project).unwrap();
+    let mismatch = check.ports[0].first_mismatch.as_ref().unwrap();
+    assert_eq!(mismatch.index, 1);
+    assert_eq!(mismatch.expected.as_deref(), Some("-122"));
+    assert_ne!(mismatch.actual.as_deref(), Some("-122"));
+}
+
+#[test]
+fn float_ports_need_the_float_format() {
+    let (spf_path, project) = write_project("golden_no_format", "", &[(2, "float", "1.5\nten\n")]);
+    let errors = check_expected_files(&spf_path, &project);
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert_eq!(errors[0].line, 2);
+    assert!(
+        errors[1].message.contains("mantissa"),
+        "{}",
+        errors[1].message
+    );
+}
+
+#[test]
+fn every_processor_is_checked_against_its_own_ports() {
+    let dir = scratch_file("golden_two_processors");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let vcd = "$timescale 1ns $end
+$scope module tb $end
+$scope module p_A $end
+$var wire 8 a out_sig_0 $end
+$var wire 1 b out_en_sim_0 $end
+$upscope $end
+$scope module p_B $end
+$var wire 8 c out_sig_0 $end
+$var wire 1 d out_en_sim_0 $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+b0 a
+0b
+b0 c
+0d
+#10
+b11 a
+1b
+b111 c
+1d
+#20
+0b
+0d
+#30
+b100 a
+1b
+#40
+0b
+";
+    let path = dir.join("tb.vcd");
+    std::fs::write(&path, vcd).unwrap();
+    std::fs::write(dir.join("a.txt"), "3\n4\n").unwrap();
+    std::fs::write(dir.join("b.txt"), "7\n").unwrap();
+    let spf = r#"{ "processors": [
+        { "name": "A", "expectedOutputs": [{ "port": 0, "file": "a.txt" }] },
+        { "name": "B", "expectedOutputs": [{ "port": 0, "file": "b.txt" }] }
+    ] }"#;
+    let spf_path = dir.join("Test.spf");
+    std::fs::write(&spf_path, spf).unwrap();
+    let project = SpfProject::parse(spf).unwrap();
+
+    let mut session = open_file(&path);
+    let check = check_outputs(&mut session, spf_path.to_str().unwrap(), &project).unwrap();
+    assert!(check.passed, "{:?}", check.ports);
+    assert_eq!(check.processors, ["A", "B"]);
+    let ports: Vec<_> = check
+        .ports
+        .iter()
+        .map(|port| {
+            (
+                port.processor.as_str(),
+                port.output_path.as_deref(),
+                port.actual_count,
+            )
+        })
+        .collect();
+    assert_eq!(
+        ports,
+        [
+            ("A", Some("tb.p_A.out_sig_0"), 2),
+            ("B", Some("tb.p_B.out_sig_0"), 1)
+        ]
+    );
+}
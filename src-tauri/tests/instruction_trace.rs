@@ -0,0 +1,144 @@
+//! Instruction traces of the DTW processor testbench, rebuilt from `valr2`
+//! and the opcode table next to it.
+
+mod common;
+
+use common::{open_example, open_file, scratch_file};
+use polaris_lib::instruction_trace::{
+    instruction_trace, row_matches, trace_to_csv, vars_of_kind, InstructionTrace,
+};
+use polaris_lib::sapho_names::SignalKind;
+use polaris_lib::translation::signed_value;
+use polaris_lib::waveform::WaveformSession;
+
+fn trace() -> InstructionTrace {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    instruction_trace(&mut session, None).unwrap()
+}
+
+#[test]
+fn one_row_per_instruction_fetch() {
+    let trace = trace();
+    assert_eq!(trace.instruction_path, "ProcDTW_tb.proc.valr2");
+    assert_eq!(trace.rows.len(), 765);
+    assert!(trace
+        .stack_pointer_path
+        .as_deref()
+        .is_some_and(|path| path.ends_with("core.sp.pointeri")));
+
+    let first = &trace.rows[0];
+    assert_eq!((first.time, first.pc, first.cycle), (0, Some(0), Some(-1)));
+    assert_eq!(first.mnemonic.as_deref(), Some("NOP"));
+
+    let row = trace.rows.iter().find(|row| row.time == 35000).unwrap();
+    assert_eq!((row.pc, row.cycle, row.cycles), (Some(1), Some(3), Some(1)));
+    assert_eq!(row.mnemonic.as_deref(), Some("LOD"));
+    assert_eq!(row.operand.as_deref(), Some("75"));
+}
+
+#[test]
+fn rows_carry_the_data_stack_pointer() {
+    let trace = trace();
+    let push = trace
+        .rows
+        .iter()
+        .find(|row| row.mnemonic.as_deref() == Some("P_LOD"))
+        .unwrap();
+    assert_eq!(push.stack_pointer, Some(1));
+    assert_eq!(trace.rows[push.step + 1].stack_pointer, Some(0));
+}
+
+#[test]
+fn search_matches_whole_words() {
+    let trace = trace();
+    let outputs: Vec<_> = trace
+        .rows
+        .iter()
+        .filter(|row| row_matches(row, "out 2"))
+        .collect();
+    assert!(!outputs.is_empty());
+    assert!(outputs
+        .iter()
+        .all(|row| row.mnemonic.as_deref() == Some("OUT")));
+
+    let loads = trace.rows.iter().filter(|row| row_matches(row, "LOD"));
+    assert!(loads
+        .clone()
+        .all(|row| row.mnemonic.as_deref() == Some("LOD")));
+    assert!(loads.count() > 0);
+    assert!(trace.rows.iter().any(|row| row_matches(row, "1 LOD 75")));
+}
+
+#[test]
+fn csv_has_a_header_and_one_line_per_row() {
+    let trace = trace();
+    let csv = trace_to_csv(&trace.rows);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("step,cycle,time,pc,mnemonic,operand,cycles,stack_pointer")
+    );
+    assert_eq!(lines.clone().count(), trace.rows.len());
+    assert_eq!(lines.nth(1), Some("1,3,35000,1,LOD,\"75\",1,0"));
+}
+
+#[test]
+fn reads_values_as_twos_complement() {
+    assert_eq!(signed_value("0101"), Some(5));
+    assert_eq!(signed_value("1111"), Some(-1));
+    assert_eq!(signed_value("11111111111111111111"), Some(-1));
+    assert_eq!(signed_value("01x1"), None);
+    assert_eq!(signed_value(""), None);
+}
+
+#[test]
+fn siblings_come_from_the_same_processor() {
+    // Only p_A has a data stack pointer; p_B must not borrow it
+    let path = scratch_file("two_processors_trace.vcd");
+    let vcd = "$timescale 1ns $end
+$scope module tb $end
+$scope module p_A $end
+$var wire 8 a valr2 $end
+$scope module core $end
+$scope module sp $end
+$var wire 4 b pointeri $end
+$upscope $end
+$upscope $end
+$upscope $end
+$scope module p_B $end
+$var wire 8 c valr2 $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+b0 a
+b1 b
+b0 c
+#10
+b1 a
+b10 b
+b1 c
+";
+    std::fs::write(&path, vcd).unwrap();
+    let mut session = open_file(&path);
+    let valr2 = |session: &WaveformSession, name: &str| {
+        vars_of_kind(session, SignalKind::Instruction)
+            .into_iter()
+            .find(|(path, _)| path.contains(name))
+            .map(|(_, index)| index)
+    };
+
+    let a = valr2(&session, "p_A");
+    let trace = instruction_trace(&mut session, a).unwrap();
+    assert_eq!(
+        trace.stack_pointer_path.as_deref(),
+        Some("tb.p_A.core.sp.pointeri")
+    );
+    assert_eq!(trace.rows[1].stack_pointer, Some(2));
+
+    let b = valr2(&session, "p_B");
+    let trace = instruction_trace(&mut session, b).unwrap();
+    assert_eq!(trace.instruction_path, "tb.p_B.valr2");
+    assert_eq!(trace.stack_pointer_path, None);
+    assert!(trace.rows.iter().all(|row| row.stack_pointer.is_none()));
+}
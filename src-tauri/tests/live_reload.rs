@@ -0,0 +1,250 @@
+//! Follow example VCDs as if a simulator were still writing them: open the
+//! first part of the file, append the rest in pieces and check that the
+//! session ends up with the same data as one that read the whole file.
+
+mod common;
+
+use common::{all_vars, example, open_example, open_file, scratch_file};
+use polaris_lib::clock::detect_clocks;
+use polaris_lib::live_reload::read_appended;
+use polaris_lib::trigger::{find_triggers, TriggerMatch};
+use polaris_lib::waveform::WaveformSession;
+use polaris_lib::waveform_search::{find_edge, find_transition};
+use std::io::Write;
+use std::path::Path;
+
+fn append(path: &Path, bytes: &[u8]) {
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .unwrap()
+        .write_all(bytes)
+        .unwrap();
+}
+
+/// Byte offset just after the `fraction` of the body, at a line boundary.
+fn split_point(text: &[u8], fraction: f64) -> usize {
+    let body = text
+        .windows(15)
+        .position(|w| w == b"$enddefinitions")
+        .unwrap();
+    let target = body + ((text.len() - body) as f64 * fraction) as usize;
+    target + text[target..].iter().position(|&b| b == b'\n').unwrap() + 1
+}
+
+/// Everything the viewer can ask for a signal, as comparable strings.
+fn snapshot(session: &mut WaveformSession) -> Vec<String> {
+    let hierarchy = session.hierarchy(0);
+    let end = hierarchy.time_range.end;
+    let mut out = vec![format!("end {}", end)];
+    for var in all_vars(&hierarchy) {
+        let changes = session.signal_changes(var.signal_ref).unwrap();
+        let encoded = session.encoded_signal_changes(var.signal_ref).unwrap();
+        let window = session
+            .signal_window(var.signal_ref, end / 3, end, 50)
+            .unwrap();
+        out.push(format!(
+            "{} {:?} {:?} {:?} {}",
+            var.path,
+            changes.times,
+            changes.values,
+            encoded,
+            serde_json::to_string(&window).unwrap()
+        ));
+    }
+    out
+}
+
+fn check_follow(name: &str, fractions: &[f64]) {
+    let src = example(name);
+    let text = std::fs::read(&src).unwrap();
+    let dst = scratch_file(&format!("{}.live.vcd", name));
+
+    let mut written = split_point(&text, fractions[0]);
+    std::fs::write(&dst, &text[..written]).unwrap();
+    let mut session = open_file(&dst);
+    let opened_end = session.end_time();
+
+    for &fraction in &fractions[1..] {
+        let next = split_point(&text, fraction);
+        // Half a line first: nothing complete to read yet
+        let half = written + (next - written) / 2;
+        let half = written
+            + text[written..half]
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1);
+        append(&dst, &text[written..half]);
+        append(&dst, &text[half..next - 1]);
+        read_appended(&mut session).unwrap();
+        append(&dst, &text[next - 1..next]);
+        let end = read_appended(&mut session).unwrap();
+        assert!(end.is_some_and(|end| end >= opened_end));
+        written = next;
+    }
+    append(&dst, &text[written..]);
+    // Some writers leave the last line without newline; it only counts once
+    // it is complete
+    if text.last() != Some(&b'\n') {
+        append(&dst, b"\n");
+    }
+    read_appended(&mut session).unwrap();
+    assert_eq!(read_appended(&mut session).unwrap(), None);
+
+    let mut full = open_file(&src);
+    assert_eq!(session.end_time(), full.end_time());
+    let (followed, expected) = (snapshot(&mut session), snapshot(&mut full));
+    assert_eq!(followed.len(), expected.len());
+    for (followed, expected) in followed.iter().zip(&expected) {
+        assert_eq!(followed, expected);
+    }
+}
+
+#[test]
+fn basic_vcd_followed_to_the_end_matches_a_full_read() {
+    check_follow("basic.vcd", &[0.3, 0.6, 0.8]);
+}
+
+#[test]
+fn processor_testbenches_followed_to_the_end_match_a_full_read() {
+    check_follow("ProcDTW_tb.vcd", &[0.05, 0.5, 0.9]);
+    check_follow("proc_fft_tb_complex.vcd", &[0.2, 0.7]);
+}
+
+#[test]
+fn appended_time_steps_extend_the_time_range() {
+    let dst = scratch_file("basic.vcd.growing.vcd");
+    let text = std::fs::read(example("basic.vcd")).unwrap();
+    let split = split_point(&text, 0.5);
+    std::fs::write(&dst, &text[..split]).unwrap();
+
+    let mut session = open_file(&dst);
+    let before = session.hierarchy(0).time_range.end;
+    append(&dst, &text[split..]);
+    let end = read_appended(&mut session).unwrap().unwrap();
+    assert!(end > before);
+    assert_eq!(session.hierarchy(0).time_range.end, end);
+}
+
+const SMALL: &str = "$timescale 1ns $end
+$scope module tb $end
+$var wire 1 ! clk $end
+$var wire 4 \" count $end
+$upscope $end
+$enddefinitions $end
+#0
+0!
+b0000 \"
+#10
+1!
+b0001 \"
+#20
+0!
+";
+
+fn var_index(session: &WaveformSession, path: &str) -> usize {
+    all_vars(&session.hierarchy(0))
+        .into_iter()
+        .find(|var| var.path == path)
+        .unwrap()
+        .signal_ref
+}
+
+#[test]
+fn appended_changes_are_seen_by_searches_triggers_and_clocks() {
+    let dst = scratch_file("small.appended.vcd");
+    std::fs::write(&dst, SMALL).unwrap();
+    let mut session = open_file(&dst);
+    let clk = var_index(&session, "tb.clk");
+    let count = var_index(&session, "tb.count");
+
+    assert_eq!(find_edge(&mut session, clk, 20, true, true).unwrap(), None);
+    assert!(session.search.cached_conditions() > 0);
+    assert!(detect_clocks(&mut session).unwrap().is_empty());
+    // Not loaded before the append, so its changes are read on loading
+    session.unload_signals(&[count]).unwrap();
+
+    append(
+        &dst,
+        b"#30\n1!\nb0010 \"\n#40\n0!\n#50\n1!\nb0011 \"\n#60\n0!\n#70\n1!\n",
+    );
+    assert_eq!(read_appended(&mut session).unwrap(), Some(70));
+    assert_eq!(session.search.cached_conditions(), 0);
+    assert!(session.clocks.is_none());
+
+    assert_eq!(
+        find_edge(&mut session, clk, 20, true, true).unwrap(),
+        Some(30)
+    );
+    assert_eq!(
+        find_edge(&mut session, clk, 70, true, false).unwrap(),
+        Some(50)
+    );
+    assert_eq!(
+        find_transition(&mut session, clk, 40, true).unwrap(),
+        Some(50)
+    );
+    assert_eq!(
+        find_transition(&mut session, clk, 40, false).unwrap(),
+        Some(30)
+    );
+    assert_eq!(
+        find_triggers(&mut session, "tb.clk", None, None, None).unwrap(),
+        [(10, 20), (30, 40), (50, 60), (70, 70)].map(|(start, end)| TriggerMatch { start, end })
+    );
+    assert_eq!(
+        session.signal_changes(count).unwrap().times,
+        vec![0, 10, 30, 50]
+    );
+    let clocks = detect_clocks(&mut session).unwrap();
+    assert_eq!(clocks.len(), 1);
+    assert_eq!((clocks[0].period, clocks[0].last_edge), (20, 70));
+}
+
+#[test]
+fn only_vcd_files_can_be_followed() {
+    let mut session = open_example("basic.fst");
+    let error = read_appended(&mut session).unwrap_err();
+    assert!(error.contains("only VCD files"), "{}", error);
+}
+
+#[test]
+fn truncated_file_stops_following() {
+    let dst = scratch_file("basic.vcd.truncated.vcd");
+    let text = std::fs::read(example("basic.vcd")).unwrap();
+    std::fs::write(&dst, &text).unwrap();
+
+    let mut session = open_file(&dst);
+    std::fs::write(&dst, &text[..split_point(&text, 0.5)]).unwrap();
+    let error = read_appended(&mut session).unwrap_err();
+    assert!(error.contains("truncated"), "{}", error);
+}
+
+#[test]
+fn delta_cycles_back_to_the_previous_value_are_not_changes() {
+    let dst = scratch_file("small.delta.vcd");
+    std::fs::write(&dst, SMALL).unwrap();
+    let mut session = open_file(&dst);
+    let clk = var_index(&session, "tb.clk");
+
+    // A glitch at 50 settles to the value it started from
+    append(&dst, b"#30\n1!\n#40\n0!\n#50\n1!\n0!\n#60\n");
+    assert_eq!(read_appended(&mut session).unwrap(), Some(60));
+    let changes = session.signal_changes(clk).unwrap();
+    assert_eq!(changes.times, vec![0, 10, 20, 30, 40]);
+    assert_eq!(changes.values.last().map(String::as_str), Some("0"));
+}
+
+#[test]
+fn bytes_that_are_not_ascii_read_as_unknown_bits() {
+    let dst = scratch_file("small.garbled.vcd");
+    std::fs::write(&dst, SMALL).unwrap();
+    let mut session = open_file(&dst);
+    let count = var_index(&session, "tb.count");
+
+    append(&dst, b"#30\nb1\xff01 \"\n#40\nb\xc3\xa9 \"\n");
+    assert_eq!(read_appended(&mut session).unwrap(), Some(40));
+    let changes = session.signal_changes(count).unwrap();
+    assert_eq!(changes.times, vec![0, 10, 30, 40]);
+    assert_eq!(changes.values[2..], ["1x01", "xxxx"]);
+}
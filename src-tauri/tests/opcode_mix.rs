@@ -0,0 +1,89 @@
+//! Instruction mix of the DTW processor testbench.
+
+mod common;
+
+use common::{example, open_example, open_file, scratch_file};
+use polaris_lib::opcode_mix::{instruction_mix, InstructionMix};
+
+fn mix() -> InstructionMix {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    instruction_mix(&mut session, None).unwrap()
+}
+
+#[test]
+fn counts_every_executed_mnemonic() {
+    let mix = mix();
+    assert_eq!((mix.total_count, mix.unknown), (766, 0));
+    assert_eq!(mix.total_time, 7_665_000);
+    assert_eq!(
+        mix.mnemonics.iter().map(|m| m.count).sum::<usize>(),
+        mix.total_count
+    );
+
+    let lod = &mix.mnemonics[0];
+    assert_eq!(lod.mnemonic, "LOD");
+    assert_eq!((lod.count, lod.cycles), (230, Some(230)));
+
+    let p_lod = mix
+        .mnemonics
+        .iter()
+        .find(|m| m.mnemonic == "P_LOD")
+        .unwrap();
+    assert_eq!(p_lod.count, 75);
+    let jmp = mix.mnemonics.iter().find(|m| m.mnemonic == "JMP").unwrap();
+    assert_eq!((jmp.count, jmp.cycles), (75, Some(75)));
+    assert!((mix.mnemonics.iter().map(|m| m.share).sum::<f64>() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn lists_mnemonics_that_never_ran() {
+    let mix = mix();
+    assert!(mix.unused.iter().any(|m| m == "MLT"));
+    assert!(mix.unused.iter().any(|m| m == "S_ADD"));
+    assert!(!mix.unused.iter().any(|m| m == "LOD"));
+    assert!(mix.unused.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn splits_the_mix_by_function() {
+    let mix = mix();
+    assert_eq!(mix.source_path.as_deref(), Some("ProcDTW_tb.proc.linetabs"));
+    assert_eq!(mix.functions.len(), 1);
+    let main = &mix.functions[0];
+    assert_eq!(main.function, "main");
+    assert_eq!((main.count, main.cycles), (761, Some(761)));
+    assert!(main.mnemonics.iter().any(|m| m.mnemonic == "STI"));
+}
+
+
+#[test]
+fn leaves_the_final_halt_out() {
+    let mix = mix();
+    let halt = mix.halt.unwrap();
+    assert_eq!(halt.pc, Some(21));
+    assert_eq!(halt.mnemonic.as_deref(), Some("JMP"));
+    // The first fetch is at 0, so the program runs until the halt
+    assert_eq!(halt.time, mix.total_time);
+    assert!(!mix.unused.iter().any(|m| m == "JMP"));
+}
+
+#[test]
+fn keeps_a_final_jmp_that_is_not_held() {
+    // Cut the dump one cycle after the loop's JMP at 185000
+    let dir = scratch_file("opcode_mix_loop");
+    std::fs::create_dir_all(&dir).unwrap();
+    for table in ["trad_opcode.txt", "trad_cmm.txt"] {
+        std::fs::copy(example(table), dir.join(table)).unwrap();
+    }
+    let text = std::fs::read_to_string(example("ProcDTW_tb.vcd")).unwrap();
+    let cut = text.find("#190000\n").unwrap() + "#190000\n".len();
+    let path = dir.join("ProcDTW_tb.vcd");
+    std::fs::write(&path, &text[..cut]).unwrap();
+
+    let mut session = open_file(&path);
+    let mix = instruction_mix(&mut session, None).unwrap();
+    assert!(mix.halt.is_none());
+    assert_eq!((mix.total_count, mix.total_time), (19, 190_000));
+    let jmp = mix.mnemonics.iter().find(|m| m.mnemonic == "JMP").unwrap();
+    assert_eq!(jmp.count, 1);
+}
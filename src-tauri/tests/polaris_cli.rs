@@ -0,0 +1,156 @@
+//! Run the `polaris-cli` binary on the example files, as a CI job would.
+
+mod common;
+
+use common::{
+    all_vars, compare_values_with_dump, example, open_example, open_file, read_dump, scratch_file,
+};
+use std::process::{Command, Output};
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_polaris-cli"))
+        .args(args)
+        .output()
+        .expect("Failed to run polaris-cli")
+}
+
+fn path(path: &std::path::Path) -> &str {
+    path.to_str().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn converts_fst_to_vcd() {
+    let dst = scratch_file("basic.fst.cli.vcd");
+    let output = run(&["convert", path(&example("basic.fst")), path(&dst)]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let mut session = open_file(&dst);
+    let errors = compare_values_with_dump(&mut session, &read_dump("basic.fst.dump"));
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn prints_the_hierarchy_as_json() {
+    let output = run(&["hierarchy", path(&example("ProcDTW_tb.vcd"))]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let json: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+    let session = open_example("ProcDTW_tb.vcd");
+    let hierarchy = session.hierarchy(0);
+    assert_eq!(json["timescale"], hierarchy.timescale);
+    assert_eq!(json["timeRange"]["end"], hierarchy.time_range.end);
+    assert_eq!(
+        json["scopes"][0]["name"].as_str(),
+        Some(hierarchy.scopes[0].name.as_str())
+    );
+    assert_eq!(all_vars(&hierarchy).len(), 26);
+}
+
+#[test]
+fn compare_fails_only_when_runs_differ() {
+    let src = example("ProcDTW_tb.vcd");
+    let same = run(&["compare", path(&src), path(&src)]);
+    assert!(same.status.success(), "{:?}", same);
+
+    let dst = scratch_file("ProcDTW_tb.cli.vcd");
+    let text = std::fs::read_to_string(&src).unwrap();
+    std::fs::write(&dst, text.replacen("\nb0 (\n", "\nb111 (\n", 1)).unwrap();
+    let differ = run(&["compare", path(&src), path(&dst), "--limit", "5"]);
+    assert_eq!(differ.status.code(), Some(1), "{:?}", differ);
+    let json: serde_json::Value = serde_json::from_str(&stdout(&differ)).unwrap();
+    assert_eq!(json["mismatches"][0]["pathA"], "ProcDTW_tb.proc.out_sig_0");
+}
+
+#[test]
+fn traces_instructions_as_csv() {
+    let output = run(&["trace", path(&example("ProcDTW_tb.vcd"))]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let csv = stdout(&output);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("step,cycle,time,pc,mnemonic,operand,cycles,stack_pointer")
+    );
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    assert!(rows.len() > 100);
+    assert!(rows.iter().any(|row| !row[4].is_empty()));
+}
+
+#[test]
+fn exports_signals_sampled_on_the_clock() {
+    let dst = scratch_file("ProcDTW_tb.cli.json");
+    let output = run(&[
+        "export",
+        path(&example("ProcDTW_tb.vcd")),
+        path(&dst),
+        "--signal",
+        "ProcDTW_tb.proc.out_sig_2=signed decimal",
+        "--clock-edge",
+        "rising",
+        "--from",
+        "7600000",
+        "--cycles",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&dst).unwrap()).unwrap();
+    assert_eq!(json["clock"], "ProcDTW_tb.clk");
+    let values = json["columns"][0]["values"].as_array().unwrap();
+    assert_eq!(values.len(), json["times"].as_array().unwrap().len());
+    assert_eq!(values.len(), json["cycles"].as_array().unwrap().len());
+    assert!(values.contains(&serde_json::json!("-122")));
+}
+
+#[test]
+fn validate_reports_bad_tables_by_line() {
+    let dir = scratch_file("cli_project");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("op.txt"), "0 NOP\n1\n").unwrap();
+    std::fs::write(dir.join("state.txt"), "0 IDLE\n1 RUN\n").unwrap();
+    let spf = dir.join("Test.spf");
+    std::fs::write(
+        &spf,
+        r#"{ "processors": [{ "name": "ProcDTW",
+            "translation": { "opcodes": "op.txt", "sourceLines": "missing.txt" },
+            "enums": [{ "signal": "core.state", "file": "state.txt" }] }] }"#,
+    )
+    .unwrap();
+
+    let output = run(&["validate", path(&spf)]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let report = stdout(&output);
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", report);
+    assert!(lines[0].ends_with("op.txt:2: address 1 has no mnemonic"));
+    assert!(lines[1].contains("missing.txt:0:"));
+
+    // With a waveform the enum table must also name one of its signals
+    let output = run(&[
+        "validate",
+        path(&spf),
+        "--waveform",
+        path(&example("ProcDTW_tb.vcd")),
+    ]);
+    assert!(stdout(&output).contains("no signal matches core.state"));
+}
+
+#[test]
+fn usage_errors_exit_with_2() {
+    for args in [
+        &["frobnicate"][..],
+        &["convert", "only-one"],
+        &["hierarchy", "a.vcd", "--bogus", "1"],
+        &["hierarchy", "does-not-exist.vcd"],
+    ] {
+        let output = run(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("polaris-cli: "));
+    }
+}
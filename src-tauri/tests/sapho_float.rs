@@ -0,0 +1,182 @@
+//! Decoding and encoding of SAPHO floats. Small formats are checked for
+//! every bit pattern, wide ones with property tests.
+
+mod common;
+
+use common::{all_vars, open_example};
+use polaris_lib::sapho_float::{
+    decode_pair, encode_pair, format_value, FloatFormat, SaphoFloat, MAX_EXPONENT_BITS,
+    MAX_MANTISSA_BITS,
+};
+use proptest::prelude::*;
+
+fn format(mantissa_bits: u32, exponent_bits: u32) -> FloatFormat {
+    FloatFormat::new(mantissa_bits, exponent_bits).unwrap()
+}
+
+fn check_round_trip(bits: &str, format: FloatFormat) {
+    let value = SaphoFloat::decode(bits, format).unwrap();
+    assert_eq!(value.encode(format).unwrap(), bits);
+
+    // The exact decimal always parses to the same f64 as the direct conversion
+    let decimal: f64 = value.to_string().parse().unwrap();
+    assert_eq!(decimal, value.to_f64(), "{} in {:?}", bits, format);
+
+    // Converting back from f64 may pick another exponent, not another value.
+    // Underflowed, subnormal and infinite f64s have already lost the value.
+    let float = value.to_f64();
+    if format.mantissa_bits <= 52 && (value.mantissa.is_zero() || float.is_normal()) {
+        let again = SaphoFloat::from_f64(value.to_f64(), format).unwrap();
+        assert_eq!(
+            again.to_string(),
+            value.to_string(),
+            "{} in {:?}",
+            bits,
+            format
+        );
+    }
+}
+
+#[test]
+fn every_small_format_round_trips() {
+    for mantissa_bits in 1..=5 {
+        for exponent_bits in 1..=4 {
+            let format = format(mantissa_bits, exponent_bits);
+            for pattern in 0..1u32 << format.width() {
+                check_round_trip(&format!("{:0w$b}", pattern, w = format.width()), format);
+            }
+        }
+    }
+}
+
+/// A format of any supported width and one of its bit patterns.
+fn format_and_bits() -> impl Strategy<Value = (FloatFormat, String)> {
+    (1..=MAX_MANTISSA_BITS, 1..=MAX_EXPONENT_BITS).prop_flat_map(|(mantissa, exponent)| {
+        let format = format(mantissa, exponent);
+        let bits = proptest::collection::vec(any::<bool>(), format.width()).prop_map(|bits| {
+            bits.into_iter()
+                .map(|b| if b { '1' } else { '0' })
+                .collect()
+        });
+        (Just(format), bits)
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1000))]
+
+    #[test]
+    fn wide_formats_round_trip((format, bits) in format_and_bits()) {
+        check_round_trip(&bits, format);
+    }
+
+    #[test]
+    fn from_f64_is_the_nearest_value(
+        negative in any::<bool>(),
+        fraction in 1.0f64..2.0,
+        // Below 2^-100 the -128 exponent leaves fewer than 23 mantissa bits
+        exponent in -100i32..200,
+    ) {
+        let format = format(23, 8);
+        let value = fraction * 2f64.powi(exponent) * if negative { -1.0 } else { 1.0 };
+        let Ok(encoded) = SaphoFloat::from_f64(value, format) else {
+            // Only values beyond 23 bits shifted by the 127 exponent do not fit
+            prop_assert!(value.abs() > 2f64.powi(149), "{}", value);
+            return Ok(());
+        };
+        // 23 mantissa bits leave at most half a unit of the last place of error
+        let error = (encoded.to_f64() - value).abs();
+        prop_assert!(
+            error <= value.abs() / (1u64 << 23) as f64,
+            "{} -> {:?}",
+            value,
+            encoded
+        );
+    }
+}
+
+#[test]
+fn values_that_do_not_fit_are_errors() {
+    let format = format(23, 8);
+    assert!(SaphoFloat::from_f64(f64::MAX, format).is_err());
+    assert!(SaphoFloat::from_f64(f64::NAN, format).is_err());
+}
+
+#[test]
+fn decimals_are_exact() {
+    let value = |bits: &str, format| SaphoFloat::decode(bits, format).unwrap().to_string();
+    // -1 exponent, mantissa 5
+    assert_eq!(value("0111101", format(3, 3)), "2.5");
+    assert_eq!(value("1001011", format(3, 3)), "-6");
+    assert_eq!(value("0000000", format(3, 3)), "0");
+    assert_eq!(value("1000000", format(3, 3)), "0");
+    // 60 mantissa bits are beyond what an f64 (or the JS viewer) can hold
+    let wide = format!("0{}{}", "0".repeat(4), "1".repeat(60));
+    assert_eq!(value(&wide, format(60, 4)), "1152921504606846975");
+    // 2^-8 times 3
+    assert_eq!(value("011100011", format(2, 6)), "0.01171875");
+    // Mantissas wider than 128 bits, up to the 255 the width header allows
+    let ones = format!("0{}{}", "0".repeat(4), "1".repeat(200));
+    assert_eq!(
+        value(&ones, format(200, 4)),
+        "1606938044258990275541962092341162602522202993782792835301375"
+    );
+    let top = format!("1{}1{}", "0".repeat(8), "0".repeat(254));
+    let top = SaphoFloat::decode(&top, format(255, 8)).unwrap();
+    assert_eq!(top.to_f64(), -(2f64.powi(254)));
+    assert_eq!(top.mantissa.bits(), 255);
+}
+
+#[test]
+fn pairs_carry_their_format() {
+    let bits = "0000001100000010011101101011";
+    let (format, a, b) = decode_pair(bits).unwrap();
+    assert_eq!(format, self::format(3, 2));
+    assert_eq!((a.to_f64(), b.to_f64()), (2.5, -6.0));
+    assert_eq!(encode_pair(format, &a, &b).unwrap(), bits);
+
+    assert_eq!(format_value(bits, "vector", None).unwrap(), "[2.5, -6]");
+    assert_eq!(format_value(bits, "complex", None).unwrap(), "2.5 + j -6");
+    assert_eq!(
+        format_value("01111", "float", Some(self::format(3, 1))).unwrap(),
+        "3.5"
+    );
+    assert!(format_value("0000001100000010x11101101011", "vector", None).is_err());
+    assert!(format_value(&bits[..20], "complex", None).is_err());
+    assert!(format_value("01111", "float", None).is_err());
+}
+
+#[test]
+fn wide_pairs_decode() {
+    let format = format(200, 4);
+    let one = SaphoFloat::from_f64(1.0, format).unwrap();
+    let half = SaphoFloat::from_f64(-0.5, format).unwrap();
+    let bits = encode_pair(format, &one, &half).unwrap();
+    assert!(bits.starts_with("1100100000000100"));
+    assert_eq!(format_value(&bits, "complex", None).unwrap(), "1 + j -0.5");
+}
+
+#[test]
+fn fft_testbench_complex_values_decode() {
+    let mut session = open_example("proc_fft_tb_complex.vcd");
+    let hierarchy = session.hierarchy(0);
+    let complex: Vec<usize> = all_vars(&hierarchy)
+        .into_iter()
+        .filter(|var| var.name.starts_with("comp"))
+        .map(|var| var.signal_ref)
+        .collect();
+    assert!(!complex.is_empty());
+
+    for signal_ref in complex {
+        let changes = session.signal_changes(signal_ref).unwrap();
+        for bits in changes
+            .values
+            .iter()
+            .filter(|bits| !bits.contains(['x', 'z']))
+        {
+            let (format, re, im) = decode_pair(bits).unwrap();
+            let width = 16 + 2 * format.width();
+            assert_eq!(encode_pair(format, &re, &im).unwrap(), bits[..width]);
+        }
+    }
+}
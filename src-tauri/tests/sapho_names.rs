@@ -0,0 +1,139 @@
+//! Classification of the signal names in the example testbenches.
+
+mod common;
+
+use common::{all_vars, open_example};
+use polaris_lib::sapho_names::{classify, SignalClass, SignalKind};
+use std::collections::HashMap;
+
+fn class(
+    kind: SignalKind,
+    function: Option<&str>,
+    variable: Option<&str>,
+    port: Option<u32>,
+) -> SignalClass {
+    SignalClass {
+        kind,
+        function: function.map(str::to_string),
+        variable: variable.map(str::to_string),
+        port,
+    }
+}
+
+/// Class of every var of an example, by path.
+fn classes(name: &str) -> HashMap<String, SignalClass> {
+    let session = open_example(name);
+    let hierarchy = session.hierarchy(0);
+    all_vars(&hierarchy)
+        .into_iter()
+        .map(|var| (var.path.clone(), var.class.clone()))
+        .collect()
+}
+
+#[test]
+fn dtw_testbench_names() {
+    let classes = classes("ProcDTW_tb.vcd");
+    let expected = [
+        ("ProcDTW_tb.clk", class(SignalKind::Clock, None, None, None)),
+        ("ProcDTW_tb.rst", class(SignalKind::Reset, None, None, None)),
+        (
+            "ProcDTW_tb.proc.in_sim_2",
+            class(SignalKind::Input, None, None, Some(2)),
+        ),
+        (
+            "ProcDTW_tb.proc.req_in_sim_1",
+            class(SignalKind::InputRequest, None, None, Some(1)),
+        ),
+        (
+            "ProcDTW_tb.proc.out_sig_0",
+            class(SignalKind::Output, None, None, Some(0)),
+        ),
+        (
+            "ProcDTW_tb.proc.out_en_sim_2",
+            class(SignalKind::OutputEnable, None, None, Some(2)),
+        ),
+        (
+            "ProcDTW_tb.proc.valr2",
+            class(SignalKind::Instruction, None, None, None),
+        ),
+        (
+            "ProcDTW_tb.proc.linetabs",
+            class(SignalKind::SourceLine, None, None, None),
+        ),
+        (
+            "ProcDTW_tb.proc.me1_f_global_v_len_data_e_",
+            class(SignalKind::Int, Some("global"), Some("len_data"), None),
+        ),
+        (
+            "ProcDTW_tb.proc.p_ProcDTW.core.sp.pointeri",
+            class(SignalKind::DataStackPointer, None, None, None),
+        ),
+    ];
+    for (path, class) in expected {
+        assert_eq!(classes.get(path), Some(&class), "{}", path);
+    }
+
+    let flags: Vec<&str> = classes
+        .iter()
+        .filter(|(_, class)| class.kind == SignalKind::Flag)
+        .map(|(path, _)| path.as_str())
+        .collect();
+    assert!(flags.iter().all(|path| path.contains(".core.")));
+    assert!(flags.iter().any(|path| path.ends_with(".fl_full")));
+}
+
+#[test]
+fn fft_testbench_names() {
+    let classes = classes("proc_fft_tb_complex.vcd");
+    let by_name = |name: &str| {
+        classes
+            .iter()
+            .find(|(path, _)| path.ends_with(name))
+            .map(|(_, class)| class.clone())
+            .unwrap()
+    };
+    assert_eq!(
+        by_name(".comp_me3_f_fft_v_temp_e_"),
+        class(SignalKind::Complex, Some("fft"), Some("temp"), None)
+    );
+    assert_eq!(
+        by_name(".me1_f_fft_v_N_e_"),
+        class(SignalKind::Int, Some("fft"), Some("N"), None)
+    );
+    assert_eq!(
+        by_name(".out_sig_1"),
+        class(SignalKind::Output, None, None, Some(1))
+    );
+}
+
+#[test]
+fn names_outside_the_examples() {
+    assert_eq!(
+        classify("pointeri", "tb.proc.core.isp.pointeri").kind,
+        SignalKind::InstructionStackPointer
+    );
+    assert_eq!(
+        classify("me2_f_main_v_x_e_", "tb.proc.me2_f_main_v_x_e_"),
+        class(SignalKind::Float, Some("main"), Some("x"), None)
+    );
+    assert_eq!(
+        classify("me3_f_main_v_xy_e_[61:0]", "tb.proc.me3_f_main_v_xy_e_"),
+        class(SignalKind::Vector, Some("main"), Some("xy"), None)
+    );
+    assert_eq!(
+        classify("arr_buffer", "tb.proc.arr_buffer"),
+        class(SignalKind::Array, None, Some("buffer"), None)
+    );
+    // Near misses stay unclassified
+    for name in [
+        "in_sim_",
+        "out_sig_a",
+        "me1_f_main_e_",
+        "me4_f_a_v_b_e_",
+        "clk2",
+    ] {
+        assert_eq!(classify(name, name).kind, SignalKind::Other, "{}", name);
+    }
+    // A var named `core` is not inside a core scope
+    assert_eq!(classify("core", "tb.core").kind, SignalKind::Other);
+}
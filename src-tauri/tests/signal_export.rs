@@ -0,0 +1,232 @@
+//! Export processor outputs sampled on changes and on clock edges, in the
+//! radixes the viewer shows them in.
+
+mod common;
+
+use common::{all_vars, open_example, open_file, scratch_file};
+use polaris_lib::sapho_float::{FloatFormat, SaphoFloat};
+use polaris_lib::signal_export::{
+    format_radix, sample_signals, table_to_csv, table_to_json, ExportSignal, Sampling,
+    SignalExportRequest,
+};
+use polaris_lib::waveform::WaveformSession;
+
+fn signal(session: &WaveformSession, path: &str, radix: Option<&str>) -> ExportSignal {
+    let hierarchy = session.hierarchy(0);
+    let var = all_vars(&hierarchy)
+        .into_iter()
+        .find(|var| var.path == path)
+        .unwrap_or_else(|| panic!("{} not found", path));
+    ExportSignal {
+        signal_ref: var.signal_ref,
+        radix: radix.map(str::to_string),
+        float_format: None,
+    }
+}
+
+fn request(signals: Vec<ExportSignal>, sampling: Sampling) -> SignalExportRequest {
+    SignalExportRequest {
+        signals,
+        sampling,
+        clock_ref: None,
+        from: None,
+        to: None,
+        float_format: None,
+        cycles: false,
+    }
+}
+
+#[test]
+fn formats_values_in_each_radix() {
+    assert_eq!(format_radix("1010", 8, "hex", None), "0A");
+    assert_eq!(format_radix("1010", 8, "binary", None), "00001010");
+    assert_eq!(format_radix("1010", 8, "decimal", None), "10");
+    let minus_122 = "11111111111111111111111110000110";
+    assert_eq!(format_radix(minus_122, 32, "signed decimal", None), "-122");
+    assert_eq!(format_radix(minus_122, 32, "decimal", None), "4294967174");
+    assert_eq!(format_radix("1x10", 4, "hex", None), "1x10");
+    assert_eq!(
+        format_radix(&"1".repeat(128), 128, "signed decimal", None),
+        "-1"
+    );
+}
+
+#[test]
+fn changes_are_sampled_with_the_value_in_effect() {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    let signals = vec![
+        signal(
+            &session,
+            "ProcDTW_tb.proc.out_sig_2",
+            Some("signed decimal"),
+        ),
+        signal(&session, "ProcDTW_tb.proc.out_en_sim_2", None),
+    ];
+    let request = SignalExportRequest {
+        from: Some(7_600_000),
+        to: Some(7_700_000),
+        ..request(signals, Sampling::Changes)
+    };
+    let table = sample_signals(&mut session, &request).unwrap();
+    // Nothing needs a clock, so none was looked for
+    assert!(session.clocks.is_none());
+    assert_eq!((table.clock, table.cycles), (None, None));
+
+    assert_eq!(table.times[0], 7_600_000);
+    let at = |time: u64| table.times.iter().position(|&t| t == time).unwrap();
+    let outputs = &table.columns[0].values;
+    let strobes = &table.columns[1].values;
+    assert_eq!(table.columns[1].radix, "binary");
+    assert_eq!(outputs[0].as_deref(), Some("0"));
+    assert_eq!(outputs[at(7_635_000)].as_deref(), Some("10"));
+    assert_eq!(strobes[at(7_635_000)].as_deref(), Some("1"));
+    assert_eq!(outputs[at(7_655_000)].as_deref(), Some("-122"));
+    assert_eq!(strobes[at(7_645_000)].as_deref(), Some("0"));
+    assert_eq!(table.times, {
+        let mut sorted = table.times.clone();
+        sorted.dedup();
+        sorted
+    });
+}
+
+#[test]
+fn clock_edges_are_sampled_once_per_cycle() {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    let signals = vec![signal(&session, "ProcDTW_tb.proc.out_sig_2", None)];
+    let request = SignalExportRequest {
+        from: Some(7_600_000),
+        to: Some(7_700_000),
+        cycles: true,
+        ..request(signals, Sampling::RisingEdge)
+    };
+    let table = sample_signals(&mut session, &request).unwrap();
+
+    assert_eq!(table.clock.as_deref(), Some("ProcDTW_tb.clk"));
+    let cycles = table.cycles.as_ref().unwrap();
+    assert_eq!(table.times.len(), 10);
+    assert!(cycles.windows(2).all(|pair| pair[1] == pair[0] + 1));
+    assert!(table
+        .times
+        .windows(2)
+        .all(|pair| pair[1] - pair[0] == 10_000));
+    assert!(table.columns[0]
+        .values
+        .iter()
+        .any(|value| value.as_deref() == Some("0000000A")));
+
+    let falling = SignalExportRequest {
+        sampling: Sampling::FallingEdge,
+        ..request
+    };
+    // Falling edges sit half a period after the rising ones; the range is
+    // inclusive and both of its ends are falling edges
+    let falling = sample_signals(&mut session, &falling).unwrap();
+    assert_eq!(falling.times.len(), 11);
+    assert!(table.times.iter().all(|t| t % 10_000 == 5_000));
+    assert!(falling.times.iter().all(|t| t % 10_000 == 0));
+}
+
+#[test]
+fn complex_values_are_quoted_in_csv_and_kept_in_json() {
+    let mut session = open_example("proc_fft_tb_complex.vcd");
+    let path = "proc_fft_tb.proc.comp_me3_f_fft_v_temp_e_";
+    let signals = vec![
+        signal(&session, path, Some("complex")),
+        signal(&session, path, Some("vector")),
+    ];
+    let table = sample_signals(&mut session, &request(signals, Sampling::Changes)).unwrap();
+
+    let csv = table_to_csv(&table);
+    let mut lines = csv.lines();
+    let header = lines.next().unwrap();
+    assert!(header.starts_with("time,"));
+    assert!(header.ends_with(&format!("{},{}", path, path)));
+    assert_eq!(lines.count(), table.times.len());
+    assert!(csv.contains("5 + j 0,\"[5, 0]\""), "{}", csv);
+
+    let json: serde_json::Value = serde_json::from_str(&table_to_json(&table).unwrap()).unwrap();
+    assert_eq!(json["columns"][0]["radix"], "complex");
+    assert_eq!(
+        json["columns"][0]["values"].as_array().unwrap().len(),
+        table.times.len()
+    );
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_holds_the_same_columns_as_csv() {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use polaris_lib::signal_export::{write_table, ExportFormat};
+
+    let mut session = open_example("proc_fft_tb_complex.vcd");
+    let path = "proc_fft_tb.proc.comp_me3_f_fft_v_temp_e_";
+    let signals = vec![signal(&session, path, Some("complex"))];
+    let table = sample_signals(&mut session, &request(signals, Sampling::Changes)).unwrap();
+    let dst = scratch_file("fft.parquet");
+    std::fs::write(&dst, write_table(&table, ExportFormat::Parquet).unwrap()).unwrap();
+
+    let reader = SerializedFileReader::new(std::fs::File::open(&dst).unwrap()).unwrap();
+    let schema = reader.metadata().file_metadata().schema_descr();
+    let names: Vec<&str> = schema.columns().iter().map(|c| c.name()).collect();
+    let value_column = names.len() - 1;
+    assert_eq!(names[0], "time");
+    assert_eq!(names[value_column], path);
+    let rows: Vec<_> = reader
+        .get_row_iter(None)
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    assert_eq!(rows.len(), table.times.len());
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(row.get_long(0).unwrap() as u64, table.times[i]);
+        assert_eq!(
+            row.get_string(value_column).ok(),
+            table.columns[0].values[i].as_ref()
+        );
+    }
+    assert_eq!(
+        ExportFormat::from_path("FFT.Parquet"),
+        ExportFormat::Parquet
+    );
+}
+
+#[test]
+fn each_signal_can_bring_the_float_format_of_its_processor() {
+    // 1.5 in floats of 5 mantissa and 3 exponent bits, read by two processors
+    let format = FloatFormat::new(5, 3).unwrap();
+    let bits = SaphoFloat::from_f64(1.5, format)
+        .unwrap()
+        .encode(format)
+        .unwrap();
+    let vcd = format!(
+        "$timescale 1ns $end\n$scope module tb $end\n\
+         $var wire 9 ! me2_main_x $end\n$var wire 9 \" me2_main_y $end\n\
+         $upscope $end\n$enddefinitions $end\n#0\nb{} !\nb{} \"\n#4\n",
+        bits, bits
+    );
+    let path = scratch_file("export_float.vcd");
+    std::fs::write(&path, vcd).unwrap();
+    let mut session = open_file(&path);
+
+    let mut x = signal(&session, "tb.me2_main_x", Some("float"));
+    x.float_format = Some(format);
+    let y = signal(&session, "tb.me2_main_y", Some("float"));
+    let table = sample_signals(&mut session, &request(vec![x, y], Sampling::Changes)).unwrap();
+    assert_eq!(table.columns[0].values[0].as_deref(), Some("1.5"));
+    // Without a format the bits are kept
+    assert_eq!(table.columns[1].values[0].as_deref(), Some(bits.as_str()));
+}
+
+#[test]
+fn reversed_range_is_an_error() {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    let signals = vec![signal(&session, "ProcDTW_tb.proc.out_sig_2", None)];
+    let request = SignalExportRequest {
+        from: Some(10),
+        to: Some(5),
+        ..request(signals, Sampling::Changes)
+    };
+    let error = sample_signals(&mut session, &request).unwrap_err();
+    assert!(error.contains("range"), "{}", error);
+}
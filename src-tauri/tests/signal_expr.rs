@@ -0,0 +1,139 @@
+//! Derived signals over `examples/basic.vcd` and the DTW processor testbench.
+
+mod common;
+
+use common::{open_example, open_file, scratch_file};
+use polaris_lib::signal_expr::{evaluate, evaluate_with_symbols};
+use polaris_lib::waveform::WaveformSession;
+use std::collections::HashMap;
+
+fn changes(session: &mut WaveformSession, expression: &str) -> Vec<(u64, String)> {
+    let derived = evaluate(session, expression).unwrap();
+    derived.times.into_iter().zip(derived.values).collect()
+}
+
+fn change(time: u64, value: &str) -> (u64, String) {
+    (time, value.to_string())
+}
+
+#[test]
+fn arithmetic_keeps_the_operand_width() {
+    let mut session = open_example("basic.vcd");
+    let derived = evaluate(&mut session, "vector - 1").unwrap();
+    assert_eq!(derived.width, 8);
+    assert_eq!(derived.values[0], "11111111");
+    assert_eq!(derived.values[1], "00000000");
+
+    let sum = changes(&mut session, "variables.vector + vector[3:0]");
+    assert_eq!(sum[2], change(2, "00000100"));
+    assert_eq!(sum.last(), Some(&change(9, "00000000")));
+}
+
+#[test]
+fn comparisons_are_one_bit() {
+    let mut session = open_example("basic.vcd");
+    assert_eq!(
+        changes(&mut session, "vector > 4"),
+        vec![change(0, "0"), change(5, "1")]
+    );
+    assert_eq!(
+        changes(&mut session, "integer == 0xfffffff8"),
+        vec![change(0, "0"), change(9, "1")]
+    );
+}
+
+#[test]
+fn unknown_operands_give_x_unless_the_result_is_decided() {
+    let mut session = open_example("basic.vcd");
+    // one_transition is x until 4ns, the other side decides at 2ns
+    assert_eq!(
+        changes(&mut session, "!one_transition || vector == 2"),
+        vec![
+            change(0, "x"),
+            change(2, "1"),
+            change(3, "x"),
+            change(4, "1")
+        ]
+    );
+}
+
+#[test]
+fn processor_expressions() {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    assert_eq!(
+        evaluate(&mut session, "clk & req_in_sim_1").unwrap().width,
+        1
+    );
+    assert_eq!(
+        evaluate(&mut session, "out_sig_0 - in_sim_1")
+            .unwrap()
+            .width,
+        32
+    );
+    assert_eq!(evaluate(&mut session, "pointeri > 4").unwrap().width, 1);
+}
+
+#[test]
+fn errors() {
+    let mut session = open_example("basic.vcd");
+    assert_eq!(
+        evaluate(&mut session, "missing + 1").unwrap_err(),
+        "Signal missing not found"
+    );
+    assert!(evaluate(&mut session, "vector +")
+        .unwrap_err()
+        .starts_with("Invalid expression"));
+    assert!(evaluate(&mut session, "real + 1").is_err());
+    assert!(evaluate(&mut session, "vector[8]").is_err());
+}
+
+const PORTS: &str = "$timescale 1ns $end
+$scope module tb $end
+$var wire 8 ! out_sig_0 $end
+$var wire 8 \" in_sim_1 $end
+$var wire 8 # count $end
+$scope module a $end
+$var wire 8 $ OUT $end
+$upscope $end
+$scope module b $end
+$var wire 8 % OUT $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+b11111011 !
+b11 \"
+b11111011 #
+b0 $
+b0 %
+#10
+";
+
+#[test]
+fn ports_are_signed_numbers() {
+    let path = scratch_file("signed_ports.vcd");
+    std::fs::write(&path, PORTS).unwrap();
+    let mut session = open_file(&path);
+    let value =
+        |session: &mut WaveformSession, expression: &str| changes(session, expression).remove(0).1;
+
+    // -5 and 3
+    assert_eq!(value(&mut session, "out_sig_0 - in_sim_1"), "11111000");
+    assert_eq!(value(&mut session, "out_sig_0 / in_sim_1"), "11111111");
+    assert_eq!(value(&mut session, "out_sig_0 < in_sim_1"), "1");
+    assert_eq!(value(&mut session, "out_sig_0 < 0"), "1");
+    // Unsigned as soon as one operand is: 251 and 3
+    assert_eq!(value(&mut session, "count / in_sim_1"), "01010011");
+    assert_eq!(value(&mut session, "count < in_sim_1"), "0");
+    assert_eq!(value(&mut session, "out_sig_0[7:0] < in_sim_1"), "0");
+}
+
+#[test]
+fn ambiguous_names_are_reported_even_when_they_are_mnemonics() {
+    let path = scratch_file("ambiguous_mnemonic.vcd");
+    std::fs::write(&path, PORTS).unwrap();
+    let mut session = open_file(&path);
+    let symbols = HashMap::from([(None, HashMap::from([("OUT".to_string(), vec![3])]))]);
+    let error = evaluate_with_symbols(&mut session, "count == OUT", &symbols).unwrap_err();
+    assert!(error.contains("ambiguous"), "{}", error);
+}
@@ -0,0 +1,184 @@
+//! Statistics over `examples/basic.vcd`, where every var changes once per
+//! nanosecond from 0 to 9ns and the waveform ends at 10ns.
+
+mod common;
+
+use common::{all_vars, example, open_example, open_file, scratch_file};
+use polaris_lib::sapho_float::{FloatFormat, SaphoFloat};
+use polaris_lib::signal_stats::{signal_stats, Encoding, HistogramBin, StatsRequest};
+
+const BIT: usize = 0;
+const VECTOR: usize = 1;
+const REAL: usize = 3;
+const STRING: usize = 4;
+
+#[test]
+fn bits_report_high_time_and_duty_cycle() {
+    let mut session = open_example("basic.vcd");
+    let stats = signal_stats(&mut session, BIT, Encoding::Unsigned, None, None, None).unwrap();
+    assert_eq!((stats.from, stats.to), (0, 10));
+    assert_eq!(stats.toggles, 8);
+    // `1` from 3ns and `h` from 4ns, both up to the next change
+    assert_eq!(stats.high_time, Some(2));
+    // Low for as long (`0` from 0ns, `l` from 7ns); the rest is unknown
+    assert_eq!(stats.duty_cycle, Some(0.5));
+    assert_eq!(stats.min, None);
+    assert!(stats.histogram.is_empty());
+}
+
+#[test]
+fn vectors_follow_the_encoding() {
+    let mut session = open_example("basic.vcd");
+    // 0 to 8, then 0b11111000 from 9ns to the end
+    let unsigned =
+        signal_stats(&mut session, VECTOR, Encoding::Unsigned, None, None, None).unwrap();
+    assert_eq!(unsigned.toggles, 9);
+    assert_eq!((unsigned.min, unsigned.max), (Some(0.0), Some(248.0)));
+    assert_eq!(unsigned.mean, Some(28.4));
+    assert_eq!(unsigned.high_time, None);
+
+    let signed = signal_stats(&mut session, VECTOR, Encoding::Signed, None, None, Some(2)).unwrap();
+    assert_eq!((signed.min, signed.max), (Some(-8.0), Some(8.0)));
+    assert_eq!(signed.mean, Some(2.8));
+    assert_eq!(
+        signed.histogram,
+        vec![
+            HistogramBin {
+                start: -8.0,
+                end: 0.0,
+                time: 1
+            },
+            HistogramBin {
+                start: 0.0,
+                end: 8.0,
+                time: 9
+            },
+        ]
+    );
+}
+
+#[test]
+fn ranges_clip_the_first_and_last_value() {
+    let mut session = open_example("basic.vcd");
+    let stats = signal_stats(
+        &mut session,
+        VECTOR,
+        Encoding::Unsigned,
+        Some(2),
+        Some(5),
+        None,
+    )
+    .unwrap();
+    assert_eq!(stats.toggles, 3);
+    assert_eq!((stats.min, stats.max), (Some(2.0), Some(5.0)));
+    assert_eq!(stats.mean, Some(3.0));
+    assert_eq!(stats.histogram.iter().map(|bin| bin.time).sum::<u64>(), 3);
+
+    assert!(signal_stats(
+        &mut session,
+        VECTOR,
+        Encoding::Unsigned,
+        Some(5),
+        Some(2),
+        None
+    )
+    .is_err());
+}
+
+#[test]
+fn reals_and_strings() {
+    let mut session = open_example("basic.vcd");
+    let real = signal_stats(&mut session, REAL, Encoding::Signed, None, None, None).unwrap();
+    assert_eq!((real.min, real.max), (Some(0.5), Some(8.5)));
+    assert_eq!(real.mean, Some(4.9));
+
+    let string = signal_stats(&mut session, STRING, Encoding::Unsigned, None, None, None).unwrap();
+    assert_eq!(string.toggles, 8);
+    assert_eq!(string.mean, None);
+    assert_eq!(string.duty_cycle, None);
+}
+
+#[test]
+fn sapho_floats_are_decoded_with_the_processor_format() {
+    // 1.5, -2 and 0.25 as floats of 5 mantissa and 3 exponent bits
+    let format = FloatFormat::new(5, 3).unwrap();
+    let bits = |value| {
+        let float = SaphoFloat::from_f64(value, format).unwrap();
+        float.encode(format).unwrap()
+    };
+    let vcd = format!(
+        "$timescale 1ns $end\n$scope module tb $end\n\
+         $var wire 9 ! me2_main_x $end\n$upscope $end\n$enddefinitions $end\n\
+         #0\nb{} !\n#2\nb{} !\n#3\nb{} !\n#4\n",
+        bits(1.5),
+        bits(-2.0),
+        bits(0.25)
+    );
+    let path = scratch_file("stats_float.vcd");
+    std::fs::write(&path, vcd).unwrap();
+    let mut session = open_file(&path);
+
+    let encoding = Encoding::from_radix("float", Some(format)).unwrap();
+    let stats = signal_stats(&mut session, 0, encoding, None, None, None).unwrap();
+    assert_eq!((stats.min, stats.max), (Some(-2.0), Some(1.5)));
+    assert_eq!(stats.mean, Some((1.5 * 2.0 - 2.0 + 0.25) / 4.0));
+    assert!(stats.components.is_empty());
+}
+
+#[test]
+fn complex_values_summarize_magnitude_and_components() {
+    let path = example("proc_fft_tb_complex.vcd");
+    let mut session = open_file(&path);
+    let temp = all_vars(&session.hierarchy(0))
+        .into_iter()
+        .find(|var| var.name == "comp_me3_f_fft_v_temp_e_")
+        .unwrap()
+        .signal_ref;
+
+    let stats = signal_stats(&mut session, temp, Encoding::Complex, None, None, None).unwrap();
+    let names: Vec<&str> = stats.components.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["real", "imaginary"]);
+    let ranges: Vec<(f64, f64)> = stats.components.iter().map(|c| (c.min, c.max)).collect();
+    assert_eq!(ranges, [(0.0, 20.0), (-5.65673828125, 0.0)]);
+    // The largest value is 20 - j 5.657
+    assert_eq!(stats.min, Some(0.0));
+    assert_eq!(stats.max, Some(20f64.hypot(5.65673828125)));
+    assert!(!stats.histogram.is_empty());
+}
+
+#[test]
+fn radixes_map_to_encodings() {
+    let format = FloatFormat::new(16, 6).unwrap();
+    assert_eq!(Encoding::from_radix("hex", None), Ok(Encoding::Unsigned));
+    assert_eq!(
+        Encoding::from_radix("signed decimal", None),
+        Ok(Encoding::Signed)
+    );
+    assert_eq!(Encoding::from_radix("complex", None), Ok(Encoding::Complex));
+    assert_eq!(
+        Encoding::from_radix("float", Some(format)),
+        Ok(Encoding::Float(format))
+    );
+    assert!(Encoding::from_radix("float", None).is_err());
+    assert!(Encoding::from_radix("ascii", None).is_err());
+}
+
+#[test]
+fn each_request_can_bring_the_float_format_of_its_processor() {
+    let own = FloatFormat::new(5, 3).unwrap();
+    let shared = FloatFormat::new(16, 6).unwrap();
+    let request = |float_format| StatsRequest {
+        signal_ref: VECTOR,
+        radix: Some("float".to_string()),
+        float_format,
+    };
+    assert_eq!(
+        request(Some(own)).encoding(Some(shared)),
+        Ok(Encoding::Float(own))
+    );
+    assert_eq!(
+        request(None).encoding(Some(shared)),
+        Ok(Encoding::Float(shared))
+    );
+    assert!(request(None).encoding(None).is_err());
+}
@@ -0,0 +1,68 @@
+//! C+- source-line profile of the DTW processor testbench, from `linetabs`
+//! and the `trad_cmm.txt` table next to it.
+
+mod common;
+
+use common::{example, open_example};
+use polaris_lib::source_profile::{line_functions, profile_source, SourceProfile};
+use polaris_lib::translation::TranslationTables;
+
+fn profile() -> SourceProfile {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    profile_source(&mut session, None).unwrap()
+}
+
+#[test]
+fn lines_belong_to_their_function() {
+    let text = std::fs::read_to_string(example("trad_cmm.txt")).unwrap();
+    let functions = line_functions(&TranslationTables::parse_source_lines(&text, "trad_cmm.txt").0);
+    assert_eq!(functions.get(&-2).map(String::as_str), Some("main"));
+    assert_eq!(functions.get(&11), None);
+    assert_eq!(functions.get(&16).map(String::as_str), Some("main"));
+    assert_eq!(functions.get(&104).map(String::as_str), Some("main"));
+    assert_eq!(functions.get(&127).map(String::as_str), Some("main"));
+    assert_eq!(functions.get(&-1), None);
+
+    let (lines, _) = TranslationTables::parse_source_lines(
+        "1 int f(int x) { return x; }\n2 int y;\n3 void main()\n4 {\n5 y = f(2);\n6 }\n",
+        "test.txt",
+    );
+    let functions = line_functions(&lines);
+    assert_eq!(functions.get(&1).map(String::as_str), Some("f"));
+    assert_eq!(functions.get(&2), None);
+    assert_eq!(functions.get(&5).map(String::as_str), Some("main"));
+}
+
+#[test]
+fn totals_cover_the_whole_simulation() {
+    let profile = profile();
+    assert_eq!(profile.source_path, "ProcDTW_tb.proc.linetabs");
+    assert_eq!(profile.program.as_deref(), Some("ProcDTW"));
+    assert_eq!(profile.total_time, 200_000_000);
+    assert_eq!(
+        profile.lines.iter().map(|line| line.time).sum::<u64>(),
+        profile.total_time
+    );
+    assert!(profile
+        .lines
+        .windows(2)
+        .all(|pair| pair[0].time >= pair[1].time));
+}
+
+#[test]
+fn finds_the_hot_loop() {
+    let profile = profile();
+    // The program ends at `return;` and waits on the FIM entry for the rest
+    // of the simulation
+    assert_eq!(profile.lines[0].line, -3);
+    assert_eq!(profile.lines[0].text, "FIM");
+
+    let hot = &profile.lines[1];
+    assert_eq!(hot.line, 22);
+    assert_eq!((hot.entries, hot.cycles), (76, Some(228)));
+    assert_eq!(hot.function.as_deref(), Some("main"));
+
+    assert_eq!(profile.functions.len(), 1);
+    assert_eq!(profile.functions[0].name, "main");
+    assert_eq!(profile.functions[0].cycles, Some(761));
+}
@@ -0,0 +1,122 @@
+//! Stack depth of the DTW processor testbench against the sizes of a
+//! project file or of the program's directives.
+
+mod common;
+
+use common::{open_example, open_file, scratch_file};
+use polaris_lib::sapho_names::{processor_instance, SignalKind};
+use polaris_lib::spf::SpfProject;
+use polaris_lib::stack_analysis::{analyze_stacks, StackAnalysis};
+
+const POINTER: &str = "ProcDTW_tb.proc.p_ProcDTW.core.sp.pointeri";
+
+fn analyze(spf: Option<&SpfProject>) -> StackAnalysis {
+    let mut session = open_example("ProcDTW_tb.vcd");
+    analyze_stacks(&mut session, spf.map(|project| ("Test.spf", project))).unwrap()
+}
+
+fn project(data_stack_size: u32) -> SpfProject {
+    SpfProject::parse(&format!(
+        r#"{{
+            "projectName": "Test",
+            "processors": [
+                {{ "name": "Other", "dataStackSize": 64, "instructionStackSize": 64 }},
+                {{ "name": "ProcDTW", "dataStackSize": {}, "instructionStackSize": 5 }}
+            ],
+            "settings": {{ "defaultProcessor": "Other" }}
+        }}"#,
+        data_stack_size
+    ))
+    .unwrap()
+}
+
+#[test]
+fn finds_the_peak_depth() {
+    let analysis = analyze(None);
+    assert_eq!(analysis.stacks.len(), 1);
+    let stack = &analysis.stacks[0];
+    assert_eq!(stack.kind, SignalKind::DataStackPointer);
+    assert_eq!(stack.path, POINTER);
+    assert_eq!(stack.processor.as_deref(), Some("ProcDTW"));
+    assert_eq!((stack.peak, stack.peak_time), (Some(1), Some(135000)));
+}
+
+#[test]
+fn falls_back_to_the_program_directives() {
+    let stack = &analyze(None).stacks[0];
+    assert_eq!(stack.configured, Some(5));
+    assert!(stack
+        .configured_from
+        .as_deref()
+        .is_some_and(|file| file.ends_with("trad_cmm.txt")));
+    assert!(stack.warning.as_deref().unwrap().contains("much larger"));
+}
+
+#[test]
+fn compares_against_the_project_file() {
+    let fitting = project(2);
+    let stack = &analyze(Some(&fitting)).stacks[0];
+    assert_eq!(stack.configured, Some(2));
+    assert_eq!(stack.configured_from.as_deref(), Some("Test.spf"));
+    assert_eq!(stack.warning, None);
+
+    let too_small = project(0);
+    let stack = &analyze(Some(&too_small)).stacks[0];
+    assert!(stack.warning.as_deref().unwrap().contains("too small"));
+}
+
+#[test]
+fn reports_raised_stack_flags() {
+    let flags = analyze(None).flags;
+    assert_eq!(flags.len(), 1);
+    assert!(flags[0].path.ends_with("sp.fl_max"));
+    assert_eq!((flags[0].start, flags[0].end), (135000, 200_000_000));
+    assert!(!flags.iter().any(|flag| flag.path.ends_with("fl_full")));
+}
+
+#[test]
+fn picks_the_processor_of_a_signal() {
+    assert_eq!(processor_instance(POINTER), Some("ProcDTW"));
+    assert_eq!(processor_instance("ProcDTW_tb.proc.valr2"), None);
+    assert_eq!(processor_instance("tb.p_x"), None);
+
+    let project = project(8);
+    assert_eq!(project.processor(Some("ProcDTW")).unwrap().name, "ProcDTW");
+    assert_eq!(project.processor(Some("Missing")).unwrap().name, "Other");
+    assert_eq!(project.processor(None).unwrap().name, "Other");
+
+    let old = SpfProject::parse(r#"{ "processors": [{ "name": "Only" }] }"#).unwrap();
+    assert_eq!(old.processor(Some("ProcDTW")).unwrap().name, "Only");
+    assert_eq!(old.processors[0].data_stack_size, None);
+    assert!(SpfProject::parse("{ not json").is_err());
+}
+
+#[test]
+fn pointers_beside_the_instance_belong_to_its_processor() {
+    let path = scratch_file("pointer_beside_instance.vcd");
+    let vcd = "$timescale 1ns $end
+$scope module tb $end
+$scope module proc $end
+$scope module p_X $end
+$var wire 8 a valr2 $end
+$upscope $end
+$scope module sp $end
+$var wire 4 b pointeri $end
+$upscope $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+b0 a
+b0 b
+#10
+b11 b
+";
+    std::fs::write(&path, vcd).unwrap();
+    let mut session = open_file(&path);
+    let analysis = analyze_stacks(&mut session, None).unwrap();
+    let stack = &analysis.stacks[0];
+    assert_eq!(stack.path, "tb.proc.sp.pointeri");
+    assert_eq!(stack.processor.as_deref(), Some("X"));
+    assert_eq!(stack.peak, Some(3));
+}
@@ -0,0 +1,245 @@
+//! Symbol tables: the `trad_*.txt` files next to a dump, the ones a project
+//! file declares, and value labels attached to signals.
+
+mod common;
+
+use common::{open_example, open_file, scratch_file};
+use polaris_lib::sapho_float::FloatFormat;
+use polaris_lib::spf::SpfProject;
+use polaris_lib::translation::{load_symbol_tables, TranslationTables};
+use polaris_lib::trigger::{find_triggers, TriggerMatch};
+use std::path::PathBuf;
+
+/// Two processors, each with its `valr2` beside its `p_<name>` instance.
+const TWO_PROCESSORS: &str = "\
+$timescale 1ns $end
+$scope module tb $end
+$scope module proc_a $end
+$var reg 4 ! valr2 [3:0] $end
+$scope module p_A $end
+$var reg 1 # x $end
+$upscope $end
+$upscope $end
+$scope module proc_b $end
+$var reg 4 $ valr2 [3:0] $end
+$scope module p_B $end
+$var reg 1 % x $end
+$upscope $end
+$upscope $end
+$upscope $end
+$enddefinitions $end
+#0
+b0001 !
+b0001 $
+0#
+0%
+#10
+b0010 !
+b0010 $
+#20
+1#
+";
+
+/// A project folder holding `files`, with `Test.spf` declaring `processor`.
+fn project(name: &str, processor: &str, files: &[(&str, &str)]) -> (PathBuf, SpfProject) {
+    let dir = scratch_file(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, text) in files {
+        std::fs::write(dir.join(file), text).unwrap();
+    }
+    let spf = format!(
+        r#"{{ "projectName": "Test", "processors": [{}] }}"#,
+        processor
+    );
+    let spf_path = dir.join("Test.spf");
+    std::fs::write(&spf_path, &spf).unwrap();
+    (spf_path, SpfProject::parse(&spf).unwrap())
+}
+
+#[test]
+fn parses_tables_and_reports_bad_lines() {
+    let (opcodes, errors) =
+        TranslationTables::parse_opcodes("0 NOP\n18 OUT 2\n\nheader\n18 LOD 1\n19\n", "op.txt");
+    assert_eq!(opcodes.len(), 2);
+    assert_eq!(opcodes[&18].mnemonic, "OUT");
+    assert_eq!(opcodes[&18].operand.as_deref(), Some("2"));
+    assert_eq!(opcodes[&0].operand, None);
+    let lines: Vec<usize> = errors.iter().map(|error| error.line).collect();
+    assert_eq!(lines, vec![4, 5, 6]);
+    assert!(
+        errors[1].message.contains("line 2"),
+        "{}",
+        errors[1].message
+    );
+    assert!(errors.iter().all(|error| error.file == "op.txt"));
+
+    let (lines, errors) =
+        TranslationTables::parse_source_lines("-2 void main()\n3 x = 1;\n9\n", "c");
+    assert!(errors.is_empty());
+    assert_eq!(lines[&-2], "void main()");
+    assert_eq!(lines[&9], "");
+
+    let (labels, errors) = TranslationTables::parse_enum("0 IDLE\n0x2 FETCH\n0b11 EXEC\n4\n", "s");
+    assert_eq!(labels[&2], "FETCH");
+    assert_eq!(labels[&3], "EXEC");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, 4);
+}
+
+#[test]
+fn sessions_load_the_tables_next_to_the_dump() {
+    let session = open_example("ProcDTW_tb.vcd");
+    let processors: Vec<_> = session.symbols.keys().cloned().collect();
+    assert_eq!(processors, vec![Some("ProcDTW".to_string())]);
+
+    // valr2 is declared beside the p_ProcDTW instance, not inside it
+    let tables = session.symbols_for("ProcDTW_tb.proc.valr2");
+    assert_eq!(tables.opcodes.len(), 274);
+    assert!(tables.errors.is_empty(), "{:?}", tables.errors);
+    assert!(tables
+        .cmm_file
+        .as_deref()
+        .is_some_and(|file| file.ends_with("trad_cmm.txt")));
+    assert_eq!(tables.directive("#PRNAME"), Some("ProcDTW"));
+
+    assert!(tables.warnings.is_empty(), "{:?}", tables.warnings);
+
+    // Missing tables are not errors, but each is named in a warning
+    let missing = TranslationTables::load("/nonexistent/wave.vcd");
+    assert!(missing.opcodes.is_empty() && missing.errors.is_empty());
+    let files: Vec<&str> = missing.warnings.iter().map(|w| w.file.as_str()).collect();
+    assert_eq!(
+        files,
+        ["/nonexistent/trad_opcode.txt", "/nonexistent/trad_cmm.txt"]
+    );
+    // A waveform without a processor needs no tables
+    let session = open_example("basic.vcd");
+    assert!(session
+        .symbols
+        .values()
+        .all(|tables| tables.warnings.is_empty()));
+}
+
+#[test]
+fn project_files_declare_tables() {
+    let (spf_path, spf) = project(
+        "symbols_declared",
+        r#"{
+            "name": "ProcDTW",
+            "mantissaBits": 16,
+            "exponentBits": 6,
+            "translation": { "opcodes": "ops.txt", "sourceLines": "missing.txt" },
+            "enums": [
+                { "signal": "sp.fl_max", "file": "flags.txt" },
+                { "signal": "no.such.signal", "file": "flags.txt" }
+            ]
+        }"#,
+        &[
+            ("ops.txt", "0 NOP\n1 LOD 75\n2\n"),
+            ("flags.txt", "0 EMPTY\n1 ONE\n"),
+        ],
+    );
+    let session = open_example("ProcDTW_tb.vcd");
+    let symbols = load_symbol_tables(&session, Some((spf_path.to_str().unwrap(), &spf)));
+    let tables = &symbols[&Some("ProcDTW".to_string())];
+
+    assert_eq!(tables.opcodes.len(), 2);
+    assert!(tables.opcode_file.as_deref().unwrap().ends_with("ops.txt"));
+    assert!(tables.source_lines.is_empty());
+    assert_eq!(tables.float_format, FloatFormat::new(16, 6).ok());
+
+    let labels = &tables.enums["ProcDTW_tb.proc.p_ProcDTW.core.sp.fl_max"];
+    assert_eq!(labels[&1], "ONE");
+
+    let errors: Vec<(String, usize)> = tables
+        .errors
+        .iter()
+        .map(|error| {
+            let file = PathBuf::from(&error.file);
+            (
+                file.file_name().unwrap().to_string_lossy().to_string(),
+                error.line,
+            )
+        })
+        .collect();
+    assert!(errors.contains(&("ops.txt".to_string(), 3)), "{:?}", errors);
+    assert!(
+        errors.contains(&("missing.txt".to_string(), 0)),
+        "{:?}",
+        errors
+    );
+    assert!(tables
+        .errors
+        .iter()
+        .any(|error| error.message.contains("no.such.signal")));
+}
+
+#[test]
+fn undeclared_tables_fall_back_to_the_dump_folder() {
+    let (spf_path, spf) = project("symbols_fallback", r#"{ "name": "ProcDTW" }"#, &[]);
+    let session = open_example("ProcDTW_tb.vcd");
+    let symbols = load_symbol_tables(&session, Some((spf_path.to_str().unwrap(), &spf)));
+    let tables = &symbols[&Some("ProcDTW".to_string())];
+    assert_eq!(tables.opcodes.len(), 274);
+    assert!(tables.errors.is_empty());
+}
+
+#[test]
+fn each_processor_has_its_own_tables() {
+    let (spf_path, spf) = project(
+        "symbols_per_processor",
+        r#"{ "name": "A", "translation": { "opcodes": "a.txt" } },
+           { "name": "B", "translation": { "opcodes": "b.txt" } }"#,
+        &[
+            ("a.txt", "1 OUT 2\n2 LOD 1\n"),
+            ("b.txt", "1 LOD 1\n2 OUT 2\n"),
+            ("two.vcd", TWO_PROCESSORS),
+        ],
+    );
+    let mut session = open_file(&spf_path.with_file_name("two.vcd"));
+    session.symbols = load_symbol_tables(&session, Some((spf_path.to_str().unwrap(), &spf)));
+
+    let mut processors: Vec<_> = session.symbols.keys().cloned().collect();
+    processors.sort();
+    assert_eq!(
+        processors,
+        vec![Some("A".to_string()), Some("B".to_string())]
+    );
+    assert_eq!(
+        session.symbols_for("tb.proc_a.valr2").opcodes[&1].mnemonic,
+        "OUT"
+    );
+    assert_eq!(
+        session.symbols_for("tb.proc_b.valr2").opcodes[&1].mnemonic,
+        "LOD"
+    );
+
+    // A mnemonic stands for the addresses of the processor it is compared in
+    let outs = |session: &mut _, condition| find_triggers(session, condition, None, None, None);
+    assert_eq!(
+        outs(&mut session, "tb.proc_a.valr2 == OUT").unwrap(),
+        vec![TriggerMatch { start: 0, end: 10 }]
+    );
+    assert_eq!(
+        outs(&mut session, "tb.proc_b.valr2 == OUT").unwrap(),
+        vec![TriggerMatch { start: 10, end: 20 }]
+    );
+}
+
+#[test]
+fn labels_attach_to_a_path_suffix() {
+    let mut tables = TranslationTables::default();
+    let paths = vec![
+        "tb.core.state".to_string(),
+        "tb.core.next_state".to_string(),
+    ];
+    assert!(tables
+        .attach_enum(&paths, "state", "0 IDLE\n", "s")
+        .is_empty());
+    assert!(tables.enums.contains_key("tb.core.state"));
+    assert!(!tables.enums.contains_key("tb.core.next_state"));
+
+    let errors = tables.attach_enum(&paths, "ate", "0 IDLE\n", "s");
+    assert_eq!(errors.len(), 1);
+}
@@ -0,0 +1,126 @@
+//! Trigger intervals over `examples/basic.vcd` and opcode mnemonics over the
+//! DTW processor testbench.
+
+mod common;
+
+use common::{example, open_example, value_at};
+use polaris_lib::translation::TranslationTables;
+use polaris_lib::trigger::{find_triggers, TriggerMatch};
+
+fn interval(start: u64, end: u64) -> TriggerMatch {
+    TriggerMatch { start, end }
+}
+
+#[test]
+fn intervals_last_until_the_condition_changes() {
+    let mut session = open_example("basic.vcd");
+    let end = session.hierarchy(0).time_range.end;
+    assert_eq!(
+        find_triggers(&mut session, "vector > 4", None, None, None).unwrap(),
+        vec![interval(5, end)]
+    );
+    assert_eq!(
+        find_triggers(&mut session, "vector == 2 || vector == 4", None, None, None).unwrap(),
+        vec![interval(2, 3), interval(4, 5)]
+    );
+}
+
+#[test]
+fn unknown_values_never_trigger() {
+    let mut session = open_example("basic.vcd");
+    let end = session.hierarchy(0).time_range.end;
+    // x from 0 to 2ns and from 3ns to 4ns
+    assert_eq!(
+        find_triggers(
+            &mut session,
+            "!one_transition || vector == 2",
+            None,
+            None,
+            None
+        )
+        .unwrap(),
+        vec![interval(2, 3), interval(4, end)]
+    );
+}
+
+#[test]
+fn matches_are_clipped_and_limited() {
+    let mut session = open_example("basic.vcd");
+    let condition = "vector == 2 || vector == 4 || vector > 6";
+    assert_eq!(
+        find_triggers(&mut session, condition, Some(3), Some(7), None).unwrap(),
+        vec![interval(4, 5), interval(7, 7)]
+    );
+    assert_eq!(
+        find_triggers(&mut session, condition, Some(2), None, Some(1)).unwrap(),
+        vec![interval(2, 3)]
+    );
+    assert_eq!(
+        find_triggers(&mut session, condition, Some(3), None, Some(1)).unwrap(),
+        vec![interval(4, 5)]
+    );
+}
+
+#[test]
+fn matches_in_progress_at_the_start_are_skipped() {
+    let mut session = open_example("basic.vcd");
+    let end = session.hierarchy(0).time_range.end;
+    let condition = "vector == 2 || vector > 4";
+    assert_eq!(
+        find_triggers(&mut session, condition, Some(5), None, None).unwrap(),
+        vec![interval(5, end)]
+    );
+    // vector has been above 4 since 5ns, so there is nothing left to find
+    assert_eq!(
+        find_triggers(&mut session, condition, Some(6), None, None).unwrap(),
+        vec![]
+    );
+    // Searching from inside the first match finds the next one
+    let condition = "vector == 2 || vector == 3 || vector > 4";
+    assert_eq!(
+        find_triggers(&mut session, condition, Some(3), None, Some(1)).unwrap(),
+        vec![interval(5, end)]
+    );
+}
+
+#[test]
+fn opcode_mnemonics_match_any_of_their_addresses() {
+    let path = example("ProcDTW_tb.vcd");
+    let symbols = TranslationTables::load(path.to_str().unwrap()).mnemonic_addresses();
+    assert!(symbols["OUT"].contains(&18));
+
+    let mut session = open_example("ProcDTW_tb.vcd");
+    let all = find_triggers(&mut session, "valr2 == OUT", None, None, None).unwrap();
+    assert!(!all.is_empty());
+
+    let hierarchy = session.hierarchy(0);
+    let valr2 = common::all_vars(&hierarchy)
+        .into_iter()
+        .find(|var| var.name == "valr2")
+        .unwrap()
+        .signal_ref;
+    let changes = session.signal_changes(valr2).unwrap();
+    for m in &all {
+        let bits = value_at(&changes, m.start).unwrap();
+        let address = u32::from_str_radix(bits, 2).unwrap() as i32 as i64;
+        assert!(symbols["OUT"].contains(&address), "{:?}", m);
+    }
+
+    // The next OUT after the first one has started
+    let from = all[0].start + 1;
+    let next = find_triggers(&mut session, "valr2 == OUT", Some(from), None, Some(1)).unwrap();
+    assert_eq!(next, vec![all[1].clone()]);
+
+    let error =
+        find_triggers(&mut session, "valr2 == NOT_AN_OPCODE", None, None, None).unwrap_err();
+    assert!(error.contains("NOT_AN_OPCODE"), "{}", error);
+
+    // Two mnemonics are never compared with each other
+    let error = find_triggers(&mut session, "clk && OUT == LOD", None, None, None).unwrap_err();
+    assert!(
+        error.contains("compare a signal with a mnemonic"),
+        "{}",
+        error
+    );
+    assert!(find_triggers(&mut session, "valr2 == OUT", None, None, Some(1)).is_ok());
+}
@@ -0,0 +1,108 @@
+//! Export the example waveforms with `write_vcd`, read the VCD back with
+//! wellen and check it against the source hierarchy and the GTKWave dumps.
+
+mod common;
+
+use common::{
+    compare_hierarchy_with_dump, compare_values_with_dump, example, open_file, read_dump,
+    scratch_file,
+};
+use polaris_lib::vcd_writer::export_vcd;
+use polaris_lib::waveform::WaveformSession;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use wellen::Hierarchy;
+
+fn export(name: &str) -> (WaveformSession, WaveformSession) {
+    let src = example(name);
+    let dst = scratch_file(&format!("{}.roundtrip.vcd", name));
+    let mut progress = Vec::new();
+    export_vcd(
+        src.to_str().unwrap(),
+        dst.to_str().unwrap(),
+        &AtomicBool::new(false),
+        |percent| progress.push(percent),
+    )
+    .expect("Failed to export VCD");
+    assert_eq!(progress.last(), Some(&100));
+
+    let original = open_file(&src);
+    let exported = open_file(&dst);
+    (original, exported)
+}
+
+fn scope_types(hierarchy: &Hierarchy) -> Vec<(String, String)> {
+    hierarchy
+        .all_scopes()
+        .map(|scope_ref| {
+            let scope = &hierarchy[scope_ref];
+            (
+                scope.full_name(hierarchy),
+                format!("{:?}", scope.scope_type()),
+            )
+        })
+        .collect()
+}
+
+fn var_types(hierarchy: &Hierarchy) -> Vec<(String, String)> {
+    hierarchy
+        .all_vars()
+        .map(|var_ref| {
+            let var = &hierarchy[var_ref];
+            (
+                var.full_name(hierarchy),
+                format!(
+                    "{:?} {:?} {:?} {:?}",
+                    var.var_type(),
+                    var.index(),
+                    var.length(hierarchy),
+                    var.vhdl_type_name(hierarchy)
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Full var names grouped by the signal they share.
+fn alias_groups(hierarchy: &Hierarchy) -> Vec<Vec<String>> {
+    let mut groups: HashMap<_, Vec<String>> = HashMap::new();
+    for var_ref in hierarchy.all_vars() {
+        let var = &hierarchy[var_ref];
+        groups
+            .entry(var.signal_ref())
+            .or_default()
+            .push(var.full_name(hierarchy));
+    }
+    let mut groups: Vec<_> = groups.into_values().collect();
+    groups.sort();
+    groups
+}
+
+fn check_roundtrip(name: &str) {
+    let (original, mut exported) = export(name);
+    let (before, after) = (original.waveform.hierarchy(), exported.waveform.hierarchy());
+
+    assert_eq!(scope_types(before), scope_types(after));
+    assert_eq!(var_types(before), var_types(after));
+    assert_eq!(alias_groups(before), alias_groups(after));
+
+    let dump = read_dump(&format!("{}.dump", name));
+    let mut errors = compare_hierarchy_with_dump(&exported.hierarchy(0), &dump);
+    errors.extend(compare_values_with_dump(&mut exported, &dump));
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+#[test]
+fn basic_vcd_roundtrip() {
+    check_roundtrip("basic.vcd");
+}
+
+#[test]
+fn basic_fst_roundtrip() {
+    check_roundtrip("basic.fst");
+}
+
+#[test]
+fn basic_ghw_roundtrip() {
+    check_roundtrip("basic.ghw");
+}
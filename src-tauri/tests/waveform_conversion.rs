@@ -0,0 +1,126 @@
+//! Load every example through `WaveformSession` and check what the viewer
+//! would receive: the basic files against their GTKWave dumps and the
+//! processor testbenches against their own VCD export.
+
+mod common;
+
+use common::{
+    all_vars, compare_hierarchy_with_dump, compare_values_with_dump, example, open_example,
+    open_file, read_dump, scratch_file,
+};
+use polaris_lib::vcd_writer::export_vcd;
+use polaris_lib::waveform::WaveformSession;
+use std::sync::atomic::AtomicBool;
+
+fn check_against_dump(name: &str) {
+    let mut session = open_example(name);
+    let dump = read_dump(&format!("{}.dump", name));
+
+    let mut errors = compare_hierarchy_with_dump(&session.hierarchy(0), &dump);
+    errors.extend(compare_values_with_dump(&mut session, &dump));
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+}
+
+/// Every var of a session, by path, with its changes as strings.
+fn all_changes(session: &mut WaveformSession) -> Vec<(String, Vec<u64>, Vec<String>)> {
+    let hierarchy = session.hierarchy(0);
+    all_vars(&hierarchy)
+        .into_iter()
+        .map(|var| {
+            let changes = session.signal_changes(var.signal_ref).unwrap();
+            (var.path.clone(), changes.times, changes.values)
+        })
+        .collect()
+}
+
+fn check_design(name: &str, timescale: &str) {
+    let mut session = open_example(name);
+    let hierarchy = session.hierarchy(0);
+    assert_eq!(hierarchy.file_format, "vcd");
+    assert_eq!(hierarchy.timescale, timescale);
+    assert!(!all_vars(&hierarchy).is_empty());
+
+    let widths: Vec<(String, String, u32)> = all_vars(&hierarchy)
+        .into_iter()
+        .map(|var| (var.path.clone(), var.var_type.clone(), var.width))
+        .collect();
+    let changes = all_changes(&mut session);
+    for ((path, var_type, width), (_, times, values)) in widths.iter().zip(&changes) {
+        assert!(
+            times.windows(2).all(|pair| pair[0] < pair[1]),
+            "{}: times are not strictly increasing",
+            path
+        );
+        assert!(times
+            .iter()
+            .all(|t| (hierarchy.time_range.start..=hierarchy.time_range.end).contains(t)));
+        if !matches!(var_type.as_str(), "real" | "string" | "event") {
+            assert!(
+                values.iter().all(|value| value.len() == *width as usize),
+                "{}: value width does not match {}",
+                path,
+                width
+            );
+        }
+    }
+
+    let dst = scratch_file(&format!("{}.conversion.vcd", name));
+    export_vcd(
+        example(name).to_str().unwrap(),
+        dst.to_str().unwrap(),
+        &AtomicBool::new(false),
+        |_| {},
+    )
+    .expect("Failed to export VCD");
+    let mut exported = open_file(&dst);
+    assert_eq!(exported.hierarchy(0).timescale, timescale);
+    assert_eq!(all_changes(&mut exported), changes);
+}
+
+#[test]
+fn basic_vcd_matches_dump() {
+    check_against_dump("basic.vcd");
+}
+
+#[test]
+fn basic_fst_matches_dump() {
+    check_against_dump("basic.fst");
+}
+
+#[test]
+fn basic_ghw_matches_dump() {
+    check_against_dump("basic.ghw");
+}
+
+#[test]
+fn basic_vcd_and_fst_agree() {
+    let mut vcd = open_example("basic.vcd");
+    let mut fst = open_example("basic.fst");
+    assert_eq!(vcd.hierarchy(0).timescale, fst.hierarchy(0).timescale);
+
+    // fstWriter dropped the changes of the last VCD time step, as the dumps show
+    let end = fst.hierarchy(0).time_range.end;
+    let before_end = |changes: Vec<(String, Vec<u64>, Vec<String>)>| {
+        changes
+            .into_iter()
+            .map(|(path, times, values)| {
+                let count = times.partition_point(|&t| t < end);
+                (path, times[..count].to_vec(), values[..count].to_vec())
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        before_end(all_changes(&mut vcd)),
+        before_end(all_changes(&mut fst))
+    );
+}
+
+#[test]
+fn proc_dtw_testbench_converts() {
+    check_design("ProcDTW_tb.vcd", "1ps");
+}
+
+#[test]
+fn proc_fft_testbench_converts() {
+    check_design("proc_fft_tb_complex.vcd", "1ps");
+}
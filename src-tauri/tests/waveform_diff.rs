@@ -0,0 +1,251 @@
+//! Compare the processor testbenches against edited copies of themselves.
+
+mod common;
+
+use common::{example, open_file, scratch_file};
+use polaris_lib::sapho_float::{FloatFormat, SaphoFloat};
+use polaris_lib::spf::SpfProject;
+use polaris_lib::translation::load_symbol_tables;
+use polaris_lib::waveform_diff::{
+    compare_waveforms, DiffOptions, MatchedBy, SignalMapping, WaveformDiff,
+};
+
+/// Write `name` with `edit` applied to its text and compare the original
+/// against the copy.
+fn compare_with_edit(
+    name: &str,
+    edit: impl Fn(&str) -> String,
+    signal_map: &[SignalMapping],
+    options: &DiffOptions,
+) -> WaveformDiff {
+    let src = example(name);
+    let dst = scratch_file(&format!("{}.edited.{}.vcd", name, signal_map.len()));
+    let text = std::fs::read_to_string(&src).unwrap();
+    std::fs::write(&dst, edit(&text)).unwrap();
+    compare_waveforms(
+        &mut open_file(&src),
+        &mut open_file(&dst),
+        signal_map,
+        options,
+    )
+    .unwrap()
+}
+
+/// Replace the value of the `nth` vector change of signal `id` and return
+/// the time of that change.
+fn replace_change(text: &str, id: &str, nth: usize, value: &str) -> (String, u64) {
+    let mut time = 0;
+    let mut seen = 0;
+    let mut found = None;
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            if let Some(t) = line.strip_prefix('#') {
+                time = t.parse().unwrap();
+            }
+            if line.starts_with('b') && line.ends_with(&format!(" {}", id)) {
+                seen += 1;
+                if seen == nth {
+                    found = Some(time);
+                    return format!("b{} {}", value, id);
+                }
+            }
+            line.to_string()
+        })
+        .collect();
+    (lines.join("\n") + "\n", found.unwrap())
+}
+
+#[test]
+fn identical_runs_have_no_mismatches() {
+    let path = example("ProcDTW_tb.vcd");
+    let diff = compare_waveforms(
+        &mut open_file(&path),
+        &mut open_file(&path),
+        &[],
+        &DiffOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(diff.signals.len(), 26);
+    assert!(diff.signals.iter().all(|s| s.matched_by == MatchedBy::Path));
+    assert!(diff.only_in_a.is_empty() && diff.only_in_b.is_empty());
+    assert!(diff.mismatches.is_empty());
+    assert_eq!(diff.end_a, diff.end_b);
+}
+
+#[test]
+fn changed_output_is_reported_from_its_time() {
+    let (_, time) = replace_change(
+        &std::fs::read_to_string(example("ProcDTW_tb.vcd")).unwrap(),
+        "(",
+        1,
+        "111",
+    );
+    let diff = compare_with_edit(
+        "ProcDTW_tb.vcd",
+        |text| replace_change(text, "(", 1, "111").0,
+        &[],
+        &DiffOptions::default(),
+    );
+
+    let differing: Vec<&str> = diff
+        .signals
+        .iter()
+        .filter(|s| s.mismatches > 0)
+        .map(|s| s.path_a.as_str())
+        .collect();
+    assert_eq!(differing, vec!["ProcDTW_tb.proc.out_sig_0"]);
+    let mismatch = &diff.mismatches[0];
+    assert_eq!(mismatch.start, time);
+    assert!(mismatch.end > time);
+    assert_eq!(
+        mismatch.value_b.as_deref(),
+        Some("00000000000000000000000000000111")
+    );
+    assert_ne!(mismatch.value_a, mismatch.value_b);
+}
+
+#[test]
+fn renamed_scopes_are_paired_by_class_or_mapping() {
+    let rename = |text: &str| text.replace("module ProcDTW_tb ", "module ProcDTW_run ");
+    let diff = compare_with_edit("ProcDTW_tb.vcd", rename, &[], &DiffOptions::default());
+
+    let by_class = diff
+        .signals
+        .iter()
+        .filter(|s| s.matched_by == MatchedBy::Class)
+        .count();
+    assert_eq!(by_class, diff.signals.len());
+    assert!(diff
+        .signals
+        .iter()
+        .any(|s| s.path_a == "ProcDTW_tb.proc.me1_f_global_v_k_e_"
+            && s.path_b == "ProcDTW_run.proc.me1_f_global_v_k_e_"));
+    // Core flags are too generic to pair by name
+    assert!(diff
+        .only_in_a
+        .contains(&"ProcDTW_tb.proc.p_ProcDTW.core.sp.fl_max".to_string()));
+    assert_eq!(diff.only_in_a.len(), diff.only_in_b.len());
+    assert!(diff.mismatches.is_empty());
+
+    let signal_map: Vec<SignalMapping> = diff
+        .only_in_a
+        .iter()
+        .map(|a| SignalMapping {
+            a: a.clone(),
+            b: a.replacen("ProcDTW_tb", "ProcDTW_run", 1),
+        })
+        .collect();
+    let mapped = compare_with_edit(
+        "ProcDTW_tb.vcd",
+        rename,
+        &signal_map,
+        &DiffOptions::default(),
+    );
+    assert!(mapped.only_in_a.is_empty() && mapped.only_in_b.is_empty());
+    assert_eq!(
+        mapped
+            .signals
+            .iter()
+            .filter(|s| s.matched_by == MatchedBy::Mapping)
+            .count(),
+        signal_map.len()
+    );
+    assert!(mapped.mismatches.is_empty());
+}
+
+#[test]
+fn complex_values_are_equal_within_the_tolerance() {
+    // Flip the last mantissa bit of the imaginary part
+    let edit = |text: &str| {
+        text.replacen(
+            "b10000000001100110011101000000000000001000000000000000000000 \"",
+            "b10000000001100110011101000000000000001000000000000000000001 \"",
+            1,
+        )
+    };
+    let exact = compare_with_edit(
+        "proc_fft_tb_complex.vcd",
+        edit,
+        &[],
+        &DiffOptions::default(),
+    );
+    assert_eq!(exact.mismatches.len(), 1);
+    assert!(exact.mismatches[0]
+        .value_a
+        .as_deref()
+        .unwrap()
+        .contains(" + j "));
+
+    let options = DiffOptions {
+        relative_tolerance: 1e-3,
+        ..DiffOptions::default()
+    };
+    let tolerant = compare_with_edit("proc_fft_tb_complex.vcd", edit, &[], &options);
+    assert!(tolerant.mismatches.is_empty());
+}
+
+#[test]
+fn mapping_to_a_missing_signal_fails() {
+    let path = example("basic.vcd");
+    let signal_map = [SignalMapping {
+        a: "variables.bit".to_string(),
+        b: "variables.missing".to_string(),
+    }];
+    let error = compare_waveforms(
+        &mut open_file(&path),
+        &mut open_file(&path),
+        &signal_map,
+        &DiffOptions::default(),
+    )
+    .unwrap_err();
+    assert!(error.contains("variables.missing"), "{}", error);
+}
+
+#[test]
+fn floats_are_read_in_the_format_of_their_processor() {
+    let (small, wide) = (
+        FloatFormat::new(5, 3).unwrap(),
+        FloatFormat::new(8, 4).unwrap(),
+    );
+    let bits = |value, format| {
+        SaphoFloat::from_f64(value, format)
+            .unwrap()
+            .encode(format)
+            .unwrap()
+    };
+    let run = |name: &str, value| {
+        let vcd = format!(
+            "$timescale 1ns $end\n$scope module tb $end\n\
+             $scope module p_A $end\n$var wire 16 ! me2_f_main_v_x_e_ $end\n$upscope $end\n\
+             $scope module p_B $end\n$var wire 16 \" me2_f_main_v_x_e_ $end\n$upscope $end\n\
+             $upscope $end\n$enddefinitions $end\n#0\nb{} !\nb{} \"\n#4\n",
+            bits(value, small),
+            bits(value, wide)
+        );
+        let path = scratch_file(name);
+        std::fs::write(&path, vcd).unwrap();
+        path
+    };
+    let (path_a, path_b) = (run("float_run_a.vcd", 1.5), run("float_run_b.vcd", 2.5));
+
+    let spf = r#"{ "processors": [
+        { "name": "A", "mantissaBits": 5, "exponentBits": 3 },
+        { "name": "B", "mantissaBits": 8, "exponentBits": 4 }
+    ] }"#;
+    let spf_path = scratch_file("float_runs.spf");
+    std::fs::write(&spf_path, spf).unwrap();
+    let project = SpfProject::parse(spf).unwrap();
+    let spf = Some((spf_path.to_str().unwrap(), &project));
+    let (mut a, mut b) = (open_file(&path_a), open_file(&path_b));
+    a.symbols = load_symbol_tables(&a, spf);
+    b.symbols = load_symbol_tables(&b, spf);
+
+    let diff = compare_waveforms(&mut a, &mut b, &[], &DiffOptions::default()).unwrap();
+    let values: Vec<_> = diff
+        .mismatches
+        .iter()
+        .map(|m| (m.value_a.as_deref(), m.value_b.as_deref()))
+        .collect();
+    assert_eq!(values, [(Some("1.5"), Some("2.5")); 2]);
+}
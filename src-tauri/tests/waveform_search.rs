@@ -0,0 +1,160 @@
+//! Navigation searches over `examples/basic.vcd`, whose `vector` counts
+//! 0..8 once per ns and jumps to 0xf8 at 9ns.
+
+mod common;
+
+use common::{all_vars, open_example, open_file, scratch_file};
+use polaris_lib::waveform::WaveformSession;
+use polaris_lib::waveform_search::{find_edge, find_transition, find_value, MAX_CACHED_CONDITIONS};
+
+fn open_basic() -> (WaveformSession, impl Fn(&str) -> usize) {
+    let session = open_example("basic.vcd");
+    let refs: Vec<(String, usize)> = all_vars(&session.hierarchy(0))
+        .into_iter()
+        .map(|var| (var.path.clone(), var.signal_ref))
+        .collect();
+    let signal = move |path: &str| {
+        refs.iter()
+            .find(|(candidate, _)| candidate == path)
+            .map(|(_, signal_ref)| *signal_ref)
+            .unwrap()
+    };
+    (session, signal)
+}
+
+#[test]
+fn transitions() {
+    let (mut session, signal) = open_basic();
+    let vector = signal("variables.vector");
+    let one_transition = signal("variables.one_transition");
+
+    assert_eq!(find_transition(&mut session, vector, 4, true), Ok(Some(5)));
+    assert_eq!(find_transition(&mut session, vector, 4, false), Ok(Some(3)));
+    assert_eq!(find_transition(&mut session, vector, 9, true), Ok(None));
+    assert_eq!(find_transition(&mut session, vector, 0, false), Ok(None));
+    assert_eq!(
+        find_transition(&mut session, one_transition, 0, true),
+        Ok(Some(4))
+    );
+    assert_eq!(
+        find_transition(&mut session, one_transition, 4, false),
+        Ok(None)
+    );
+}
+
+#[test]
+fn edges_follow_the_least_significant_bit() {
+    let (mut session, signal) = open_basic();
+    let vector = signal("variables.vector");
+    let bit = signal("variables.bit");
+
+    assert_eq!(find_edge(&mut session, vector, 3, true, true), Ok(Some(5)));
+    assert_eq!(find_edge(&mut session, vector, 3, true, false), Ok(Some(1)));
+    assert_eq!(find_edge(&mut session, vector, 3, false, true), Ok(Some(4)));
+    assert_eq!(find_edge(&mut session, vector, 8, false, true), Ok(None));
+    // `bit` only passes through 0 and 1 next to x/z/u/w, which are not edges
+    assert_eq!(find_edge(&mut session, bit, 0, true, true), Ok(None));
+}
+
+#[test]
+fn values() {
+    let (mut session, signal) = open_basic();
+    let vector = signal("variables.vector");
+    let integer = signal("variables.integer");
+
+    assert_eq!(
+        find_value(&mut session, vector, 0, "0x5", true, true),
+        Ok(Some(5))
+    );
+    assert_eq!(
+        find_value(&mut session, vector, 0, "b101", true, true),
+        Ok(Some(5))
+    );
+    assert_eq!(
+        find_value(&mut session, vector, 0, "6", true, true),
+        Ok(Some(6))
+    );
+    assert_eq!(
+        find_value(&mut session, vector, 7, "6", true, false),
+        Ok(Some(6))
+    );
+    assert_eq!(
+        find_value(&mut session, integer, 0, "0xfffffff8", true, true),
+        Ok(Some(9))
+    );
+    assert_eq!(
+        find_value(
+            &mut session,
+            signal("variables.string"),
+            0,
+            "str-3",
+            true,
+            true
+        ),
+        Ok(Some(3))
+    );
+    assert_eq!(
+        find_value(&mut session, signal("variables.real"), 0, "2.5", true, true),
+        Ok(Some(2))
+    );
+
+    // Only the change that leaves 0 counts, later non-zero values do not
+    assert_eq!(
+        find_value(&mut session, vector, 0, "0", false, true),
+        Ok(Some(1))
+    );
+    assert_eq!(
+        find_value(&mut session, vector, 1, "0", false, true),
+        Ok(None)
+    );
+}
+
+#[test]
+fn cached_conditions_are_bounded_and_dropped_on_unload() {
+    let (mut session, signal) = open_basic();
+    let vector = signal("variables.vector");
+
+    for value in 0..2 * MAX_CACHED_CONDITIONS {
+        let value = value.to_string();
+        find_value(&mut session, vector, 0, &value, true, true).unwrap();
+    }
+    assert_eq!(session.search.cached_conditions(), MAX_CACHED_CONDITIONS);
+    // Searches still work after their condition was evicted
+    assert_eq!(
+        find_value(&mut session, vector, 0, "0x5", true, true),
+        Ok(Some(5))
+    );
+
+    find_edge(&mut session, signal("variables.bit"), 0, true, true).unwrap();
+    session.unload_signals(&[vector]).unwrap();
+    assert_eq!(session.search.cached_conditions(), 1);
+}
+
+#[test]
+fn text_starting_with_b_is_not_a_bit_pattern() {
+    let path = scratch_file("b_words.vcd");
+    let vcd = "$timescale 1ns $end
+$scope module tb $end
+$var string 0 ! state $end
+$upscope $end
+$enddefinitions $end
+#0
+sgood !
+#5
+sbad !
+#10
+sb1 !
+";
+    std::fs::write(&path, vcd).unwrap();
+    let mut session = open_file(&path);
+    let state = all_vars(&session.hierarchy(0))[0].signal_ref;
+    assert_eq!(
+        find_value(&mut session, state, 0, "bad", true, true),
+        Ok(Some(5))
+    );
+    // `b1` is a bit pattern, and no string holds bits
+    assert_eq!(
+        find_value(&mut session, state, 0, "b1", true, true),
+        Ok(None)
+    );
+}
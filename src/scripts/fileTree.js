@@ -4,7 +4,7 @@ import { state, addTab, getFocusedInstance } from './state.js';
 import { setEditorModel } from './monaco.js';
 import { renderInstanceTabs, switchTab, closeFileFromAllInstances } from './tabs.js';
 import { ensureEditorExists, updateSplitButtons } from './splitEditor.js';
-import { openWavetraceViewer } from './wavetrace.js';
+import { openWavetraceViewer, exportWaveformAsVcd } from './wavetrace.js';
 
 // File tree state
 let fileTreeItems = [];
@@ -160,7 +160,7 @@ function createTreeElements(items, level) {
         } else {
             const isSpfFile = item.name.endsWith('.spf');
             const isVcdFile = item.name.endsWith('.vcd');
-            const isFstFile = item.name.endsWith('.fst');
+            const isFstFile = item.name.endsWith('.fst') || item.name.endsWith('.ghw');
             
             element.classList.add('file');
             
@@ -243,6 +243,7 @@ function getFileIcon(fileName) {
         'sv': 'memory',
         'vcd': 'show_chart',
         'fst': 'show_chart',
+        'ghw': 'show_chart',
         'c': 'code',
         'cpp': 'code',
         'h': 'code',
@@ -320,8 +321,8 @@ async function openFile(item) {
     const filePath = item.path;
     const fileName = item.name;
 
-    // INTERCEPT VCD|FST FILES
-    if (fileName.endsWith('.vcd') || fileName.endsWith('.fst')) {
+    // INTERCEPT VCD|FST|GHW FILES
+    if (fileName.endsWith('.vcd') || fileName.endsWith('.fst') || fileName.endsWith('.ghw')) {
         console.log('Opening file in Wavetrace viewer:', fileName);
         await openWavetraceViewer(filePath, fileName);
         return;
@@ -405,6 +406,11 @@ function showContextMenu(event, item) {
             { label: 'Delete', icon: 'delete', action: () => deleteItem(item), danger: true }
         );
     } else {
+        if (item.name.endsWith('.fst') || item.name.endsWith('.ghw')) {
+            menuItems.push(
+                { label: 'Export as VCD', icon: 'download', action: () => exportItemAsVcd(item) }
+            );
+        }
         menuItems.push(
             { label: 'Rename', icon: 'edit', action: () => renameItem(item) },
             { label: 'Delete', icon: 'delete', action: () => deleteItem(item), danger: true }
@@ -587,6 +593,28 @@ function cancelInlineInput() {
     currentInlineInput = null;
 }
 
+// ===== EXPORT WAVEFORM =====
+
+async function exportItemAsVcd(item) {
+    try {
+        const dstPath = await save({
+            title: 'Export as VCD',
+            defaultPath: item.path.replace(/\.(fst|ghw)$/, '.vcd'),
+            filters: [{
+                name: 'VCD Files',
+                extensions: ['vcd']
+            }]
+        });
+
+        if (dstPath) {
+            await exportWaveformAsVcd(item.path, dstPath);
+            await refreshFileTree(state.workspace);
+        }
+    } catch (error) {
+        console.error('Error exporting waveform:', error);
+    }
+}
+
 // ===== CREATE FILE =====
 
 async function createNewFile(parentPath = null) {
@@ -6,6 +6,11 @@ import { updateFileTreeHighlight } from './fileTree.js';
 let themesDefined = false;
 const globalModels = new Map();
 
+// Source profile shown as a gutter heatmap on the matching .cmm models
+let lineProfile = null;
+const heatmapDecorations = new Map();
+const HEAT_LEVELS = 5;
+
 // ===== MONACO EDITOR INITIALIZATION =====
 
 export async function initMonacoEditor(container, instanceId) {
@@ -246,6 +251,7 @@ export function setEditorModel(instanceId, filePath) {
         // Create new model
         model = monaco.editor.createModel(tab.content, language, uri);
         globalModels.set(filePath, model);
+        applyLineHeatmap(filePath, model);
         
         // Listen for model content changes to sync across instances
         model.onDidChangeContent(() => {
@@ -290,6 +296,47 @@ export function setEditorModel(instanceId, filePath) {
     console.log(`Model set for instance ${instanceId}:`, filePath);
 }
 
+// ===== SOURCE PROFILE HEATMAP =====
+
+function profiledFile(filePath) {
+    if (!lineProfile || !filePath.toLowerCase().endsWith('.cmm')) return false;
+    if (!lineProfile.program) return true;
+    const name = filePath.split(/[\\/]/).pop().replace(/\.cmm$/i, '');
+    return name === lineProfile.program;
+}
+
+function applyLineHeatmap(filePath, model) {
+    const decorations = [];
+    if (profiledFile(filePath)) {
+        // Relative to the hottest line of the program itself, so a long wait
+        // on an internal entry does not wash out the rest
+        const lines = lineProfile.lines.filter(line => line.line > 0);
+        const hottest = Math.max(1, ...lines.map(line => line.time));
+        for (const line of lines) {
+            if (line.line > model.getLineCount()) continue;
+            const level = Math.max(1, Math.ceil(line.time / hottest * HEAT_LEVELS));
+            const amount = line.cycles !== null ? `${line.cycles} cycles` : `${line.time} time units`;
+            decorations.push({
+                range: new monaco.Range(line.line, 1, line.line, 1),
+                options: {
+                    isWholeLine: true,
+                    linesDecorationsClassName: `line-heat line-heat-${level}`,
+                    hoverMessage: {
+                        value: `${amount} (${(line.share * 100).toFixed(2)}%), entered ${line.entries} times`
+                    }
+                }
+            });
+        }
+    }
+    heatmapDecorations.set(filePath, model.deltaDecorations(heatmapDecorations.get(filePath) || [], decorations));
+}
+
+// Show a source profile on the open .cmm files, or clear it with null.
+export function showLineHeatmap(profile) {
+    lineProfile = profile;
+    globalModels.forEach((model, filePath) => applyLineHeatmap(filePath, model));
+}
+
 export function disposeModel(filePath) {
     // Check if any instance still has this file open
     let stillInUse = false;
@@ -305,6 +352,7 @@ export function disposeModel(filePath) {
         if (model) {
             model.dispose();
             globalModels.delete(filePath);
+            heatmapDecorations.delete(filePath);
             console.log(`Model disposed: ${filePath}`);
         }
     }
@@ -0,0 +1,168 @@
+// ===== WAVEFORM BINARY CODEC =====
+// Decoders for the layouts written by src-tauri/src/waveform_codec.rs
+
+const KIND_REAL = 1;
+const KIND_STRING = 2;
+
+const RECORD_SCOPE = 0;
+const RECORD_END_SCOPE = 1;
+const RECORD_VAR = 2;
+
+const HEADER_SIZE = 16;
+const NO_PORT = 0xffffffff;
+const STATE_CHARS = '01xzhuwl-';
+
+const textDecoder = new TextDecoder();
+
+class ByteReader {
+    constructor(buffer) {
+        this.view = new DataView(buffer);
+        this.bytes = new Uint8Array(buffer);
+        this.offset = 0;
+    }
+
+    get done() {
+        return this.offset >= this.bytes.length;
+    }
+
+    u8() {
+        return this.view.getUint8(this.offset++);
+    }
+
+    u32() {
+        const value = this.view.getUint32(this.offset, true);
+        this.offset += 4;
+        return value;
+    }
+
+    f64() {
+        const value = this.view.getFloat64(this.offset, true);
+        this.offset += 8;
+        return value;
+    }
+
+    str() {
+        const length = this.view.getUint16(this.offset, true);
+        this.offset += 2;
+        const value = textDecoder.decode(this.bytes.subarray(this.offset, this.offset + length));
+        this.offset += length;
+        return value;
+    }
+}
+
+function readVar(reader, parentPath) {
+    const name = reader.str();
+    const varType = reader.str();
+    const varRef = reader.u32();
+    const signalRef = reader.u32();
+    const width = reader.u32();
+    const kind = reader.str();
+    const func = reader.str();
+    const variable = reader.str();
+    const port = reader.u32();
+    return {
+        name,
+        path: parentPath ? `${parentPath}.${name}` : name,
+        varType,
+        varRef,
+        signalRef,
+        width,
+        class: {
+            kind,
+            function: func || null,
+            variable: variable || null,
+            port: port === NO_PORT ? null : port
+        }
+    };
+}
+
+export function decodeHierarchy(buffer) {
+    const reader = new ByteReader(buffer);
+    const hierarchy = {
+        sessionId: reader.u32(),
+        timeRange: { start: reader.f64(), end: reader.f64() },
+        path: reader.str(),
+        fileFormat: reader.str(),
+        timescale: reader.str(),
+        scopes: [],
+        vars: []
+    };
+
+    const stack = [];
+    while (!reader.done) {
+        const parent = stack[stack.length - 1];
+        const record = reader.u8();
+
+        if (record === RECORD_SCOPE) {
+            const name = reader.str();
+            const scope = {
+                name,
+                path: parent ? `${parent.path}.${name}` : name,
+                scopeType: reader.str(),
+                scopes: [],
+                vars: []
+            };
+            (parent ? parent.scopes : hierarchy.scopes).push(scope);
+            stack.push(scope);
+        } else if (record === RECORD_END_SCOPE) {
+            stack.pop();
+        } else if (record === RECORD_VAR) {
+            const v = readVar(reader, parent?.path);
+            (parent ? parent.vars : hierarchy.vars).push(v);
+        } else {
+            throw new Error(`Unknown hierarchy record ${record}`);
+        }
+    }
+
+    return hierarchy;
+}
+
+function unpackBits(bytes, offset, width, bitsPerState) {
+    const mask = (1 << bitsPerState) - 1;
+    let value = '';
+    for (let i = 0; i < width; i++) {
+        const bitOffset = i * bitsPerState;
+        const shift = 8 - bitsPerState - (bitOffset % 8);
+        value += STATE_CHARS[(bytes[offset + (bitOffset >> 3)] >> shift) & mask];
+    }
+    return value;
+}
+
+// Returns the changes as { time, value } objects, the shape used by the VCD parser
+export function decodeSignalChanges(buffer) {
+    const view = new DataView(buffer);
+    const bytes = new Uint8Array(buffer);
+
+    const kind = view.getUint8(0);
+    const bitsPerState = view.getUint8(1);
+    const width = view.getUint32(4, true);
+    const count = view.getUint32(8, true);
+    const signalRef = view.getUint32(12, true);
+
+    const times = new Float64Array(buffer, HEADER_SIZE, count);
+    const valuesOffset = HEADER_SIZE + count * 8;
+    const values = new Array(count);
+
+    if (kind === KIND_REAL) {
+        const reals = new Float64Array(buffer, valuesOffset, count);
+        for (let i = 0; i < count; i++) values[i] = String(reals[i]);
+    } else if (kind === KIND_STRING) {
+        let offset = valuesOffset;
+        for (let i = 0; i < count; i++) {
+            const length = view.getUint32(offset, true);
+            offset += 4;
+            values[i] = textDecoder.decode(bytes.subarray(offset, offset + length));
+            offset += length;
+        }
+    } else {
+        const stride = Math.ceil((width * bitsPerState) / 8);
+        for (let i = 0; i < count; i++) {
+            values[i] = unpackBits(bytes, valuesOffset + i * stride, width, bitsPerState);
+        }
+    }
+
+    return {
+        signalRef,
+        changes: Array.from(times, (time, i) => ({ time, value: values[i] }))
+    };
+}
@@ -1,6 +1,10 @@
 import * as PIXI from 'pixi.js';
 import { invoke } from '@tauri-apps/api/core';
-import { ask } from '@tauri-apps/plugin-dialog';
+import { listen } from '@tauri-apps/api/event';
+import { ask, open, save } from '@tauri-apps/plugin-dialog';
+import { decodeHierarchy, decodeSignalChanges } from './waveformCodec.js';
+import { showLineHeatmap } from './monaco.js';
+import { state } from './state.js';
 
 // ===== WAVETRACE STATE =====
 const wavetraceState = {
@@ -8,6 +12,10 @@ const wavetraceState = {
     filePath: null,
     fileName: null,
     vcdData: null,
+    sessionId: null,
+    signalWindows: new Map(),
+    pendingWindows: new Map(),
+    followUnlisten: null,
     app: null,
     container: null,
     signals: [],
@@ -17,11 +25,19 @@ const wavetraceState = {
     signalRenderMode: new Map(),
     signalDisplayName: new Map(),
     signalType: new Map(),
-    opcodeMap: new Map(),  
-    cmmMap: new Map(),     
+    saphoValues: new Map(),
+    pendingSaphoValues: new Map(),
+    opcodeMaps: new Map(),
+    cmmMaps: new Map(),
+    floatFormats: new Map(),
+    enumMaps: new Map(),
+    signalProcessors: new Map(),
     timeScale: 1,
     timeOffset: 0,
     cursorPosition: null,
+    markers: [],
+    clock: null,
+    timeMode: 'time',
     selectedSignalId: null,
     signalHeight: 80,
     headerHeight: 50,
@@ -58,6 +74,7 @@ const wavetraceState = {
         text: 0xe8e8f0,
         textMuted: 0xa8a8c0,
         cursor: 0xa78bfa,
+        marker: 0xfbbf24,
         selectedBg: 0x2a2a3c,
         highlight: 0x8b5cf6,
         signalBg: 0x0d0d12,
@@ -65,153 +82,309 @@ const wavetraceState = {
     }
 };
 
-// ===== VCD PARSER =====
-class VCDParser {
-    constructor() {
-        this.timescale = '1ns';
-        this.scope = [];
-        this.signals = new Map();
-        this.values = new Map();
-        this.timeValues = [];
-    }
-
-    async parse(vcdContent, onProgress) {
-        const lines = vcdContent.split('\n');
-        const totalLines = lines.length;
-        let inHeader = true;
-        let currentTime = 0;
-        let idToSignal = new Map();
-        
-        const chunkSize = Math.ceil(totalLines * 0.01);
-
-        for (let i = 0; i < totalLines; i++) {
-            const line = lines[i].trim();
-            if (!line || line.startsWith('$comment')) continue;
-
-            if (inHeader) {
-                if (line.startsWith('$timescale')) {
-                    this.timescale = lines[++i].trim();
-                } else if (line.startsWith('$scope')) {
-                    const parts = line.split(/\s+/);
-                    this.scope.push(parts[2] || 'unknown');
-                } else if (line.startsWith('$upscope')) {
-                    this.scope.pop();
-                } else if (line.startsWith('$var')) {
-                    const parts = line.split(/\s+/);
-                    const type = parts[1];
-                    let width = parseInt(parts[2]);
-                    const id = parts[3];
-                    const name = parts[4];
-                    if (type === 'string' || type === 'real') {
-                        width = 8; 
-                    }
-                    const fullPath = [...this.scope, name].join('.');
-                    
-                    const signal = {
-                        id,
-                        name,
-                        path: fullPath,
-                        type,
-                        width,
-                        values: []
-                    };
-                    this.signals.set(id, signal);
-                    idToSignal.set(id, signal);
-                } else if (line.startsWith('$enddefinitions')) {
-                    inHeader = false;
-                }
-                continue;
-            }
+// ===== WAVEFORM SESSIONS =====
+function buildVcdDataFromHierarchy(hierarchy) {
+    const signals = [];
+
+    const collectVars = (vars) => {
+        vars.forEach(v => {
+            const isText = v.varType === 'real' || v.varType === 'string';
+            signals.push({
+                id: String(v.varRef),
+                name: v.name,
+                path: v.path,
+                type: v.varType,
+                width: isText ? 8 : v.width,
+                signalRef: v.signalRef,
+                class: v.class,
+                values: [],
+                loaded: false
+            });
+        });
+    };
 
-            if (line.startsWith('#')) {
-                currentTime = parseInt(line.substring(1));
-                if (!this.timeValues.includes(currentTime)) {
-                    this.timeValues.push(currentTime);
-                }
-            } else if (line.length > 0) {
-                let value, id;
-                const firstChar = line[0].toLowerCase();
-                
-                if (firstChar === 'b') {
-                    const parts = line.split(/\s+/);
-                    value = parts[0].substring(1);
-                    id = parts[1];
-                } else if (firstChar === 'r') {
-                    const parts = line.split(/\s+/);
-                    value = parts[0].substring(1);
-                    id = parts[1];
-                } else if (firstChar === 's') {
-                    const lastSpace = line.lastIndexOf(' ');
-                    value = line.substring(1, lastSpace);
-                    id = line.substring(lastSpace + 1);
-                } else {
-                    value = line[0];
-                    id = line.substring(1);
-                }
+    const collectScope = (scope) => {
+        collectVars(scope.vars);
+        scope.scopes.forEach(collectScope);
+    };
 
-                const signal = idToSignal.get(id);
-                if (signal) {
-                    signal.values.push({ time: currentTime, value });
-                }
-            }
+    collectVars(hierarchy.vars);
+    hierarchy.scopes.forEach(collectScope);
 
-            if (i % chunkSize === 0 && onProgress) {
-                onProgress(i, totalLines);
-                await new Promise(resolve => setTimeout(resolve, 0));
-            }
+    return {
+        timescale: hierarchy.timescale,
+        signals,
+        timeRange: hierarchy.timeRange
+    };
+}
+
+async function ensureSignalLoaded(signal) {
+    if (signal.loaded !== false || wavetraceState.sessionId === null) return;
+
+    const buffer = await invoke('load_signal_changes_binary', {
+        sessionId: wavetraceState.sessionId,
+        signalRef: signal.signalRef
+    });
+    const values = decodeSignalChanges(buffer).changes;
+
+    // Aliases share the same signal data, so fill them all at once
+    wavetraceState.signals.forEach(s => {
+        if (s.signalRef === signal.signalRef) {
+            s.values = values;
+            s.loaded = true;
         }
+    });
+}
+
+function getSignalRenderData(signal, width) {
+    if (wavetraceState.sessionId === null || signal.signalRef === undefined) {
+        return { values: signal.values, dense: [] };
+    }
 
-        if (onProgress) onProgress(totalLines, totalLines);
+    const start = Math.max(0, Math.floor(wavetraceState.timeOffset));
+    const end = Math.max(start, Math.ceil(wavetraceState.timeOffset + width / wavetraceState.timeScale));
+    const buckets = Math.max(1, Math.floor(width));
+    const key = `${start}:${end}:${buckets}`;
 
-        this.timeValues.sort((a, b) => a - b);
-        return {
-            timescale: this.timescale,
-            signals: Array.from(this.signals.values()),
-            timeRange: {
-                start: this.timeValues[0] || 0,
-                end: this.timeValues[this.timeValues.length - 1] || 0
-            }
-        };
+    const cached = wavetraceState.signalWindows.get(signal.signalRef);
+    if (!cached || cached.key !== key) {
+        requestSignalWindow(signal.signalRef, start, end, buckets, key);
     }
+
+    // A stale window is still better than walking every change while the new one arrives
+    return cached || { values: signal.values, dense: [] };
 }
 
-function parseTranslationFiles(opcodeText, cmmText) {
-    wavetraceState.opcodeMap.clear();
-    wavetraceState.cmmMap.clear();
-    
-    const fillMap = (text, map) => {
-        if (!text) return;
-        const lines = text.split('\n');
-        for (let line of lines) {
-            const trimmed = line.trim();
-            if (!trimmed) continue;
-            const match = trimmed.match(/^(-?\d+)\s+(.*)$/);
-            if (match) {
-                const num = parseInt(match[1], 10);
-                const translation = match[2].trim();
-                map.set(num, translation);
+async function requestSignalWindow(signalRef, start, end, buckets, key) {
+    if (wavetraceState.pendingWindows.get(signalRef) === key) return;
+    wavetraceState.pendingWindows.set(signalRef, key);
+
+    try {
+        const result = await invoke('query_signal_window', {
+            sessionId: wavetraceState.sessionId,
+            signalRef,
+            start,
+            end,
+            buckets
+        });
+
+        if (wavetraceState.pendingWindows.get(signalRef) !== key) return;
+        wavetraceState.pendingWindows.delete(signalRef);
+
+        wavetraceState.signalWindows.set(signalRef, {
+            key,
+            values: result.times.map((time, i) => ({ time, value: result.values[i] })),
+            dense: result.dense
+        });
+        renderWaveforms();
+    } catch (error) {
+        wavetraceState.pendingWindows.delete(signalRef);
+        console.error('Failed to query signal window:', error);
+    }
+}
+
+function closeWaveformSession() {
+    if (wavetraceState.sessionId === null) return;
+    stopFollowing();
+    invoke('close_waveform', { sessionId: wavetraceState.sessionId })
+        .catch(err => console.warn('Failed to close waveform session:', err));
+    wavetraceState.sessionId = null;
+    wavetraceState.signalWindows.clear();
+    wavetraceState.pendingWindows.clear();
+    wavetraceState.saphoValues.clear();
+    wavetraceState.pendingSaphoValues.clear();
+    wavetraceState.markers = [];
+}
+
+// Follow a VCD the simulator is still writing. Every update extends the time
+// range and refetches the displayed signals; a view showing the end of the
+// waveform scrolls along with it.
+async function startFollowing() {
+    if (wavetraceState.sessionId === null || wavetraceState.followUnlisten) return;
+    const sessionId = wavetraceState.sessionId;
+
+    wavetraceState.followUnlisten = await listen(`waveform-updated-${sessionId}`, (event) => {
+        if (wavetraceState.sessionId === sessionId) applyWaveformUpdate(event.payload);
+    });
+    try {
+        await invoke('watch_waveform', { sessionId });
+    } catch (error) {
+        stopFollowing();
+        alert(`Failed to follow waveform: ${error}`);
+    }
+    updateFollowButton();
+}
+
+function stopFollowing() {
+    if (!wavetraceState.followUnlisten) return;
+    wavetraceState.followUnlisten();
+    wavetraceState.followUnlisten = null;
+    if (wavetraceState.sessionId !== null) {
+        invoke('unwatch_waveform', { sessionId: wavetraceState.sessionId })
+            .catch(err => console.warn('Failed to stop following waveform:', err));
+    }
+    updateFollowButton();
+}
+
+function updateFollowButton() {
+    document.getElementById('wtFollow')?.classList.toggle('active', wavetraceState.followUnlisten !== null);
+}
+
+async function applyWaveformUpdate({ end, error }) {
+    if (error) {
+        stopFollowing();
+        alert(`Stopped following waveform: ${error}`);
+        return;
+    }
+    if (!wavetraceState.vcdData || !wavetraceState.app) return;
+
+    const range = wavetraceState.vcdData.timeRange;
+    const visibleTime = wavetraceState.app.view.width / wavetraceState.timeScale;
+    const showingEnd = wavetraceState.timeOffset + visibleTime >= range.end;
+    range.end = end;
+
+    // Everything fetched so far stops at the previous end
+    wavetraceState.signalWindows.clear();
+    wavetraceState.pendingWindows.clear();
+    wavetraceState.signals.forEach(s => {
+        if (s.loaded) s.loaded = false;
+    });
+    try {
+        await Promise.all(wavetraceState.displayedSignals.map(ensureSignalLoaded));
+    } catch (error) {
+        console.error('Failed to reload signals:', error);
+    }
+
+    if (showingEnd) {
+        wavetraceState.timeOffset = Math.max(range.start, end - visibleTime);
+    }
+    updateHorizontalSlider();
+    renderWaveforms();
+}
+
+// Processor each signal belongs to, as the backend keys its symbol tables:
+// its own p_<name> instance or, for valr2 and linetabs beside it, the one
+// instance sharing the most scopes with it.
+function assignSignalProcessors() {
+    const instances = new Map();
+    for (const signal of wavetraceState.signals) {
+        const scopes = signal.path.split('.').slice(0, -1);
+        const end = scopes.findIndex(scope => scope.startsWith('p_'));
+        if (end >= 0 && scopes[end].length > 2) {
+            instances.set(scopes.slice(0, end + 1).join('.'), scopes[end].slice(2));
+        }
+    }
+
+    wavetraceState.signalProcessors.clear();
+    for (const signal of wavetraceState.signals) {
+        const scopes = signal.path.split('.').slice(0, -1);
+        let most = 0;
+        let names = new Set();
+        for (const [instance, name] of instances) {
+            const parts = instance.split('.');
+            let shared = 0;
+            while (shared < parts.length && parts[shared] === scopes[shared]) shared++;
+            if (shared > most) {
+                most = shared;
+                names = new Set([name]);
+            } else if (shared === most && shared > 0) {
+                names.add(name);
             }
         }
-    };
+        if (names.size === 1) {
+            wavetraceState.signalProcessors.set(signal.path, [...names][0]);
+        }
+    }
+}
 
-    fillMap(opcodeText, wavetraceState.opcodeMap);
-    fillMap(cmmText, wavetraceState.cmmMap);
-    console.log(`Dicionários carregados! Opcode: ${wavetraceState.opcodeMap.size} itens, CMM: ${wavetraceState.cmmMap.size} itens.`);
+// Table of a signal's processor among `maps`, else the one for signals
+// outside any processor, else the only one.
+function processorTable(maps, signal) {
+    const key = wavetraceState.signalProcessors.get(signal.path) ?? '';
+    if (maps.has(key)) return maps.get(key);
+    if (maps.has('')) return maps.get('');
+    return maps.size === 1 ? maps.values().next().value : null;
 }
 
-export async function openWavetraceViewer(filePath, fileName) {
-    closeWavetraceViewer();
-    console.log('Opening Wavetrace viewer for:', fileName);
-    
-    if (typeof wavetraceState === 'undefined') window.wavetraceState = {};
-    wavetraceState.cancelLoad = false;
-    
+// Float format of a signal's processor, null when its project gives none.
+function floatFormatOf(signal) {
+    return processorTable(wavetraceState.floatFormats, signal);
+}
+
+// Fill the value translations from the symbol tables of every processor.
+function applySymbolTables(processorTables) {
+    wavetraceState.opcodeMaps.clear();
+    wavetraceState.cmmMaps.clear();
+    wavetraceState.floatFormats.clear();
+    wavetraceState.enumMaps.clear();
+    assignSignalProcessors();
+
+    for (const tables of processorTables) {
+        const opcodes = new Map();
+        for (const [address, instruction] of Object.entries(tables.opcodes)) {
+            const text = instruction.operand ? `${instruction.mnemonic} ${instruction.operand}` : instruction.mnemonic;
+            opcodes.set(Number(address), text);
+        }
+        const lines = new Map();
+        for (const [line, text] of Object.entries(tables.sourceLines)) {
+            lines.set(Number(line), text);
+        }
+        wavetraceState.opcodeMaps.set(tables.processor ?? '', opcodes);
+        wavetraceState.cmmMaps.set(tables.processor ?? '', lines);
+        if (tables.floatFormat) {
+            wavetraceState.floatFormats.set(tables.processor ?? '', tables.floatFormat);
+        }
+        for (const [path, labels] of Object.entries(tables.enums)) {
+            wavetraceState.enumMaps.set(path, new Map(Object.entries(labels).map(([value, label]) => [BigInt(value), label])));
+        }
+        console.log(`Symbol tables of ${tables.processor ?? 'the waveform'}: ${opcodes.size} opcodes, ${lines.size} source lines.`);
+    }
+
+    const warnings = processorTables.flatMap(tables => tables.warnings);
+    if (warnings.length > 0) {
+        console.warn('Missing symbol tables:\n' + warnings.map(w => `${w.file}: ${w.message}`).join('\n'));
+    }
+
+    const errors = processorTables.flatMap(tables => tables.errors);
+    if (errors.length > 0) {
+        const lines = errors.map(e => e.line > 0 ? `${e.file}:${e.line}: ${e.message}` : `${e.file}: ${e.message}`);
+        console.warn('Symbol table errors:\n' + lines.join('\n'));
+        alert(`Problems found in the symbol tables:\n${lines.slice(0, 20).join('\n')}${lines.length > 20 ? `\n... and ${lines.length - 20} more` : ''}`);
+    }
+}
+
+// Let the user pick a `<value> <label>` file for a signal, e.g. FSM states.
+async function attachSignalLabels(signal) {
+    if (wavetraceState.sessionId === null) return;
+
+    try {
+        const file = await open({
+            title: 'Attach value labels',
+            multiple: false,
+            filters: [{ name: 'Label tables', extensions: ['txt'] }]
+        });
+        if (!file) return;
+
+        const tables = await invoke('attach_signal_labels', {
+            sessionId: wavetraceState.sessionId,
+            signalPath: signal.path,
+            file
+        });
+        applySymbolTables(tables);
+        renderWaveforms();
+    } catch (error) {
+        console.error('Failed to attach labels:', error);
+        alert(`Failed to attach labels: ${error}`);
+    }
+}
+
+function showFileLoader(title, onCancel) {
     let loader = document.getElementById('wtFileLoader');
     if (!loader) {
         loader = document.createElement('div');
         loader.id = 'wtFileLoader';
         loader.innerHTML = `
-            <h3 style="margin-bottom: 15px; font-weight: 500;">Processando arquivo VCD...</h3>
+            <h3 id="wtLoaderTitle" style="margin-bottom: 15px; font-weight: 500;"></h3>
             <div style="width: 320px; height: 8px; background: #252538; border-radius: 4px; overflow: hidden; margin-bottom: 8px;">
                 <div id="wtProgressBar" style="width: 0%; height: 100%; background: #a78bfa; transition: width 0.05s ease-out;"></div>
             </div>
@@ -225,6 +398,8 @@ export async function openWavetraceViewer(filePath, fileName) {
         loader.style.display = 'flex';
     }
 
+    document.getElementById('wtLoaderTitle').textContent = title;
+
     const cancelBtn = document.getElementById('wtBtnCancelLoad');
     if (cancelBtn) {
         cancelBtn.disabled = false;
@@ -232,13 +407,63 @@ export async function openWavetraceViewer(filePath, fileName) {
         cancelBtn.textContent = "Cancelar Leitura";
         
         cancelBtn.onclick = () => {
-            wavetraceState.cancelLoad = true;
+            onCancel();
             cancelBtn.textContent = "Cancelando...";
             cancelBtn.style.background = "#991b1b";
             cancelBtn.disabled = true;
         };
     }
 
+    return loader;
+}
+
+// Converts a VCD/FST/GHW file to VCD on disk, streaming progress into the file loader
+export async function exportWaveformAsVcd(srcPath, dstPath) {
+    const cancelToken = crypto.randomUUID();
+    const loader = showFileLoader("Exportando para VCD...", () => {
+        invoke('cancel_waveform_export', { cancelToken })
+            .catch(err => console.warn('Failed to cancel export:', err));
+    });
+
+    const progressBar = document.getElementById('wtProgressBar');
+    const progressText = document.getElementById('wtProgressText');
+    if (progressBar) progressBar.style.width = "0%";
+    if (progressText) progressText.textContent = "Lendo arquivo do disco...";
+
+    const unlisten = await listen(`vcd-export-progress-${cancelToken}`, (event) => {
+        const { percent } = event.payload;
+        if (progressBar) progressBar.style.width = `${percent}%`;
+        if (progressText) progressText.textContent = `${percent}% da linha do tempo exportada`;
+    });
+
+    try {
+        await invoke('export_waveform_as_vcd', { src: srcPath, dst: dstPath, cancelToken });
+        console.log('Waveform exported to:', dstPath);
+    } catch (error) {
+        if (String(error) === 'Export cancelled') {
+            console.log('Exportação para VCD cancelada pelo usuário.');
+            return;
+        }
+
+        console.error('Error exporting waveform:', error);
+        alert(`Failed to export waveform: ${error}`);
+    } finally {
+        unlisten();
+        loader.style.display = 'none';
+    }
+}
+
+export async function openWavetraceViewer(filePath, fileName) {
+    closeWavetraceViewer();
+    console.log('Opening Wavetrace viewer for:', fileName);
+    
+    if (typeof wavetraceState === 'undefined') window.wavetraceState = {};
+    wavetraceState.cancelLoad = false;
+    
+    const loader = showFileLoader("Processando arquivo VCD...", () => {
+        wavetraceState.cancelLoad = true;
+    });
+
     const progressBar = document.getElementById('wtProgressBar');
     const progressText = document.getElementById('wtProgressText');
 
@@ -248,29 +473,15 @@ export async function openWavetraceViewer(filePath, fileName) {
         progressText.textContent = "Lendo arquivo do disco...";
         if (progressBar) progressBar.style.width = "0%";
 
-        let content;
-        if (fileName.toLowerCase().endsWith('.fst')) {
-            progressText.textContent = "Convertendo FST para VCD nativamente...";
-            content = await invoke('read_fst_as_vcd', { path: filePath });
-        } else {
-            content = await invoke('read_file', { path: filePath });
-        }
-        
+        // VCD, FST and GHW are all read natively; the format is detected from the content.
+        // Only the hierarchy is read here, signal data is fetched when a signal is added.
+        progressText.textContent = "Lendo hierarquia do arquivo...";
+        wavetraceState.sessionId = await invoke('open_waveform', { path: filePath });
         if (wavetraceState.cancelLoad) throw new Error('USER_CANCELLED');
-        
-        const parser = new VCDParser();
-        
-        const vcdData = await parser.parse(content, (current, total) => {
-            if (wavetraceState.cancelLoad) {
-                throw new Error('USER_CANCELLED');
-            }
 
-            const percentage = ((current / total) * 100).toFixed(0);
-            if (progressBar) progressBar.style.width = `${percentage}%`;
-            if (progressText) {
-                progressText.textContent = `${current.toLocaleString()} / ${total.toLocaleString()} linhas (${percentage}%)`;
-            }
-        });
+        const buffer = await invoke('get_waveform_hierarchy_binary', { sessionId: wavetraceState.sessionId });
+        const vcdData = buildVcdDataFromHierarchy(decodeHierarchy(buffer));
+        if (progressBar) progressBar.style.width = "100%";
         
         if (wavetraceState.cancelLoad) throw new Error('USER_CANCELLED');
 
@@ -281,26 +492,23 @@ export async function openWavetraceViewer(filePath, fileName) {
         wavetraceState.active = true;
 
         try {
-            const lastSlash = filePath.lastIndexOf('/');
-            const lastBackslash = filePath.lastIndexOf('\\');
-            const slashIdx = Math.max(lastSlash, lastBackslash);
-            const dirPath = slashIdx !== -1 ? filePath.substring(0, slashIdx + 1) : '';
-
-            const opcodePath = dirPath + 'trad_opcode.txt';
-            const cmmPath = dirPath + 'trad_cmm.txt';
-
-            progressText.textContent = "Carregando arquivos de tradução...";
-
-            const opcodeContent = await invoke('read_file', { path: opcodePath });
-            if (wavetraceState.cancelLoad) throw new Error('USER_CANCELLED');
-
-            const cmmContent = await invoke('read_file', { path: cmmPath });
+            progressText.textContent = "Carregando tabelas de símbolos...";
+            const tables = await invoke('load_waveform_symbols', {
+                sessionId: wavetraceState.sessionId,
+                spfPath: state.currentSpfPath || null
+            });
             if (wavetraceState.cancelLoad) throw new Error('USER_CANCELLED');
+            applySymbolTables(tables);
+        } catch (error) {
+            if (error.message === 'USER_CANCELLED') throw error;
+            console.warn('Failed to load symbol tables:', error);
+        }
 
-            parseTranslationFiles(opcodeContent, cmmContent);
-        } catch (errTxt) {
-            if (errTxt.message === 'USER_CANCELLED') throw errTxt;
-            console.warn("Aviso: Arquivos trad_opcode.txt ou trad_cmm.txt não encontrados na mesma pasta do VCD.", errTxt);
+        try {
+            const clocks = await invoke('detect_waveform_clocks', { sessionId: wavetraceState.sessionId });
+            wavetraceState.clock = clocks[0] || null;
+        } catch (error) {
+            console.warn('Failed to detect clocks:', error);
         }
 
         if (wavetraceState.cancelLoad) throw new Error('USER_CANCELLED');
@@ -327,6 +535,8 @@ export async function openWavetraceViewer(filePath, fileName) {
         console.log(`Loaded ${vcdData.signals.length} signals from VCD file`);
 
     } catch (error) {
+        closeWaveformSession();
+
         if (error.message === 'USER_CANCELLED') {
             console.log('Leitura do arquivo VCD cancelada com sucesso pelo usuário.');
             return;
@@ -339,30 +549,32 @@ export async function openWavetraceViewer(filePath, fileName) {
     }
 }
 
+// Signal kinds come from the backend classifier in sapho_names.rs
+const IO_KINDS = ['input', 'inputRequest', 'output', 'outputEnable'];
+const VARIABLE_KINDS = ['int', 'float', 'vector', 'complex', 'array'];
+
 function assignSignalColors() {
     wavetraceState.signals.forEach((signal) => {
-        const name = signal.name;
-        const parts = name.split('_');
-        const baseName = parts.slice(0, -1).join('_');
+        const kind = signal.class?.kind;
 
         let assignedColor = wavetraceState.colorsSignal.default;
 
-        if (name === "valr2") {
+        if (kind === 'instruction') {
             assignedColor = wavetraceState.colorsSignal.assembly;
         } 
-        else if (name === "linetabs") {
+        else if (kind === 'sourceLine') {
             assignedColor = wavetraceState.colorsSignal.cmm;
         }
-        else if (["req_in_sim", "in_sim", "out_en_sim", "out_sig"].includes(baseName)) {
+        else if (IO_KINDS.includes(kind)) {
             assignedColor = wavetraceState.colorsSignal.io;
         }
-        else if (parts[0] === "me1" || parts[0] === "me2" || parts[0] === "me3" || parts[0] === "arr" || parts[0] === "comp") {
+        else if (VARIABLE_KINDS.includes(kind)) {
             assignedColor = wavetraceState.colorsSignal.vars;
         }
-        else if (name === "clk" || name === "rst") { 
+        else if (kind === 'clock' || kind === 'reset') { 
             assignedColor = wavetraceState.colorsSignal.base;
         }
-        else if (signal.path.includes("core")) { 
+        else if (kind === 'flag' || kind === 'dataStackPointer' || kind === 'instructionStackPointer') { 
             assignedColor = wavetraceState.colorsSignal.flags;
         }
 
@@ -373,87 +585,88 @@ function assignSignalColors() {
 function assignSignalFormats() {
     wavetraceState.signals.forEach((signal) => {
         const name = signal.name;
-        const parts = name.split('_');
+        const { kind, function: func, variable, port } = signal.class || { kind: 'other' };
         
         let displayName = name; 
         let radix = signal.type === 'integer' ? 'decimal' : (signal.width > 1 ? 'hex' : 'binary');
         let renderMode = signal.width === 1 ? 'digital' : 'bus';
         let type = "Default";
         
-        if (name.includes("req_in_sim")) {
-            displayName = "req_in " + parts[parts.length - 1];
-            radix = "binary";
-            type = "I/O";
-        } 
-        else if (name.includes("in_sim") && !name.includes("req_in")) {
-            displayName = "input " + parts[parts.length - 1];
-            radix = "signed decimal"; 
-            type = "I/O";
-        } 
-        else if (name.includes("out_en_sim")) {
-            displayName = "out_en " + parts[parts.length - 1];
-            radix = "binary";
-            type = "I/O";
-        } 
-        else if (name.includes("out_sig")) {
-            displayName = "output " + parts[parts.length - 1];
-            radix = "signed decimal";
-            type = "I/O";
-        }
-        
-        else if (name.includes("valr2")) {
-            displayName = "Assembly";
-            radix = "signed decimal";
-            type = "Instructions";
-        } 
-        else if (name.includes("linetabs")) {
-            displayName = "C+-";
-            radix = "signed decimal";
-            type = "Instructions";
-        }
-        
-        else if (name.startsWith("me1")) {
-            const varName = parts.slice(4, -2).join('_');
-            displayName = `int ${varName} in ${parts[2]}`;
-            radix = "signed decimal";
-            type = "Variables";
-        } 
-        else if (name.startsWith("me2")) {
-            const varName = parts.slice(4, -2).join('_');
-            displayName = `float ${varName} in ${parts[2]}`;
-            radix = "signed decimal"; 
-            type = "Variables";
-        } 
-        else if (name.startsWith("me3")) {
-            const varName = parts.slice(4, -2).join('_');
-            displayName = `vector ${varName} in ${parts[2]}`;
-            radix = "vector"; 
-            type = "Variables";
-        }
-        else if (name.startsWith("comp")) {
-            const varName = parts.slice(4, -2).join('_');
-            displayName = `complex ${varName} in ${parts[3]}`;
-            radix = "complex";
-            type = "Variables";
-        }
-        
-        else if (name.includes("pointeri")) {
-            if (signal.path.includes("isp")) {
+        switch (kind) {
+            case 'inputRequest':
+                displayName = `req_in ${port}`;
+                radix = "binary";
+                type = "I/O";
+                break;
+            case 'input':
+                displayName = `input ${port}`;
+                radix = "signed decimal";
+                type = "I/O";
+                break;
+            case 'outputEnable':
+                displayName = `out_en ${port}`;
+                radix = "binary";
+                type = "I/O";
+                break;
+            case 'output':
+                displayName = `output ${port}`;
+                radix = "signed decimal";
+                type = "I/O";
+                break;
+            case 'instruction':
+                displayName = "Assembly";
+                radix = "signed decimal";
+                type = "Instructions";
+                break;
+            case 'sourceLine':
+                displayName = "C+-";
+                radix = "signed decimal";
+                type = "Instructions";
+                break;
+            case 'int':
+                displayName = `int ${variable} in ${func}`;
+                radix = "signed decimal";
+                type = "Variables";
+                break;
+            case 'float':
+                displayName = `float ${variable} in ${func}`;
+                radix = "float";
+                type = "Variables";
+                break;
+            case 'vector':
+                displayName = `vector ${variable} in ${func}`;
+                radix = "vector";
+                type = "Variables";
+                break;
+            case 'complex':
+                displayName = func ? `complex ${variable} in ${func}` : `complex ${variable}`;
+                radix = "complex";
+                type = "Variables";
+                break;
+            case 'array':
+                displayName = func ? `array ${variable} in ${func}` : `array ${variable}`;
+                type = "Variables";
+                break;
+            case 'instructionStackPointer':
                 displayName = "Inst Stack Pointer";
-            } else {
+                renderMode = "analog";
+                type = "Flags";
+                break;
+            case 'dataStackPointer':
                 displayName = "Data Stack Pointer";
-            }
-            renderMode = "analog";
-            type = "Flags";
+                renderMode = "analog";
+                type = "Flags";
+                break;
+            case 'flag':
+                type = "Flags";
+                break;
         }
-        else if (name.includes("delta_int")) {
+
+        if (name.includes("delta_int")) {
             displayName = "Rounding Error (int)";
             radix = "decimal";
             type = "Flags";
         }
-        else if (signal.path.includes(".core.")) {
-            type = "Flags";
-        }
 
         wavetraceState.signalDisplayName.set(signal.id, displayName);
         wavetraceState.signalRadix.set(signal.id, radix);
@@ -477,7 +690,8 @@ function getCustomHierarchyPath(signal) {
         }
     }
 
-    if (signal.name.includes('clk') || signal.name.includes('rst')) {
+    const kind = signal.class?.kind;
+    if (kind === 'clock' || kind === 'reset') {
         return `${tbName}.${signalName}`; 
     }
     
@@ -565,6 +779,18 @@ function initWavetraceUI() {
                 <button class="wt-btn" id="wtFitAll" title="Fit All">
                     <span class="material-symbols-outlined">fit_screen</span>
                 </button>
+                <button class="wt-btn" id="wtTimeMode" ${wavetraceState.clock ? `title="Clock Cycles (${wavetraceState.clock.path})"` : 'title="No clock detected" disabled'}>
+                    <span class="material-symbols-outlined">timer</span>
+                </button>
+                <button class="wt-btn" id="wtFollow" title="Follow simulation output">
+                    <span class="material-symbols-outlined">sync</span>
+                </button>
+                <button class="wt-btn" id="wtExportSignals" title="Export displayed signals">
+                    <span class="material-symbols-outlined">table_view</span>
+                </button>
+                <button class="wt-btn" id="wtCompare" title="Compare with another run">
+                    <span class="material-symbols-outlined">difference</span>
+                </button>
                 <button class="wt-btn wt-btn-close" id="wtClose" title="Close">
                     <span class="material-symbols-outlined">close</span>
                 </button>
@@ -575,6 +801,8 @@ function initWavetraceUI() {
             <div class="wt-sidebar" id="wtSidebar">
                 <div class="wt-sidebar-header">
                     <input type="text" id="signalSearch" class="wt-search" placeholder="Search signals...">
+                    <input type="text" id="expressionInput" class="wt-search wt-expression" placeholder="Derived signal, e.g. clk & req_in_sim_1">
+                    <input type="text" id="triggerInput" class="wt-search wt-expression" placeholder="Trigger, e.g. valr2 == OUT">
                 </div>
                 <div class="wt-signal-list" id="signalTree"></div>
                 <div class="wt-sidebar-resizer"></div>
@@ -759,11 +987,47 @@ function setupWavetraceControls() {
     document.getElementById('wtFitAll')?.addEventListener('click', () => {
         fitAllWaveforms();
     });
+
+    document.getElementById('wtTimeMode')?.addEventListener('click', (e) => {
+        if (!wavetraceState.clock) return;
+        wavetraceState.timeMode = wavetraceState.timeMode === 'cycles' ? 'time' : 'cycles';
+        e.currentTarget.classList.toggle('active', wavetraceState.timeMode === 'cycles');
+        renderWaveforms();
+        updateCursorInfo();
+    });
+    
+    document.getElementById('wtFollow')?.addEventListener('click', () => {
+        if (wavetraceState.followUnlisten) {
+            stopFollowing();
+        } else {
+            startFollowing();
+        }
+    });
+    
+    document.getElementById('wtExportSignals')?.addEventListener('click', () => {
+        exportDisplayedSignals();
+    });
+    
+    document.getElementById('wtCompare')?.addEventListener('click', () => {
+        compareWithRun();
+    });
     
     document.getElementById('signalSearch')?.addEventListener('input', (e) => {
         filterSignals(e.target.value);
     });
 
+    document.getElementById('expressionInput')?.addEventListener('keydown', async (e) => {
+        if (e.key !== 'Enter') return;
+        if (await addDerivedSignal(e.target.value)) {
+            e.target.value = '';
+        }
+    });
+
+    document.getElementById('triggerInput')?.addEventListener('keydown', (e) => {
+        if (e.key !== 'Enter') return;
+        findTriggerMarkers(e.target.value);
+    });
+
     document.getElementById('sidebarToggle')?.addEventListener('click', () => {
         const sidebar = document.getElementById('wtSidebar');
         const toggle = document.getElementById('sidebarToggle');
@@ -856,82 +1120,83 @@ function setupKeyboardNavigation() {
 
         if (e.key === 'ArrowLeft' || e.key === 'ArrowRight') {
             e.preventDefault();
-            navigateCursorToEdge(e.key === 'ArrowRight');
+            // Shift jumps between rising edges, Alt between falling edges
+            const edge = e.shiftKey ? 'rising' : e.altKey ? 'falling' : null;
+            navigateCursorToEdge(e.key === 'ArrowRight', edge);
         }
     });
 }
 
-function navigateCursorToEdge(forward) {
-    const selectedSignal = wavetraceState.displayedSignals.find(s => s.id === wavetraceState.selectedSignalId);
-    
-    if (selectedSignal) {
-        const edges = selectedSignal.values.map(v => v.time).sort((a, b) => a - b);
-        const currentTime = wavetraceState.cursorPosition;
+// Ask the backend for the next (or previous) transition or edge of a signal
+function findSignalEdge(signal, forward, edge) {
+    if (signal.signalRef === undefined) {
+        return findLocalEdge(signal, forward, edge);
+    }
 
-        let targetTime;
-        if (forward) {
-            targetTime = edges.find(t => t > currentTime);
-            if (!targetTime) targetTime = edges[edges.length - 1];
-        } else {
-            const reversed = [...edges].reverse();
-            targetTime = reversed.find(t => t < currentTime);
-            if (!targetTime) targetTime = edges[0];
-        }
+    const time = forward
+        ? Math.floor(wavetraceState.cursorPosition)
+        : Math.ceil(wavetraceState.cursorPosition);
+    const args = { sessionId: wavetraceState.sessionId, signalRef: signal.signalRef, time, forward };
 
-        if (targetTime !== undefined) {
-            wavetraceState.cursorPosition = targetTime;
-            
-            const canvas = wavetraceState.app?.view;
-            if (canvas) {
-                const cursorX = (targetTime - wavetraceState.timeOffset) * wavetraceState.timeScale;
-                
-                if (cursorX < 0 || cursorX > canvas.width) {
-                    wavetraceState.timeOffset = targetTime - (canvas.width / 2) / wavetraceState.timeScale;
-                    constrainTimeOffset();
-                }
-            }
-            
-            renderWaveforms();
-            updateCursorInfo();
-        }
-    } else {
-        if (wavetraceState.displayedSignals.length === 0) return;
+    if (edge) {
+        return invoke('find_signal_edge', { ...args, rising: edge === 'rising' });
+    }
+    return invoke('find_signal_transition', args);
+}
 
-        let edges = new Set();
-        wavetraceState.displayedSignals.forEach(signal => {
-            signal.values.forEach(v => edges.add(v.time));
-        });
+// Derived signals only exist in the frontend, so search their values here
+function findLocalEdge(signal, forward, edge) {
+    const time = wavetraceState.cursorPosition;
+    const isEdge = (v, i) => {
+        if (!edge) return true;
+        const previous = signal.values[i - 1]?.value.slice(-1);
+        const current = v.value.slice(-1);
+        return edge === 'rising'
+            ? previous === '0' && current === '1'
+            : previous === '1' && current === '0';
+    };
 
-        const sortedEdges = Array.from(edges).sort((a, b) => a - b);
-        const currentTime = wavetraceState.cursorPosition;
+    const matches = signal.values.filter((v, i) => isEdge(v, i) && (forward ? v.time > time : v.time < time));
+    if (matches.length === 0) return null;
+    return forward ? matches[0].time : matches[matches.length - 1].time;
+}
 
-        let targetTime;
-        if (forward) {
-            targetTime = sortedEdges.find(t => t > currentTime);
-            if (!targetTime) targetTime = sortedEdges[sortedEdges.length - 1];
-        } else {
-            const reversed = [...sortedEdges].reverse();
-            targetTime = reversed.find(t => t < currentTime);
-            if (!targetTime) targetTime = sortedEdges[0];
-        }
+async function navigateCursorToEdge(forward, edge = null) {
+    if (wavetraceState.sessionId === null) return;
 
-        if (targetTime !== undefined) {
-            wavetraceState.cursorPosition = targetTime;
-            
-            const canvas = wavetraceState.app?.view;
-            if (canvas) {
-                const cursorX = (targetTime - wavetraceState.timeOffset) * wavetraceState.timeScale;
-                
-                if (cursorX < 0 || cursorX > canvas.width) {
-                    wavetraceState.timeOffset = targetTime - (canvas.width / 2) / wavetraceState.timeScale;
-                    constrainTimeOffset();
-                }
-            }
-            
-            renderWaveforms();
-            updateCursorInfo();
+    const selectedSignal = wavetraceState.displayedSignals.find(s => s.id === wavetraceState.selectedSignalId);
+    const signals = selectedSignal ? [selectedSignal] : wavetraceState.displayedSignals;
+    if (signals.length === 0) return;
+
+    let times;
+    try {
+        times = await Promise.all(signals.map(signal => findSignalEdge(signal, forward, edge)));
+    } catch (error) {
+        console.error('Failed to search signal:', error);
+        return;
+    }
+
+    const found = times.filter(t => t !== null);
+    if (found.length === 0) return;
+    moveCursorTo(forward ? Math.min(...found) : Math.max(...found));
+}
+
+// Place the cursor at `targetTime`, scrolling it into view when needed.
+function moveCursorTo(targetTime) {
+    wavetraceState.cursorPosition = targetTime;
+
+    const canvas = wavetraceState.app?.view;
+    if (canvas) {
+        const cursorX = (targetTime - wavetraceState.timeOffset) * wavetraceState.timeScale;
+
+        if (cursorX < 0 || cursorX > canvas.width) {
+            wavetraceState.timeOffset = targetTime - (canvas.width / 2) / wavetraceState.timeScale;
+            constrainTimeOffset();
         }
     }
+
+    renderWaveforms();
+    updateCursorInfo();
 }
 
 function renderSignalTree() {
@@ -1354,7 +1619,14 @@ function toggleSignal(signal) {
     }
 }
 
-function addSignalToWaveform(signal) {
+async function addSignalToWaveform(signal) {
+    try {
+        await ensureSignalLoaded(signal);
+    } catch (error) {
+        console.error(`Failed to load signal ${signal.path}:`, error);
+        return;
+    }
+
     if (!wavetraceState.displayedSignals.some(s => s.id === signal.id)) {
         wavetraceState.displayedSignals.push(signal);
         updateScrollLimits();
@@ -1362,6 +1634,73 @@ function addSignalToWaveform(signal) {
     }
 }
 
+// Evaluate an expression over the session and show it like any other signal.
+// Derived signals have no signalRef, so they render from their own values.
+async function addDerivedSignal(expression) {
+    if (!expression.trim() || wavetraceState.sessionId === null) return false;
+
+    let derived;
+    try {
+        derived = await invoke('evaluate_derived_signal', {
+            sessionId: wavetraceState.sessionId,
+            expression
+        });
+    } catch (error) {
+        console.error('Failed to evaluate expression:', error);
+        alert(`Failed to evaluate expression: ${error}`);
+        return false;
+    }
+
+    const signal = {
+        id: `expr:${derived.name}`,
+        name: derived.name,
+        path: derived.name,
+        type: 'derived',
+        width: derived.width,
+        values: derived.times.map((time, i) => ({ time, value: derived.values[i] })),
+        loaded: true
+    };
+
+    wavetraceState.displayedSignals = wavetraceState.displayedSignals.filter(s => s.id !== signal.id);
+    wavetraceState.displayedSignals.push(signal);
+    updateScrollLimits();
+    renderWaveforms();
+    return true;
+}
+
+// Mark every interval where a condition holds and jump to the first one.
+// An empty condition clears the markers.
+async function findTriggerMarkers(condition) {
+    if (wavetraceState.sessionId === null) return;
+    if (!condition.trim()) {
+        wavetraceState.markers = [];
+        renderWaveforms();
+        return;
+    }
+
+    let matches;
+    try {
+        matches = await invoke('find_trigger_matches', {
+            sessionId: wavetraceState.sessionId,
+            condition,
+            from: null,
+            to: null,
+            limit: null
+        });
+    } catch (error) {
+        console.error('Failed to search trigger:', error);
+        alert(`Failed to search trigger: ${error}`);
+        return;
+    }
+
+    wavetraceState.markers = matches;
+    if (matches.length > 0) {
+        moveCursorTo(matches[0].start);
+    } else {
+        renderWaveforms();
+    }
+}
+
 function removeSignalFromWaveform(signal) {
     wavetraceState.displayedSignals = wavetraceState.displayedSignals.filter(s => s.id !== signal.id);
     if (wavetraceState.selectedSignalId === signal.id) {
@@ -1407,6 +1746,10 @@ function showSignalContextMenu(event, signal) {
                     <span class="material-symbols-outlined">exposure</span>
                     <span>Signed Decimal</span>
                 </div>
+                <div class="wt-context-item ${currentRadix === 'float' ? 'active' : ''}" data-action="radix-float">
+                    <span class="material-symbols-outlined">functions</span>
+                    <span>Float (SAPHO)</span>
+                </div>
                 <div class="wt-context-item ${currentRadix === 'complex' ? 'active' : ''}" data-action="radix-complex">
                     <span class="material-symbols-outlined">calculate</span>
                     <span>Complex (a + j b)</span>
@@ -1439,6 +1782,47 @@ function showSignalContextMenu(event, signal) {
                 </div>
             `}
         </div>
+        ${signal.signalRef !== undefined ? `
+            <div class="wt-context-section">
+                <div class="wt-context-label">Analysis</div>
+                <div class="wt-context-item" data-action="labels">
+                    <span class="material-symbols-outlined">label</span>
+                    <span>Attach value labels...</span>
+                </div>
+                <div class="wt-context-item" data-action="stats">
+                    <span class="material-symbols-outlined">monitoring</span>
+                    <span>Statistics (visible range)</span>
+                </div>
+                ${signal.class?.kind === 'instruction' ? `
+                    <div class="wt-context-item" data-action="trace">
+                        <span class="material-symbols-outlined">list_alt</span>
+                        <span>Export instruction trace...</span>
+                    </div>
+                    <div class="wt-context-item" data-action="mix">
+                        <span class="material-symbols-outlined">bar_chart</span>
+                        <span>Instruction mix</span>
+                    </div>
+                ` : ''}
+                ${['dataStackPointer', 'instructionStackPointer'].includes(signal.class?.kind) ? `
+                    <div class="wt-context-item" data-action="stack">
+                        <span class="material-symbols-outlined">stacks</span>
+                        <span>Stack usage</span>
+                    </div>
+                ` : ''}
+                ${signal.class?.kind === 'sourceLine' ? `
+                    <div class="wt-context-item" data-action="profile">
+                        <span class="material-symbols-outlined">local_fire_department</span>
+                        <span>Source line profile</span>
+                    </div>
+                ` : ''}
+                ${signal.class?.kind === 'output' && state.currentSpfPath ? `
+                    <div class="wt-context-item" data-action="golden">
+                        <span class="material-symbols-outlined">rule</span>
+                        <span>Check expected outputs</span>
+                    </div>
+                ` : ''}
+            </div>
+        ` : ''}
     `;
     
     menu.style.left = `${event.clientX}px`;
@@ -1452,6 +1836,20 @@ function showSignalContextMenu(event, signal) {
                 wavetraceState.signalRadix.set(signal.id, action.replace('radix-', ''));
             } else if (action.startsWith('mode-')) {
                 wavetraceState.signalRenderMode.set(signal.id, action.replace('mode-', ''));
+            } else if (action === 'stats') {
+                showSignalStats(signal);
+            } else if (action === 'trace') {
+                exportInstructionTrace(signal);
+            } else if (action === 'labels') {
+                attachSignalLabels(signal);
+            } else if (action === 'stack') {
+                showStackUsage();
+            } else if (action === 'mix') {
+                showInstructionMix(signal);
+            } else if (action === 'profile') {
+                showSourceProfile(signal);
+            } else if (action === 'golden') {
+                showExpectedOutputCheck();
             }
             
             renderWaveforms();
@@ -1470,6 +1868,306 @@ function showSignalContextMenu(event, signal) {
     }, 0);
 }
 
+// Summarize a signal over the visible time range, reading it in its radix.
+async function showSignalStats(signal) {
+    if (wavetraceState.sessionId === null || !wavetraceState.app) return;
+
+    const from = Math.max(0, Math.floor(wavetraceState.timeOffset));
+    const to = Math.ceil(wavetraceState.timeOffset + wavetraceState.app.view.width / wavetraceState.timeScale);
+    const radix = signal.width > 1 ? (wavetraceState.signalRadix.get(signal.id) || 'hex') : 'binary';
+
+    let stats;
+    try {
+        [stats] = await invoke('compute_signal_stats', {
+            sessionId: wavetraceState.sessionId,
+            signals: [{ signalRef: signal.signalRef, radix, floatFormat: floatFormatOf(signal) }],
+            from,
+            to,
+            bins: null,
+            floatFormat: null
+        });
+    } catch (error) {
+        console.error('Failed to compute statistics:', error);
+        alert(`Failed to compute statistics: ${error}`);
+        return;
+    }
+
+    const lines = [
+        `${wavetraceState.signalDisplayName.get(signal.id) || signal.name}`,
+        `${formatTimeWithUnit(stats.from)} - ${formatTimeWithUnit(stats.to)}`,
+        `Toggles: ${stats.toggles}`
+    ];
+    if (stats.dutyCycle !== null) {
+        lines.push(`High time: ${formatDuration(stats.highTime)}`);
+        lines.push(`Duty cycle: ${(stats.dutyCycle * 100).toFixed(2)}%`);
+    }
+    if (stats.mean !== null) {
+        // Vector and complex values are summarized by their magnitude
+        const bar = stats.components.length > 0 ? '|' : '';
+        lines.push(`${bar}Min${bar}: ${stats.min}  ${bar}Max${bar}: ${stats.max}`);
+        lines.push(`${bar}Mean${bar}: ${stats.mean.toFixed(4)}`);
+    }
+    for (const component of stats.components) {
+        const mean = component.mean === null ? '-' : component.mean.toFixed(4);
+        lines.push(`${component.name}: min ${component.min}  max ${component.max}  mean ${mean}`);
+    }
+    alert(lines.join('\n'));
+}
+
+// Write the instructions a processor executed, one row per valr2 change.
+async function exportInstructionTrace(signal) {
+    if (wavetraceState.sessionId === null) return;
+
+    try {
+        const dst = await save({
+            title: 'Export instruction trace',
+            defaultPath: 'instruction_trace.csv',
+            filters: [{ name: 'CSV Files', extensions: ['csv'] }]
+        });
+        if (!dst) return;
+
+        await invoke('export_instruction_trace', {
+            sessionId: wavetraceState.sessionId,
+            signalRef: signal.signalRef,
+            dst
+        });
+    } catch (error) {
+        console.error('Failed to export instruction trace:', error);
+        alert(`Failed to export instruction trace: ${error}`);
+    }
+}
+
+// Peak stack depths against the sizes of the open project, and flag events.
+async function showStackUsage() {
+    if (wavetraceState.sessionId === null) return;
+
+    let analysis;
+    try {
+        analysis = await invoke('analyze_stack_usage', {
+            sessionId: wavetraceState.sessionId,
+            spfPath: state.currentSpfPath || null
+        });
+    } catch (error) {
+        console.error('Failed to analyze stacks:', error);
+        alert(`Failed to analyze stacks: ${error}`);
+        return;
+    }
+
+    const lines = [];
+    for (const stack of analysis.stacks) {
+        const name = stack.kind === 'instructionStackPointer' ? 'Instruction stack' : 'Data stack';
+        lines.push(`${name}${stack.processor ? ` of ${stack.processor}` : ''}`);
+        lines.push(stack.peak !== null
+            ? `  Peak depth: ${stack.peak} at ${formatTimeWithUnit(stack.peakTime)}`
+            : '  Peak depth: unknown');
+        if (stack.configured !== null) {
+            lines.push(`  Configured: ${stack.configured} (${stack.configuredFrom})`);
+        }
+        if (stack.warning) lines.push(`  ${stack.warning}`);
+    }
+    for (const flag of analysis.flags) {
+        lines.push(`${flag.path.split('.').pop()} = ${flag.value} from ${formatTimeWithUnit(flag.start)} to ${formatTimeWithUnit(flag.end)}`);
+    }
+    alert(lines.join('\n') || 'No stack pointers found');
+}
+
+// Count the executed mnemonics, overall and per C+- function.
+async function showInstructionMix(signal) {
+    if (wavetraceState.sessionId === null) return;
+
+    let mix;
+    try {
+        mix = await invoke('get_instruction_mix', {
+            sessionId: wavetraceState.sessionId,
+            signalRef: signal.signalRef
+        });
+    } catch (error) {
+        console.error('Failed to compute instruction mix:', error);
+        alert(`Failed to compute instruction mix: ${error}`);
+        return;
+    }
+
+    const describe = m => {
+        const amount = m.cycles !== null ? `${m.cycles} cycles` : formatDuration(m.time);
+        return `  ${m.mnemonic}: ${m.count}x, ${amount} (${(m.share * 100).toFixed(2)}%)`;
+    };
+    const lines = [`Instruction mix: ${mix.totalCount} instructions`, ...mix.mnemonics.map(describe)];
+    if (mix.halt) {
+        lines.push('', `Halted at ${formatDuration(mix.halt.time)}: ${mix.halt.mnemonic} ${mix.halt.operand ?? ''} (address ${mix.halt.pc})`);
+    }
+    for (const fn of mix.functions) {
+        lines.push('', `${fn.function}(): ${fn.count} instructions`, ...fn.mnemonics.map(describe));
+    }
+    if (mix.unused.length > 0) {
+        lines.push('', `Never executed: ${mix.unused.join(', ')}`);
+    }
+    alert(lines.join('\n'));
+}
+
+// Total the cycles of every C+- line, shown as a heatmap in the editor.
+async function showSourceProfile(signal) {
+    if (wavetraceState.sessionId === null) return;
+
+    let profile;
+    try {
+        profile = await invoke('profile_source_lines', {
+            sessionId: wavetraceState.sessionId,
+            signalRef: signal.signalRef
+        });
+    } catch (error) {
+        console.error('Failed to profile source lines:', error);
+        alert(`Failed to profile source lines: ${error}`);
+        return;
+    }
+    showLineHeatmap(profile);
+
+    const amount = entry => entry.cycles !== null ? `${entry.cycles} cycles` : formatDuration(entry.time);
+    const percent = entry => `${(entry.share * 100).toFixed(2)}%`;
+    const lines = [`Source profile${profile.program ? ` of ${profile.program}` : ''}`, ''];
+    for (const fn of profile.functions) {
+        lines.push(`${fn.name}(): ${amount(fn)} (${percent(fn)})`);
+    }
+    lines.push('', 'Hottest lines:');
+    for (const line of profile.lines.slice(0, 10)) {
+        lines.push(`${line.line}: ${line.text.trim()} - ${amount(line)} (${percent(line)})`);
+    }
+    alert(lines.join('\n'));
+}
+
+// Export format named by the extension of the chosen file, CSV by default.
+function exportFormatOf(path) {
+    const lower = path.toLowerCase();
+    if (lower.endsWith('.json')) return 'json';
+    if (lower.endsWith('.parquet')) return 'parquet';
+    return 'csv';
+}
+
+// Export the displayed signals over the visible range, each in its radix,
+// sampled on every change or on the clock's rising edges.
+async function exportDisplayedSignals() {
+    if (wavetraceState.sessionId === null || !wavetraceState.app) return;
+
+    const signals = wavetraceState.displayedSignals
+        .filter(signal => signal.signalRef !== undefined)
+        .map(signal => ({
+            signalRef: signal.signalRef,
+            radix: wavetraceState.signalRadix.get(signal.id) || null,
+            floatFormat: floatFormatOf(signal)
+        }));
+    if (signals.length === 0) {
+        alert('No signals to export');
+        return;
+    }
+
+    try {
+        const onClock = wavetraceState.clock !== null && await ask(
+            `Sample on the rising edges of ${wavetraceState.clock.path}, or on every value change?`,
+            { title: 'Export signals', okLabel: 'Clock edges', cancelLabel: 'Every change' }
+        );
+        const dst = await save({
+            title: 'Export signals',
+            defaultPath: 'signals.csv',
+            filters: [
+                { name: 'CSV Files', extensions: ['csv'] },
+                { name: 'JSON Files', extensions: ['json'] },
+                { name: 'Parquet Files', extensions: ['parquet'] }
+            ]
+        });
+        if (!dst) return;
+
+        await invoke('export_signals', {
+            sessionId: wavetraceState.sessionId,
+            request: {
+                signals,
+                sampling: onClock ? 'risingEdge' : 'changes',
+                clockRef: onClock ? wavetraceState.clock.signalRef : null,
+                cycles: onClock,
+                from: Math.max(0, Math.floor(wavetraceState.timeOffset)),
+                to: Math.ceil(wavetraceState.timeOffset + wavetraceState.app.view.width / wavetraceState.timeScale)
+            },
+            format: exportFormatOf(dst),
+            dst
+        });
+    } catch (error) {
+        console.error('Failed to export signals:', error);
+        alert(`Failed to export signals: ${error}`);
+    }
+}
+
+// Compare the open file against another simulation run of the same design.
+async function compareWithRun() {
+    if (!wavetraceState.filePath) return;
+
+    let diff;
+    try {
+        const other = await open({
+            title: 'Compare with another run',
+            multiple: false,
+            filters: [{ name: 'Waveform files', extensions: ['vcd', 'fst', 'ghw'] }]
+        });
+        if (!other) return;
+
+        diff = await invoke('compare_waveform_files', {
+            a: wavetraceState.filePath,
+            b: other,
+            signalMap: null,
+            options: null,
+            spfPath: state.currentSpfPath || null
+        });
+    } catch (error) {
+        console.error('Failed to compare waveforms:', error);
+        alert(`Failed to compare waveforms: ${error}`);
+        return;
+    }
+
+    const differing = diff.signals.filter(s => s.mismatches > 0);
+    const lines = [`${diff.signals.length} signals compared, ${differing.length} differ`];
+    for (const signal of differing) {
+        const first = diff.mismatches.find(m => m.pathA === signal.pathA);
+        const values = first ? ` (${first.valueA ?? '-'} vs ${first.valueB ?? '-'})` : '';
+        lines.push(`${signal.pathA}: ${signal.mismatches}x, first at ${formatTimeWithUnit(signal.firstMismatch)}${values}`);
+    }
+    if (diff.truncated) lines.push(`Only the first ${diff.mismatches.length} mismatches were listed`);
+    if (diff.onlyInA.length > 0) lines.push('', `Only in this run: ${diff.onlyInA.join(', ')}`);
+    if (diff.onlyInB.length > 0) lines.push('', `Only in the other run: ${diff.onlyInB.join(', ')}`);
+    if (diff.endA !== diff.endB) {
+        lines.push('', `Runs end at ${formatTimeWithUnit(diff.endA)} and ${formatTimeWithUnit(diff.endB)}; compared up to the earlier`);
+    }
+    alert(lines.join('\n'));
+}
+
+// Compare the output ports with the values the project expects from them.
+async function showExpectedOutputCheck() {
+    if (wavetraceState.sessionId === null || !state.currentSpfPath) return;
+
+    let check;
+    try {
+        check = await invoke('check_expected_outputs', {
+            sessionId: wavetraceState.sessionId,
+            spfPath: state.currentSpfPath
+        });
+    } catch (error) {
+        console.error('Failed to check outputs:', error);
+        alert(`Failed to check outputs: ${error}`);
+        return;
+    }
+
+    const lines = [`Expected outputs of ${check.processors.join(', ')}: ${check.passed ? 'passed' : 'FAILED'}`];
+    for (const port of check.ports) {
+        const name = check.processors.length > 1 ? `${port.processor} ` : '';
+        lines.push('', `${name}out_sig_${port.port}: ${port.actualCount} written, ${port.expectedCount} expected`);
+        for (const error of port.errors) {
+            lines.push(`  ${error.file}:${error.line}: ${error.message}`);
+        }
+        const m = port.firstMismatch;
+        if (!m) continue;
+        lines.push(`  Output #${m.index + 1}: expected ${m.expected ?? 'nothing'}, got ${m.actual ?? 'nothing'}`);
+        if (m.time !== null) lines.push(`  At ${formatTimeWithUnit(m.time, false)}${m.cycle !== null ? `, cycle ${m.cycle}` : ''}`);
+        if (m.sourceLine !== null) lines.push(`  Line ${m.sourceLine}: ${(m.sourceText ?? '').trim()}`);
+    }
+    alert(lines.join('\n'));
+}
+
 function hideContextMenu() {
     const menu = document.getElementById('signalContextMenu');
     if (menu) {
@@ -1716,7 +2414,26 @@ function getSignalValueAtTime(signal, time) {
     return lastValue;
 }
 
-function formatTimeWithUnit(time) {
+// In cycles mode times are counted in periods of the detected clock,
+// starting from its first rising edge.
+function formatCycle(time) {
+    const { period, firstEdge } = wavetraceState.clock;
+    const cycle = (time - firstEdge) / period;
+    return `${Number.isInteger(cycle) ? cycle : cycle.toFixed(2)} cyc`;
+}
+
+function formatDuration(duration) {
+    if (wavetraceState.timeMode === 'cycles' && wavetraceState.clock) {
+        return `${(duration / wavetraceState.clock.period).toFixed(2)} cyc`;
+    }
+    return formatTimeWithUnit(duration, false);
+}
+
+function formatTimeWithUnit(time, inCycles = true) {
+    if (inCycles && wavetraceState.timeMode === 'cycles' && wavetraceState.clock) {
+        return formatCycle(time);
+    }
+
     const timescale = wavetraceState.vcdData.timescale.toLowerCase();
     
     let baseUnit = 'ns';
@@ -1777,6 +2494,8 @@ function renderWaveforms() {
         yOffset += wavetraceState.signalHeight;
     });
     
+    drawMarkers(container, canvasHeight);
+
     if (wavetraceState.cursorPosition !== null) {
         drawCursor(container, wavetraceState.cursorPosition, canvasHeight);
     }
@@ -1803,6 +2522,29 @@ function drawCursor(container, time, height) {
     }
 }
 
+function drawMarkers(container, height) {
+    const width = wavetraceState.app.view.width;
+    const graphics = new PIXI.Graphics();
+
+    wavetraceState.markers.forEach(({ start, end }) => {
+        const x1 = (start - wavetraceState.timeOffset) * wavetraceState.timeScale;
+        const x2 = (end - wavetraceState.timeOffset) * wavetraceState.timeScale;
+        if (x2 < 0 || x1 > width) return;
+
+        graphics.beginFill(wavetraceState.colors.marker, 0.08);
+        graphics.drawRect(Math.max(x1, 0), 0, Math.min(x2, width) - Math.max(x1, 0), height);
+        graphics.endFill();
+        if (x1 >= 0) {
+            graphics.lineStyle(1, wavetraceState.colors.marker, 0.7);
+            graphics.moveTo(x1, 0);
+            graphics.lineTo(x1, height);
+            graphics.lineStyle(0);
+        }
+    });
+
+    container.addChild(graphics);
+}
+
 function drawSignalBackground(container, yOffset, width, index, signal) {
     const graphics = new PIXI.Graphics();
     const isSelected = wavetraceState.selectedSignalId === signal.id;
@@ -1889,87 +2631,38 @@ function calculateTimeStep() {
     return niceStep * magnitude;
 }
 
-function formatVectorValue(binValue) {
-    if (binValue.includes('x') || binValue.includes('z') || binValue.includes('u')) return binValue;
-    
-    const nbm = parseInt(binValue.substring(0, 8), 2);
-    const nbe = parseInt(binValue.substring(8, 16), 2);
-    
-    const nbits = 1 + nbe + nbm;
-    if (binValue.length < 16 + 2 * nbits) return binValue;
-    
-    const partA = binValue.substring(16, 16 + nbits);
-    const partB = binValue.substring(16 + nbits, 16 + 2 * nbits);
-    
-    const parseComponent = (ifl) => {
-        const s = ifl[0] === '1';
-        let exb = ifl.substring(1, 1 + nbe);
-        const es = exb[0] === '1';
-        
-        if (es) {
-            let inverted = '';
-            for (let i = 0; i < exb.length; i++) inverted += exb[i] === '1' ? '0' : '1';
-            exb = inverted;
-        }
-        
-        let e = parseInt(exb, 2);
-        if (es) e = -(e + 1);
-        
-        const mab = ifl.substring(1 + nbe, 1 + nbe + nbm);
-        const m = parseInt(mab, 2);
-        
-        let f = m * Math.pow(2, e);
-        if (s) f = -f;
-        return f.toFixed(2);
-    };
-    
-    const valA = parseComponent(partA);
-    const valB = parseComponent(partB);
-    
-    return `[${valA}, ${valB}]`;
-}
-
-function formatComplexValue(binValue) {
-    const valLow = String(binValue).toLowerCase();
-    if (valLow.includes('x') || valLow.includes('z') || valLow.includes('u') || valLow.includes('w')) {
-        return binValue;
+// SAPHO vector, complex and float values are decoded exactly by the
+// backend, floats in the format of their processor. Until a value comes
+// back its raw bits are shown, then the canvas redraws.
+function formatSaphoValue(binValue, radix, format = null) {
+    const group = format ? `${radix}:${format.mantissaBits},${format.exponentBits}` : radix;
+    const key = `${group}:${binValue}`;
+    const decoded = wavetraceState.saphoValues.get(key);
+    if (decoded !== undefined) return decoded;
+
+    if (!wavetraceState.pendingSaphoValues.has(group)) {
+        wavetraceState.pendingSaphoValues.set(group, { radix, format, values: new Set() });
+        setTimeout(() => decodePendingSaphoValues(group), 0);
     }
+    wavetraceState.pendingSaphoValues.get(group).values.add(binValue);
+    return binValue;
+}
 
-    if (binValue.length < 16) return binValue;
-
-    const nbm = parseInt(binValue.substring(0, 8), 2);
-    const nbe = parseInt(binValue.substring(8, 16), 2);
-    const nbits = 1 + nbm + nbe;
-
-    if (binValue.length < 16 + 2 * nbits) return binValue;
-
-    const reStr = binValue.substring(16, 16 + nbits);
-    const imStr = binValue.substring(16 + nbits, 16 + 2 * nbits);
-
-    function b2mf(binStr, num_m, num_e) {
-        const s = binStr[0] === '1';
-        let exb = binStr.substring(1, 1 + num_e);
-        
-        const es = exb[0] === '1';
-        if (es) {
-            exb = exb.split('').map(b => b === '1' ? '0' : '1').join('');
-        }
-        
-        let e = parseInt(exb, 2);
-        if (es) e = -(e + 1);
-
-        const mab = binStr.substring(1 + num_e, 1 + num_e + num_m);
-        const m = parseInt(mab, 2);
+async function decodePendingSaphoValues(group) {
+    const pending = wavetraceState.pendingSaphoValues.get(group);
+    wavetraceState.pendingSaphoValues.delete(group);
+    if (!pending || pending.values.size === 0) return;
+    const { radix, format } = pending;
+    const values = [...pending.values];
 
-        let f = m * Math.pow(2, e);
-        if (s) f = -f;
-        return f;
+    try {
+        const decoded = await invoke('decode_sapho_values', { values, radix, format });
+        values.forEach((value, i) => wavetraceState.saphoValues.set(`${group}:${value}`, decoded[i]));
+    } catch (error) {
+        console.error('Failed to decode SAPHO values:', error);
+        values.forEach(value => wavetraceState.saphoValues.set(`${group}:${value}`, value));
     }
-
-    const fre = b2mf(reStr, nbm, nbe);
-    const fim = b2mf(imStr, nbm, nbe);
-
-    return `${fre.toFixed(2)} + j ${fim.toFixed(2)}`;
+    renderWaveforms();
 }
 
 function drawSignal(container, signal, yOffset, width) {
@@ -2001,13 +2694,15 @@ function drawSignal(container, signal, yOffset, width) {
     
     const gradientContainer = new PIXI.Graphics();
     
+    const { values, dense } = getSignalRenderData(signal, width);
+    
     let lastValue = 'x';
     let lastX = 0;
     
-    signal.values.forEach((change, index) => {
+    values.forEach((change, index) => {
         const x = (change.time - wavetraceState.timeOffset) * wavetraceState.timeScale;
         
-        if (x > -100 || (index < signal.values.length - 1)) {
+        if (x > -100 || (index < values.length - 1)) {
             drawWaveformSegment(graphics, gradientContainer, Math.max(-50, lastX), x, waveformY, waveformHeight, lastValue, signal.width, signalColor, signal, renderMode, change.value);
             
             if (x >= 0 && x <= width + 50 && lastValue !== change.value && renderMode !== 'analog') {
@@ -2025,10 +2720,37 @@ function drawSignal(container, signal, yOffset, width) {
         drawWaveformSegment(graphics, gradientContainer, Math.max(-50, lastX), width + 50, waveformY, waveformHeight, lastValue, signal.width, signalColor, signal, renderMode, null);
     }
     
+    dense.forEach(bucket => {
+        drawDenseBucket(graphics, bucket, waveformY, waveformHeight, signalColor, signal, renderMode);
+    });
+    
     container.addChild(gradientContainer);
     container.addChild(graphics);
 }
 
+// Pixel column with more transitions than can be drawn: min/max bar in analog mode, solid block otherwise
+function drawDenseBucket(graphics, bucket, y, height, color, signal, renderMode) {
+    const x1 = (bucket.start - wavetraceState.timeOffset) * wavetraceState.timeScale;
+    const x2 = Math.max(x1 + 1, (bucket.end - wavetraceState.timeOffset) * wavetraceState.timeScale);
+
+    if (renderMode === 'analog' && signal.width > 1 && bucket.min !== null && bucket.max !== null) {
+        const maxValue = Math.pow(2, signal.width) - 1;
+        const minY = y + height - (bucket.min / maxValue) * height;
+        const maxY = y + height - (bucket.max / maxValue) * height;
+
+        graphics.lineStyle(0);
+        graphics.beginFill(color, 0.9);
+        graphics.drawRect(x1, maxY, x2 - x1, Math.max(1, minY - maxY));
+        graphics.endFill();
+        return;
+    }
+
+    graphics.lineStyle(0);
+    graphics.beginFill(color, 0.55);
+    graphics.drawRect(x1, y, x2 - x1, height);
+    graphics.endFill();
+}
+
 function drawTextBusWaveform(graphics, gradientContainer, x1, x2, y, height, value, color, signal) {
     graphics.lineStyle(2, color, 0.95);
 
@@ -2098,8 +2820,8 @@ function drawWaveformSegment(graphics, gradientContainer, x1, x2, y, height, val
         }
     }
 
-    const signalName = (signal.name || '').toLowerCase();
-    if (signalName.includes('valr2') || signalName.includes('linetabs')) {
+    const kind = signal.class?.kind;
+    if (kind === 'instruction' || kind === 'sourceLine') {
         drawTextBusWaveform(graphics, gradientContainer, x1, x2, y, height, displayValue, renderColor, signal);
         return;
     }
@@ -2274,9 +2996,8 @@ function formatBusValue(value, signal) {
     if (value.includes('x') || value.includes('X')) return 'X';
     if (value.includes('z') || value.includes('Z')) return 'Z';
 
-    const lowerName = signal.name ? signal.name.toLowerCase() : '';
-    const isOpcode = lowerName.includes('assembly') || lowerName.includes('valr2');
-    const isCmm = lowerName.includes('c+-') || lowerName.includes('linetabs');
+    const isOpcode = signal.class?.kind === 'instruction';
+    const isCmm = signal.class?.kind === 'sourceLine';
 
     if (isOpcode || isCmm) {
         try {
@@ -2294,7 +3015,7 @@ function formatBusValue(value, signal) {
                 signedNum = -Number(BigInt('0b' + inverted) + 1n);
             }
 
-            const targetMap = isOpcode ? wavetraceState.opcodeMap : wavetraceState.cmmMap;
+            const targetMap = processorTable(isOpcode ? wavetraceState.opcodeMaps : wavetraceState.cmmMaps, signal);
             
             if (targetMap && targetMap.has(signedNum)) {
                 return targetMap.get(signedNum);
@@ -2303,6 +3024,14 @@ function formatBusValue(value, signal) {
         }
     }
 
+    const labels = wavetraceState.enumMaps.get(signal.path);
+    if (labels && /^[01]+$/.test(value)) {
+        const unsigned = BigInt('0b' + value);
+        const signed = value[0] === '1' ? unsigned - (1n << BigInt(value.length)) : unsigned;
+        const label = labels.get(unsigned) ?? labels.get(signed);
+        if (label !== undefined) return label;
+    }
+
     let radix = wavetraceState.signalRadix.get(signal.id);
     if (!radix) {
         radix = (signal.type === 'integer') ? 'decimal' : 'hex';
@@ -2343,11 +3072,15 @@ function formatBusValue(value, signal) {
         }
         case 'vector': {
             const paddedValue = value.padStart(signal.width, '0');
-            return formatVectorValue(paddedValue);
+            return formatSaphoValue(paddedValue, 'vector');
         }
         case 'complex': {
             const paddedValue = value.padStart(signal.width, '0');
-            return formatComplexValue(paddedValue);
+            return formatSaphoValue(paddedValue, 'complex');
+        }
+        case 'float': {
+            const paddedValue = value.padStart(signal.width, '0');
+            return formatSaphoValue(paddedValue, 'float', floatFormatOf(signal));
         }
         case 'binary': {
             return '0b' + value.padStart(signal.width, '0');
@@ -2392,6 +3125,8 @@ export function closeWavetraceViewer() {
         container.innerHTML = '';
     }
     
+    closeWaveformSession();
+
     wavetraceState.active = false;
     wavetraceState.filePath = null;
     wavetraceState.fileName = null;
@@ -2402,6 +3137,9 @@ export function closeWavetraceViewer() {
     wavetraceState.signalRadix.clear();
     wavetraceState.signalRenderMode.clear();
     wavetraceState.cursorPosition = null;
+    wavetraceState.markers = [];
+    wavetraceState.clock = null;
+    wavetraceState.timeMode = 'time';
     wavetraceState.selectedSignalId = null;
     wavetraceState.canvasScrollY = 0;
 }